@@ -15,10 +15,12 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
+
 use criterion::{criterion_group, criterion_main, Criterion};
 use starlark::{
-    environment::{Globals, Module},
-    eval::Evaluator,
+    environment::{FrozenModule, Globals, Module},
+    eval::{Evaluator, ReturnFileLoader},
     syntax::{AstModule, Dialect},
 };
 
@@ -81,6 +83,38 @@ pub fn criterion_parsing_benchmark(c: &mut Criterion) {
     });
 }
 
+// The first comprehension knows its length up front, the filter in the second one
+// hides it, so comparing them shows the effect of pre-sizing the list.
+const LIST_COMPREHENSION: &str = r#"
+def bench():
+    return len([x * 2 for x in range(1000000)])
+bench
+"#;
+
+const LIST_COMPREHENSION_FILTERED: &str = r#"
+def bench():
+    return len([x * 2 for x in range(1000000) if x >= 0])
+bench
+"#;
+
+// Calls the same method on values of the same type, resolved at the same call site.
+const METHOD_CALL_LOOP: &str = r#"
+def bench():
+    s = "abc"
+    n = 0
+    for _ in range(100000):
+        n += s.count("b")
+    return n
+bench
+"#;
+
+const STRING_JOIN: &str = r#"
+xs = [str(x) for x in range(100000)]
+def bench():
+    return len(",".join(xs))
+bench
+"#;
+
 pub fn criterion_eval_benchmark(c: &mut Criterion, globals: &Globals) {
     c.bench_function("run_tight_loop", |b| {
         let env = Module::new();
@@ -90,6 +124,173 @@ pub fn criterion_eval_benchmark(c: &mut Criterion, globals: &Globals) {
         let bench_function = eval.eval_module(ast, globals).unwrap();
         b.iter(move || eval.eval_function(bench_function, &[], &[]).unwrap())
     });
+    for (name, code) in [
+        ("list_comprehension_sized", LIST_COMPREHENSION),
+        ("list_comprehension_filtered", LIST_COMPREHENSION_FILTERED),
+        ("method_call_loop", METHOD_CALL_LOOP),
+        ("string_join", STRING_JOIN),
+    ] {
+        c.bench_function(name, |b| {
+            let env = Module::new();
+            let mut eval = Evaluator::new(&env);
+            let ast =
+                AstModule::parse("benchmark.sky", code.to_owned(), &Dialect::Standard).unwrap();
+            let bench_function = eval.eval_module(ast, globals).unwrap();
+            b.iter(move || eval.eval_function(bench_function, &[], &[]).unwrap())
+        });
+    }
+}
+
+const TUPLE_GRID: &str = r#"
+grid = [{(x, y): i for x in range(30) for y in range(30)} for i in range(30)]
+"#;
+
+fn benchmark_freeze(globals: &Globals, code: &str, interning: bool) -> FrozenModule {
+    let env = Module::new();
+    {
+        let mut eval = Evaluator::new(&env);
+        let ast = AstModule::parse("benchmark.sky", code.to_owned(), &Dialect::Standard).unwrap();
+        eval.eval_module(ast, globals).unwrap();
+    }
+    if interning {
+        env.freeze_with_interning().unwrap()
+    } else {
+        env.freeze().unwrap()
+    }
+}
+
+const TUPLE_GRID_LOOKUP: &str = r#"
+load("grid.star", "grid")
+def bench():
+    n = 0
+    for d in grid:
+        for x in range(30):
+            n += d[(x, x)]
+    return n
+bench
+"#;
+
+pub fn criterion_freeze_benchmark(c: &mut Criterion, globals: &Globals) {
+    for interning in [false, true] {
+        let name = if interning { "interned" } else { "plain" };
+        c.bench_function(&format!("freeze_tuple_grid_{}", name), |b| {
+            b.iter(|| benchmark_freeze(globals, TUPLE_GRID, interning))
+        });
+        let grid = benchmark_freeze(globals, TUPLE_GRID, interning);
+        c.bench_function(&format!("lookup_tuple_grid_{}", name), |b| {
+            let env = Module::new();
+            let mut eval = Evaluator::new(&env);
+            let mut modules = HashMap::new();
+            modules.insert("grid.star", &grid);
+            let loader = ReturnFileLoader { modules: &modules };
+            eval.set_loader(&loader);
+            let ast = AstModule::parse(
+                "benchmark.sky",
+                TUPLE_GRID_LOOKUP.to_owned(),
+                &Dialect::Standard,
+            )
+            .unwrap();
+            let bench_function = eval.eval_module(ast, globals).unwrap();
+            b.iter(move || eval.eval_function(bench_function, &[], &[]).unwrap())
+        });
+    }
+}
+
+const FROZEN_KEYS: &str = r#"
+keys = [(x, str(y)) for x in range(30) for y in range(30)]
+d = {k: 1 for k in keys}
+"#;
+
+const FROZEN_KEYS_LOOKUP: &str = r#"
+load("keys.star", "keys", "d")
+def bench():
+    n = 0
+    for k in keys:
+        n += d[k]
+    return n
+bench
+"#;
+
+/// Dict lookups keyed by frozen tuples, which hash them on every lookup.
+pub fn criterion_frozen_key_benchmark(c: &mut Criterion, globals: &Globals) {
+    let keys = benchmark_freeze(globals, FROZEN_KEYS, false);
+    c.bench_function("lookup_frozen_tuple_keys", |b| {
+        let env = Module::new();
+        let mut eval = Evaluator::new(&env);
+        let mut modules = HashMap::new();
+        modules.insert("keys.star", &keys);
+        let loader = ReturnFileLoader { modules: &modules };
+        eval.set_loader(&loader);
+        let ast = AstModule::parse(
+            "benchmark.sky",
+            FROZEN_KEYS_LOOKUP.to_owned(),
+            &Dialect::Standard,
+        )
+        .unwrap();
+        let bench_function = eval.eval_module(ast, globals).unwrap();
+        b.iter(move || eval.eval_function(bench_function, &[], &[]).unwrap())
+    });
+}
+
+fn dict_lookup_code(size: usize) -> String {
+    format!(
+        r#"
+d = {{x: x for x in range({size})}}
+def bench():
+    n = 0
+    for _ in range({repeat}):
+        for x in range({size}):
+            n += d[x]
+    return n
+bench
+"#,
+        size = size,
+        repeat = 10000 / size
+    )
+}
+
+/// The same number of lookups in a dict small enough to be scanned linearly,
+/// and in a 10k-entry dict, which is looked up by its hash index.
+pub fn criterion_dict_lookup_benchmark(c: &mut Criterion, globals: &Globals) {
+    for size in [12, 10000] {
+        c.bench_function(&format!("dict_lookup_{}", size), |b| {
+            let env = Module::new();
+            let mut eval = Evaluator::new(&env);
+            let ast = AstModule::parse("benchmark.sky", dict_lookup_code(size), &Dialect::Standard)
+                .unwrap();
+            let bench_function = eval.eval_module(ast, globals).unwrap();
+            b.iter(move || eval.eval_function(bench_function, &[], &[]).unwrap())
+        });
+    }
+}
+
+const MANY_STRINGS: &str = r#"
+def bench():
+    return len([str(x) for x in range(100000)])
+bench()
+"#;
+
+fn benchmark_run_in(env: Module, globals: &Globals, code: &str) -> Module {
+    {
+        let mut eval = Evaluator::new(&env);
+        let ast = AstModule::parse("benchmark.sky", code.to_owned(), &Dialect::Standard).unwrap();
+        eval.eval_module(ast, globals).unwrap();
+    }
+    env
+}
+
+/// Allocating on a heap reserved up front, with the size measured in a previous run,
+/// compared to a heap that grows as needed.
+pub fn criterion_heap_capacity_benchmark(c: &mut Criterion, globals: &Globals) {
+    let bytes = benchmark_run_in(Module::new(), globals, MANY_STRINGS)
+        .heap()
+        .allocated_bytes();
+    c.bench_function("many_strings", |b| {
+        b.iter(|| benchmark_run_in(Module::new(), globals, MANY_STRINGS))
+    });
+    c.bench_function("many_strings_heap_capacity", |b| {
+        b.iter(|| benchmark_run_in(Module::with_heap_capacity(bytes), globals, MANY_STRINGS))
+    });
 }
 
 pub fn criterion_benchmark(c: &mut Criterion) {
@@ -97,6 +298,10 @@ pub fn criterion_benchmark(c: &mut Criterion) {
     criterion_general_benchmark(c, &g);
     criterion_parsing_benchmark(c);
     criterion_eval_benchmark(c, &g);
+    criterion_freeze_benchmark(c, &g);
+    criterion_frozen_key_benchmark(c, &g);
+    criterion_heap_capacity_benchmark(c, &g);
+    criterion_dict_lookup_benchmark(c, &g);
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -158,6 +158,10 @@ fn stmt(x: &AstStmt, res: &mut Vec<Bind>) {
         }
         Stmt::Break | Stmt::Continue | Stmt::Return(None) => flow(res),
         Stmt::Pass => {}
+        Stmt::Assert(cond, message) => {
+            expr(cond, res);
+            opt_expr(message.as_deref(), res);
+        }
         Stmt::Return(Some(x)) => {
             expr(x, res);
             flow(res)
@@ -199,12 +203,16 @@ fn stmt(x: &AstStmt, res: &mut Vec<Bind>) {
             expr(rhs, res);
             expr_lvalue(lhs, res);
         }
-        Stmt::For(dest, box (inner, body)) => {
+        Stmt::For(dest, box (inner, body, else_block)) => {
             expr(inner, res);
             expr_lvalue(dest, res);
             flow(res);
             stmt(body, res);
-            flow(res)
+            flow(res);
+            if let Some(else_block) = else_block {
+                stmt(else_block, res);
+                flow(res);
+            }
         }
         Stmt::Load(load) => {
             for x in &load.node.args {
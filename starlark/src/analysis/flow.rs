@@ -78,6 +78,21 @@ fn is_fail(x: &AstExpr) -> bool {
     }
 }
 
+// Does the loop body break out of the loop, so the `else` block of the loop may not run
+fn breaks(x: &AstStmt) -> bool {
+    match &**x {
+        Stmt::Break => true,
+        Stmt::Def(..) => false,
+        // A break in a nested loop body is for that loop, but one in its `else` block is for us
+        Stmt::For(_, box (_, _, else_block)) => else_block.as_ref().map_or(false, breaks),
+        _ => {
+            let mut res = false;
+            x.visit_stmt(|x| res = res || breaks(x));
+            res
+        }
+    }
+}
+
 fn final_return(x: &AstStmt) -> bool {
     match &**x {
         Stmt::Return(_) => true,
@@ -87,6 +102,7 @@ fn final_return(x: &AstStmt) -> bool {
             Some(x) => final_return(x),
         },
         Stmt::IfElse(_, box (x, y)) => final_return(x) && final_return(y),
+        Stmt::For(_, box (_, body, Some(else_block))) => final_return(else_block) && !breaks(body),
         _ => false,
     }
 }
@@ -174,6 +190,12 @@ fn reachable(codemap: &CodeMap, x: &AstStmt, res: &mut Vec<LintT<FlowIssue>>) ->
             let abort2 = reachable(codemap, y, res);
             abort1 && abort2
         }
+        // Unless the loop body breaks, the `else` block always runs after the loop
+        Stmt::For(_, box (_, body, Some(else_block))) => {
+            reachable(codemap, body, res);
+            let abort = reachable(codemap, else_block, res);
+            abort && !breaks(body)
+        }
         // For all remaining constructs, visit their children to accumulate errors,
         // but even if they are present with returns, you don't guarantee the code with inner returns
         // gets executed.
@@ -205,13 +227,15 @@ fn redundant(codemap: &CodeMap, x: &AstStmt, res: &mut Vec<LintT<FlowIssue>>) {
                 check(is_loop, codemap, x, res);
                 check(is_loop, codemap, y, res);
             }
+            // The end of the `else` block of a loop is the end of the loop
+            Stmt::For(_, box (_, _, Some(x))) => check(is_loop, codemap, x, res),
             _ => {}
         }
     }
 
     fn f(codemap: &CodeMap, x: &AstStmt, res: &mut Vec<LintT<FlowIssue>>) {
         match &**x {
-            Stmt::For(_, box (_, body)) => check(true, codemap, body, res),
+            Stmt::For(_, box (_, body, _)) => check(true, codemap, body, res),
             Stmt::Def(_, _, _, body, _payload) => check(false, codemap, body, res),
             _ => {}
         }
@@ -320,13 +344,24 @@ def yes3():
     pass
 def yes4() -> "string":
     fail("die")
+def yes5() -> "string":
+    for x in xs:
+        pass
+    else:
+        return "x"
+def no5() -> "string":
+    for x in xs:
+        if x:
+            break
+    else:
+        return "x"
 "#,
         );
         let mut res = Vec::new();
         stmt(&m.codemap, &m.statement, &mut res);
         assert_eq!(
             res.map(|x| x.problem.about()),
-            &["no1", "no2", "no3", "no4"]
+            &["no1", "no2", "no3", "no4", "no5"]
         );
     }
 
@@ -367,13 +402,36 @@ def f():
     def g():
         return 5
     reachable
+def test7():
+    for x in xs:
+        pass
+    else:
+        return
+        no5
+    no6
+def test8():
+    for x in xs:
+        if x:
+            break
+    else:
+        return
+    reachable
+def test9():
+    for x in xs:
+        for y in ys:
+            pass
+        else:
+            break
+    else:
+        return
+    reachable
 "#,
         );
         let mut res = Vec::new();
         reachable(&m.codemap, &m.statement, &mut res);
         assert_eq!(
             res.map(|x| x.problem.about()),
-            &["no1", "no2", "no3", "no4"]
+            &["no1", "no2", "no3", "no4", "no5", "no6"]
         );
     }
 
@@ -410,13 +468,21 @@ def test7():
         if x:
             continue
         return
+def test8(): # 30
+    for x in xs:
+        for y in ys:
+            pass
+        else:
+            continue # bad: 35
+    else:
+        return # bad: 37
 "#,
         );
         let mut res = Vec::new();
         redundant(&m.codemap, &m.statement, &mut res);
         assert_eq!(
             res.map(|x| x.location.resolve_span().begin_line),
-            &[3, 9, 19]
+            &[3, 9, 19, 35, 37]
         );
     }
 
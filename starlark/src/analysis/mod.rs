@@ -26,7 +26,7 @@ mod flow;
 mod incompatible;
 mod names;
 mod performance;
-mod types;
+pub(crate) mod types;
 
 impl AstModule {
     /// Run a static linter over the module. If the complete set of global variables are known
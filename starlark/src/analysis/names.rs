@@ -338,6 +338,51 @@ def foo():
         assert_eq!(res, &["_no2", "_no4", "_no6", "no1", "no3", "no5"]);
     }
 
+    #[test]
+    fn test_lint_unused_public() {
+        let m = module(
+            r#"
+load("test", "used", "unused")
+def f(x):
+    y = used
+    _z = 1
+    return x
+"#,
+        );
+        let mut res = m
+            .lint(None)
+            .into_iter()
+            .filter(|x| x.short_name.starts_with("unused-"))
+            .map(|x| {
+                (
+                    x.short_name,
+                    x.problem,
+                    x.original,
+                    x.location.resolve_span().begin_line,
+                )
+            })
+            .collect::<Vec<_>>();
+        res.sort();
+        // Underscore-prefixed locals are exempt.
+        assert_eq!(
+            res,
+            &[
+                (
+                    "unused-assign".to_owned(),
+                    "Unused assignment of `y`".to_owned(),
+                    "y".to_owned(),
+                    3
+                ),
+                (
+                    "unused-load".to_owned(),
+                    "Unused `load` of `unused`".to_owned(),
+                    "\"unused\"".to_owned(),
+                    1
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn test_lint_duplicate_assign() {
         let m = module(
@@ -30,10 +30,11 @@ use std::{
 };
 
 use gazebo::prelude::*;
+use serde::Serialize;
 
 /// A small, `Copy`, value representing a position in a `CodeMap`'s file.
 #[derive(
-    Copy, Clone, Dupe, Hash, Eq, PartialEq, Ord, PartialOrd, Debug, Default
+    Copy, Clone, Dupe, Hash, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Serialize
 )]
 pub struct Pos(u32);
 
@@ -55,7 +56,7 @@ impl Add<u32> for Pos {
 }
 
 /// A range of text within a CodeMap.
-#[derive(Copy, Dupe, Clone, Hash, Eq, PartialEq, Debug, Default)]
+#[derive(Copy, Dupe, Clone, Hash, Eq, PartialEq, Debug, Default, Serialize)]
 pub struct Span {
     /// The position in the codemap representing the first byte of the span.
     begin: Pos,
@@ -113,7 +114,7 @@ impl Span {
 }
 
 /// Associate a Span with a value of arbitrary type (e.g. an AST node).
-#[derive(Clone, PartialEq, Eq, Hash, Debug, Copy)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Copy, Serialize)]
 pub struct Spanned<T> {
     pub node: T,
     pub span: Span,
@@ -202,7 +203,7 @@ impl CodeMap {
         &self.0
     }
 
-    fn full_span(&self) -> Span {
+    pub(crate) fn full_span(&self) -> Span {
         Span {
             begin: Pos(0),
             end: Pos(self.0.source.len() as u32),
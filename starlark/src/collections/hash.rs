@@ -24,6 +24,12 @@ use crate as starlark;
 use crate::collections::StarlarkHasher;
 
 /// A hash result.
+///
+/// [`SmallMap`](crate::collections::SmallMap) and the other collections compare keys by this
+/// value, so an external index over the same keys must compute it with
+/// [`SmallHashResult::new`] (or [`Hashed::new`], which is equivalent).
+/// The hash is deterministic, but the algorithm is not part of the stable API:
+/// it may change between versions of this crate, so hashes must not be persisted.
 #[derive(Hash, Eq, PartialEq, Clone, Copy, Dupe, Debug, Default, Trace)]
 // Hash value must be well swizzled.
 pub struct SmallHashResult(u32);
@@ -75,7 +81,8 @@ impl SmallHashResult {
         SmallHashResult(h as u32)
     }
 
-    pub(crate) fn get(self) -> u32 {
+    /// Get the underlying hash value.
+    pub fn get(self) -> u32 {
         self.0
     }
 }
@@ -189,7 +196,7 @@ impl<K> Hashed<K> {
 mod tests {
     use indexmap::map::IndexMap;
 
-    use crate::collections::{BorrowHashed, Hashed};
+    use crate::collections::{BorrowHashed, Hashed, SmallHashResult};
 
     #[test]
     fn borrow_and_hashed_equivalent() {
@@ -198,4 +205,14 @@ mod tests {
 
         assert_eq!(m.get(&BorrowHashed::new(&1)), Some(&'b'));
     }
+
+    #[test]
+    fn small_hash_result_matches_hashed() {
+        for key in ["", "x", "hello world"] {
+            let hash = SmallHashResult::new(key);
+            assert_eq!(hash, Hashed::new(key).hash());
+            assert_eq!(hash, BorrowHashed::new(key).hash());
+            assert_eq!(hash.get(), Hashed::new(key).hash().get());
+        }
+    }
 }
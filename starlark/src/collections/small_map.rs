@@ -23,7 +23,7 @@
 use std::{
     cmp::Ordering,
     fmt::{self, Debug},
-    hash::{Hash, Hasher},
+    hash::{BuildHasher, Hash, Hasher},
     iter::FromIterator,
     mem,
 };
@@ -33,7 +33,7 @@ use gazebo::{
     prelude::*,
 };
 use hashbrown::raw::RawTable;
-use indexmap::Equivalent;
+use indexmap::{Equivalent, IndexMap};
 
 use crate::collections::{
     hash::{BorrowHashed, Hashed},
@@ -43,6 +43,9 @@ use crate::collections::{
 };
 
 /// Max size of map when we do not create index.
+///
+/// Lookups in a map without an index are a linear scan of `entries`,
+/// so the map is promoted to a hashed index as soon as it grows past this size.
 // TODO: benchmark, is this the right threshold
 const NO_INDEX_THRESHOLD: usize = 12;
 
@@ -202,7 +205,11 @@ impl<K, V> SmallMap<K, V> {
         K: Eq,
     {
         match &self.index {
-            None => self.entries.get_index_of_hashed(key),
+            None => {
+                // Linear scan is only acceptable for small maps, larger maps must have an index.
+                debug_assert!(self.entries.len() <= NO_INDEX_THRESHOLD);
+                self.entries.get_index_of_hashed(key)
+            }
             Some(index) => index
                 .get(mix_u32(key.hash().get()), |&index| unsafe {
                     key.key().equivalent(&self.entries.get_unchecked(index).key)
@@ -327,6 +334,23 @@ impl<K, V> SmallMap<K, V> {
         }
     }
 
+    /// Append all the entries without checking for duplicate keys, e.g. to build
+    /// a map from data whose keys are known to be unique without comparing them.
+    ///
+    /// The caller must guarantee that the keys are unique, both among themselves and
+    /// with respect to the keys already in the map. This is not checked: violating it is
+    /// not undefined behaviour, but lookups may find any of the duplicates.
+    pub fn extend_unique(&mut self, iter: impl IntoIterator<Item = (Hashed<K>, V)>)
+    where
+        K: Eq,
+    {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for (k, v) in iter {
+            self.insert_unique_unchecked(k, v);
+        }
+    }
+
     pub fn insert_hashed(&mut self, key: Hashed<K>, val: V) -> Option<V>
     where
         K: Eq,
@@ -448,6 +472,33 @@ impl<K, V> SmallMap<K, V> {
             index.clear();
         }
     }
+
+    /// Create from an [`IndexMap`], keeping its order, e.g. to pass a map built by
+    /// host code to Starlark. The keys of an [`IndexMap`] are unique, so each key is
+    /// hashed once and never compared.
+    pub fn from_index_map<S>(map: IndexMap<K, V, S>) -> Self
+    where
+        K: Hash,
+    {
+        let mut res = SmallMap {
+            entries: VecMap::from_index_map(map),
+            index: None,
+        };
+        if res.entries.len() > NO_INDEX_THRESHOLD {
+            res.create_index(res.entries.len());
+        }
+        res
+    }
+
+    /// Copy into an [`IndexMap`], keeping the order.
+    pub fn to_index_map<S>(&self) -> IndexMap<K, V, S>
+    where
+        K: Hash + Eq + Clone,
+        V: Clone,
+        S: BuildHasher + Default,
+    {
+        self.entries.to_index_map()
+    }
 }
 
 pub struct OccupiedEntry<'a, K, V> {
@@ -800,4 +851,73 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_index_map_round_trip() {
+        for n in [3, 100] {
+            // Keys out of order, to check the order is kept rather than sorted.
+            let im: IndexMap<String, usize> = (0..n).rev().map(|i| (i.to_string(), i)).collect();
+            let map = SmallMap::from_index_map(im.clone());
+            assert_eq!(n, map.len());
+            assert!(map.iter().eq(im.iter()));
+            for i in 0..n {
+                assert_eq!(Some(&i), map.get(&i.to_string()));
+            }
+            assert_eq!(None, map.get("missing"));
+
+            let back: IndexMap<String, usize> = map.to_index_map();
+            assert!(back.iter().eq(im.iter()));
+        }
+    }
+
+    #[test]
+    fn test_from_iter_hashed_last_wins() {
+        // Like a dict literal: a later entry for an equivalent key replaces the value,
+        // but the entry keeps the position where the key was first seen.
+        let mp: SmallMap<&str, i32> = vec![("a", 1), ("b", 2), ("a", 3), ("c", 4), ("b", 5)]
+            .into_iter()
+            .map(|(k, v)| (Hashed::new(k), v))
+            .collect();
+        assert_eq!(
+            vec![(&"a", &3), (&"b", &5), (&"c", &4)],
+            mp.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_extend_unique() {
+        for n in [4, 100] {
+            let mut mp = SmallMap::new();
+            mp.insert(0, "zero");
+            mp.extend_unique((1..n).map(|i| (Hashed::new(i), "many")));
+            assert_eq!(n, mp.len());
+            assert!(mp.keys().copied().eq(0..n));
+            // The index, if any, is kept in sync.
+            assert_eq!(n > NO_INDEX_THRESHOLD, mp.index.is_some());
+            for i in 1..n {
+                assert_eq!(Some((i, &i, &"many")), mp.get_full(&i));
+            }
+            assert_eq!(None, mp.get(&n));
+        }
+    }
+
+    #[test]
+    fn test_promote_to_index() {
+        let mut map = SmallMap::new();
+        for i in 0..=NO_INDEX_THRESHOLD {
+            assert!(map.index.is_none());
+            map.insert(i, i);
+        }
+        // The map has grown past the threshold, so lookups are no longer linear.
+        assert!(map.index.is_some());
+        for i in 0..=NO_INDEX_THRESHOLD {
+            assert_eq!(Some(&i), map.get(&i));
+        }
+
+        map.remove(&0);
+        map.maybe_drop_index();
+        assert!(map.index.is_none());
+        assert_eq!(None, map.get(&0));
+        assert_eq!(Some(&1), map.get(&1));
+    }
 }
@@ -15,10 +15,13 @@
  * limitations under the License.
  */
 
-use std::mem;
+use std::{
+    hash::{BuildHasher, Hash},
+    mem,
+};
 
 use gazebo::prelude::*;
-use indexmap::Equivalent;
+use indexmap::{Equivalent, IndexMap};
 
 use crate::collections::hash::{BorrowHashed, Hashed, SmallHashResult};
 
@@ -342,6 +345,30 @@ impl<K, V> VecMap<K, V> {
         });
     }
 
+    /// Create from an [`IndexMap`], keeping its order. The keys of an [`IndexMap`] are
+    /// unique, so they are not compared, but they are hashed again, since [`IndexMap`]
+    /// doesn't expose its hashes, which may come from a different hasher anyway.
+    pub fn from_index_map<S>(map: IndexMap<K, V, S>) -> Self
+    where
+        K: Hash,
+    {
+        let mut res = Self::with_capacity(map.len());
+        for (k, v) in map {
+            res.insert_unique_unchecked(Hashed::new(k), v);
+        }
+        res
+    }
+
+    /// Copy into an [`IndexMap`], keeping the order.
+    pub fn to_index_map<S>(&self) -> IndexMap<K, V, S>
+    where
+        K: Hash + Eq + Clone,
+        V: Clone,
+        S: BuildHasher + Default,
+    {
+        self.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+
     pub fn remove_hashed_entry<Q>(&mut self, key: BorrowHashed<Q>) -> Option<(K, V)>
     where
         Q: ?Sized + Equivalent<K>,
@@ -422,3 +449,27 @@ impl<K, V> VecMap<K, V> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_map_round_trip() {
+        let mut im = IndexMap::<String, i32>::new();
+        for k in ["z", "a", "m", "b"] {
+            im.insert(k.to_owned(), k.len() as i32 * 10);
+        }
+        im.insert("a".to_owned(), 1);
+
+        let mp = VecMap::from_index_map(im.clone());
+        assert_eq!(
+            vec![("z", 10), ("a", 1), ("m", 10), ("b", 10)],
+            mp.iter().map(|(k, v)| (k.as_str(), *v)).collect::<Vec<_>>()
+        );
+        assert_eq!(Some(2), mp.get_index_of_hashed(BorrowHashed::new("m")));
+
+        let back: IndexMap<String, i32> = mp.to_index_map();
+        assert!(back.iter().eq(im.iter()));
+    }
+}
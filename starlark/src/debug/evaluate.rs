@@ -17,7 +17,23 @@
 
 use std::{collections::HashMap, mem};
 
-use crate::{debug::inspect::to_scope_names, eval::Evaluator, syntax::AstModule, values::Value};
+use thiserror::Error;
+
+use crate::{
+    debug::inspect::to_scope_names,
+    eval::Evaluator,
+    syntax::{
+        ast::{AstStmt, Stmt},
+        AstModule, Dialect,
+    },
+    values::Value,
+};
+
+#[derive(Error, Debug)]
+enum EvaluateError {
+    #[error("Expected an expression, got a statement")]
+    NotExpression,
+}
 
 impl<'v, 'a> Evaluator<'v, 'a> {
     /// Evaluate statements in the existing context. This function is designed for debugging,
@@ -41,7 +57,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         // everything before, shove the local variables into the module, and then revert after
         let original_module: HashMap<String, Option<Value<'v>>> = self
             .module_env
-            .names()
+            .mutable_names()
             .all_names()
             .iter()
             .map(|(name, slot)| (name.clone(), self.module_env.slots().get_slot(*slot)))
@@ -84,9 +100,9 @@ impl<'v, 'a> Evaluator<'v, 'a> {
                     self.local_variables.set_slot(*slot, value)
                 }
             }
-            for (name, slot) in self.module_env.names().all_names() {
+            for (name, slot) in self.module_env.mutable_names().all_names() {
                 match original_module.get(&name) {
-                    None => self.module_env.names().hide_name(&name),
+                    None => self.module_env.mutable_names().hide_name(&name),
                     Some(Some(value)) => self.module_env.slots().set_slot(slot, *value),
                     _ => {} // No way to unassign a previously assigned value yet
                 }
@@ -95,6 +111,30 @@ impl<'v, 'a> Evaluator<'v, 'a> {
 
         res
     }
+
+    /// Evaluate a single expression in the existing context, for example a watch expression
+    /// in a REPL or a debugger. While evaluating Starlark code, the expression sees the local
+    /// variables of the innermost function being evaluated, the module variables and the
+    /// globals. After [`eval_module`](Evaluator::eval_module) returns, only the module
+    /// variables are visible.
+    ///
+    /// Returns an error if `src` fails to parse or is not an expression.
+    /// Has the same health warnings as [`eval_statements`](Evaluator::eval_statements).
+    pub fn eval_expr(&mut self, src: &str) -> anyhow::Result<Value<'v>> {
+        fn is_expression(stmt: &AstStmt) -> bool {
+            match &stmt.node {
+                Stmt::Expression(_) => true,
+                Stmt::Statements(xs) => matches!(xs.as_slice(), [x] if is_expression(x)),
+                _ => false,
+            }
+        }
+
+        let ast = AstModule::parse("interactive", src.to_owned(), &Dialect::Extended)?;
+        if !is_expression(&ast.statement) {
+            return Err(EvaluateError::NotExpression.into());
+        }
+        self.eval_statements(ast)
+    }
 }
 
 #[cfg(test)]
@@ -102,7 +142,10 @@ mod tests {
     use itertools::Itertools;
 
     use super::*;
-    use crate::{self as starlark, assert, environment::GlobalsBuilder, syntax::Dialect};
+    use crate::{
+        self as starlark, assert,
+        environment::{Globals, GlobalsBuilder, Module},
+    };
 
     #[starlark_module]
     fn debugger(builder: &mut GlobalsBuilder) {
@@ -110,6 +153,10 @@ mod tests {
             let ast = AstModule::parse("interactive", code, &Dialect::Extended)?;
             eval.eval_statements(ast)
         }
+
+        fn debug_eval_expr(code: &str) -> Value<'v> {
+            eval.eval_expr(code)
+        }
     }
 
     #[test]
@@ -170,4 +217,39 @@ def bar(y):
         );
         a.pass("load('test', 'bar'); assert_eq(bar(4), 4 + 7 + 2)");
     }
+
+    #[test]
+    fn test_eval_expr() {
+        let module = Module::new();
+        let globals = Globals::standard();
+        let mut eval = Evaluator::new(&module);
+        let ast = AstModule::parse("x.star", "x = 5".to_owned(), &Dialect::Standard).unwrap();
+        eval.eval_module(ast, &globals).unwrap();
+
+        assert_eq!(Some(6), eval.eval_expr("x + 1").unwrap().unpack_int());
+
+        let err = eval.eval_expr("x +").unwrap_err();
+        assert!(err.to_string().contains("Parse error"), "{}", err);
+        let err = eval.eval_expr("y = x").unwrap_err();
+        assert!(
+            err.to_string().contains("Expected an expression"),
+            "{}",
+            err
+        );
+        // The failed statement was not evaluated.
+        assert!(module.get("y").is_none());
+
+        // Inside a function the expression sees the locals and the globals.
+        let mut a = assert::Assert::new();
+        a.globals_add(debugger);
+        a.pass(
+            r#"
+x = 7
+def bar(y):
+    return debug_eval_expr("len([x, y]) + x + y")
+assert_eq(bar(4), 2 + 7 + 4)
+"#,
+        );
+        a.fail("debug_eval_expr('z = 1')", "Expected an expression");
+    }
 }
@@ -33,6 +33,11 @@ impl<'v, 'a> Evaluator<'v, 'a> {
     /// Obtain the local variables currently in scope. When at top-level these will be
     /// [`Module`](crate::environment::Module) variables, otherwise local definitions. The precise number of variables
     /// may change over time due to optimisation. The only legitimate use of this function is for debugging.
+    ///
+    /// The variables are keyed by their names in the source code, and always reflect the innermost
+    /// active Starlark function frame, so calling this from a native function or a
+    /// [`before_stmt`](Evaluator::before_stmt) callback reports the locals of the Starlark caller.
+    /// Variables which have not been assigned yet are omitted.
     pub fn local_variables(&self) -> SmallMap<String, Value<'v>> {
         inspect_local_variables(self).unwrap_or_else(|| inspect_module_variables(self))
     }
@@ -53,7 +58,7 @@ fn inspect_local_variables<'v>(eval: &Evaluator<'v, '_>) -> Option<SmallMap<Stri
 
 fn inspect_module_variables<'v>(eval: &Evaluator<'v, '_>) -> SmallMap<String, Value<'v>> {
     let mut res = SmallMap::new();
-    for (name, slot) in eval.module_env.names().all_names() {
+    for (name, slot) in eval.module_env.mutable_names().all_names() {
         if let Some(v) = eval.module_env.slots().get_slot(slot) {
             res.insert(name, v);
         }
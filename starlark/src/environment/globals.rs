@@ -15,7 +15,11 @@
  * limitations under the License.
  */
 
-use std::{mem, sync::Arc};
+use std::{
+    fmt::{self, Debug},
+    mem,
+    sync::Arc,
+};
 
 use derive_more::Display;
 use gazebo::prelude::*;
@@ -28,10 +32,12 @@ use crate::{
         symbol_map::{Symbol, SymbolMap},
         SmallMap,
     },
+    environment::EnvironmentError,
     stdlib,
     values::{
-        function::NativeAttribute, structs::FrozenStruct, AllocFrozenValue, FrozenHeap,
-        FrozenHeapRef, FrozenStringValue, FrozenValue, Value,
+        function::{NativeAttribute, NativeFunction, NativeMethod},
+        structs::FrozenStruct,
+        AllocFrozenValue, FrozenHeap, FrozenHeapRef, FrozenStringValue, FrozenValue, Heap, Value,
     },
 };
 
@@ -49,6 +55,17 @@ pub struct Methods(Globals);
 struct GlobalsData {
     heap: FrozenHeapRef,
     variables: SymbolMap<FrozenValue>,
+    dynamic_resolver: Option<DynamicResolver>,
+}
+
+/// Function to produce globals which are not set in [`GlobalsBuilder`],
+/// see [`GlobalsBuilder::set_dynamic_resolver`].
+struct DynamicResolver(Box<dyn Fn(&str, &FrozenHeap) -> Option<FrozenValue> + Send + Sync>);
+
+impl Debug for DynamicResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DynamicResolver")
+    }
 }
 
 /// Used to build a [`Globals`] value.
@@ -60,6 +77,8 @@ pub struct GlobalsBuilder {
     variables: SymbolMap<FrozenValue>,
     // Set to Some when we are in a struct builder, otherwise None
     struct_fields: Option<SmallMap<FrozenStringValue, FrozenValue>>,
+    // Called for names not in `variables`
+    dynamic_resolver: Option<DynamicResolver>,
 }
 
 /// Used to build a [`Methods`] value.
@@ -68,6 +87,32 @@ pub struct MethodsBuilder {
     globals: GlobalsBuilder,
 }
 
+/// What a name in [`Globals`] or [`Methods`] refers to, see [`Globals::get_kind`].
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub enum ValueKind {
+    /// A native function, or a method of [`Methods`].
+    Function,
+    /// An attribute of [`Methods`], computed from the value it is accessed on.
+    Attribute,
+    /// Any other value, e.g. a constant or a struct.
+    Constant,
+}
+
+impl ValueKind {
+    fn of(x: FrozenValue) -> Self {
+        let x = x.to_value();
+        if x.downcast_ref::<NativeFunction>().is_some()
+            || x.downcast_ref::<NativeMethod>().is_some()
+        {
+            ValueKind::Function
+        } else if x.downcast_ref::<NativeAttribute>().is_some() {
+            ValueKind::Attribute
+        } else {
+            ValueKind::Constant
+        }
+    }
+}
+
 impl Globals {
     /// Create an empty [`Globals`], with no functions in scope.
     pub fn new() -> Self {
@@ -116,6 +161,12 @@ impl Globals {
         self.0.variables.get(name).copied()
     }
 
+    /// Produce a global not set in this environment with the dynamic resolver, if any.
+    /// The value is allocated on (or kept alive by) `heap`.
+    pub(crate) fn resolve_dynamic(&self, name: &str, heap: &FrozenHeap) -> Option<FrozenValue> {
+        (self.0.dynamic_resolver.as_ref()?.0)(name, heap)
+    }
+
     /// Get all the names defined in this environment.
     pub fn names(&self) -> Vec<String> {
         self.0
@@ -125,6 +176,11 @@ impl Globals {
             .collect()
     }
 
+    /// Get the kind of the value defined with this name, if any.
+    pub fn get_kind(&self, name: &str) -> Option<ValueKind> {
+        self.get_frozen(name).map(ValueKind::of)
+    }
+
     pub(crate) fn heap(&self) -> &FrozenHeapRef {
         &self.0.heap
     }
@@ -152,9 +208,15 @@ impl Methods {
         self.0.get_frozen_symbol(name)
     }
 
-    pub(crate) fn names(&self) -> Vec<String> {
+    /// Get all the names of methods and attributes.
+    pub fn names(&self) -> Vec<String> {
         self.0.names()
     }
+
+    /// Get the kind of the method or attribute with this name, if any.
+    pub fn get_kind(&self, name: &str) -> Option<ValueKind> {
+        self.0.get_kind(name)
+    }
 }
 
 impl GlobalsBuilder {
@@ -164,6 +226,7 @@ impl GlobalsBuilder {
             heap: FrozenHeap::new(),
             variables: SymbolMap::new(),
             struct_fields: None,
+            dynamic_resolver: None,
         }
     }
 
@@ -192,7 +255,26 @@ impl GlobalsBuilder {
     /// Add a nested struct to the builder. If `f` adds the definition `foo`,
     /// it will end up on a struct `name`, accessible as `name.foo`.
     /// This function cannot be called recursively from inside `f`.
-    pub fn struct_(&mut self, name: &str, f: impl Fn(&mut GlobalsBuilder)) {
+    ///
+    /// ```
+    /// #[macro_use]
+    /// extern crate starlark;
+    /// # fn main() {
+    /// use starlark::environment::GlobalsBuilder;
+    ///
+    /// #[starlark_module]
+    /// fn fs(builder: &mut GlobalsBuilder) {
+    ///     fn read(path: &str) -> String {
+    ///         Ok(format!("contents of {}", path))
+    ///     }
+    /// }
+    ///
+    /// // Makes `fs.read("x")` available to Starlark code.
+    /// let globals = GlobalsBuilder::new().with_struct("fs", fs).build();
+    /// # let _ = globals;
+    /// # }
+    /// ```
+    pub fn struct_(&mut self, name: &str, f: impl FnOnce(&mut GlobalsBuilder)) {
         assert!(
             self.struct_fields.is_none(),
             "Can't recursively nest GlobalsBuilder::struct_"
@@ -210,7 +292,7 @@ impl GlobalsBuilder {
     }
 
     /// A fluent API for modifying [`GlobalsBuilder`] using [`struct_`](GlobalsBuilder::struct_).
-    pub fn with_struct(mut self, name: &str, f: impl Fn(&mut GlobalsBuilder)) -> Self {
+    pub fn with_struct(mut self, name: &str, f: impl FnOnce(&mut GlobalsBuilder)) -> Self {
         self.struct_(name, f);
         self
     }
@@ -220,6 +302,7 @@ impl GlobalsBuilder {
         Globals(Arc::new(GlobalsData {
             heap: self.heap.into_ref(),
             variables: self.variables,
+            dynamic_resolver: self.dynamic_resolver,
         }))
     }
 
@@ -235,12 +318,74 @@ impl GlobalsBuilder {
         };
     }
 
+    /// Copy all the values of `globals` into the builder, to layer domain-specific
+    /// functions on top of an existing set. Fails without changing the builder if any
+    /// of the names is already defined, see [`inherit_override`](GlobalsBuilder::inherit_override).
+    /// Inside [`struct_`](GlobalsBuilder::struct_) the values become fields of the struct,
+    /// so another [`Globals`] can be imported as a namespace.
+    ///
+    /// The [dynamic resolver](GlobalsBuilder::set_dynamic_resolver) of `globals`,
+    /// if any, is not copied.
+    pub fn inherit(&mut self, globals: &Globals) -> anyhow::Result<()> {
+        for name in globals.0.variables.keys() {
+            if self.is_set(name.as_str()) {
+                let name = name.as_str().to_owned();
+                return Err(EnvironmentError::GlobalAlreadyDefined(name).into());
+            }
+        }
+        self.inherit_override(globals);
+        Ok(())
+    }
+
+    /// Like [`inherit`](GlobalsBuilder::inherit), but values of `globals`
+    /// replace those already defined with the same names.
+    pub fn inherit_override(&mut self, globals: &Globals) {
+        // The values stay on the heap of `globals`, which must be kept alive.
+        self.heap.add_reference(&globals.0.heap);
+        for (name, value) in globals.0.variables.iter() {
+            self.set(name.as_str(), *value);
+        }
+    }
+
+    fn is_set(&self, name: &str) -> bool {
+        match &self.struct_fields {
+            None => self.variables.get_str(name).is_some(),
+            Some(fields) => fields.keys().any(|x| x.as_str() == name),
+        }
+    }
+
+    /// Produce globals which are not [`set`](GlobalsBuilder::set), e.g. when they come
+    /// from a registry too large to enumerate. The resolver is called with a name used by a
+    /// module being evaluated and the frozen heap of that module, and the returned value
+    /// must be allocated on that heap, or on a heap added to it with
+    /// [`add_reference`](FrozenHeap::add_reference).
+    ///
+    /// Globals are resolved when a module is compiled, so the resolver is called
+    /// at most once per name for each evaluated module, and names it does not produce
+    /// are reported as not found before the module runs.
+    pub fn set_dynamic_resolver(
+        &mut self,
+        resolver: impl Fn(&str, &FrozenHeap) -> Option<FrozenValue> + Send + Sync + 'static,
+    ) {
+        self.dynamic_resolver = Some(DynamicResolver(box resolver));
+    }
+
     /// Allocate a value using the same underlying heap as the [`GlobalsBuilder`],
     /// only intended for values that are referred to by those which are passed
     /// to [`set`](GlobalsBuilder::set).
     pub fn alloc<'v, V: AllocFrozenValue>(&'v self, value: V) -> FrozenValue {
         value.alloc_frozen_value(&self.heap)
     }
+
+    /// Allocate a constant which can't be allocated directly with [`alloc`](GlobalsBuilder::alloc),
+    /// e.g. a dict. The value is computed by `f` on a fresh [`Heap`], then frozen onto
+    /// the heap of the [`GlobalsBuilder`], see [`FrozenHeap::freeze_computed`].
+    pub fn alloc_complex<F>(&self, f: F) -> anyhow::Result<FrozenValue>
+    where
+        F: for<'v> FnOnce(&'v Heap) -> anyhow::Result<Value<'v>>,
+    {
+        self.heap.freeze_computed(f)
+    }
 }
 
 impl Methods {
@@ -362,9 +507,17 @@ impl MethodsStatic {
 
     /// Move all the globals in this [`GlobalsBuilder`] into a new one. All variables will
     /// only be allocated once (ensuring things like function comparison works properly).
+    ///
+    /// Panics if `out` already has a method or attribute with the same name as one being
+    /// added, since one would silently shadow the other.
     pub fn populate(&'static self, x: impl FnOnce(&mut MethodsBuilder), out: &mut MethodsBuilder) {
         let methods = self.methods(x).unwrap();
         for (name, value) in methods.0.0.variables.iter() {
+            assert!(
+                out.globals.variables.get_str(name.as_str()).is_none(),
+                "Method or attribute `{}` is defined more than once",
+                name.as_str()
+            );
             out.set(name.as_str(), *value)
         }
     }
@@ -375,7 +528,12 @@ mod test {
     use derive_more::Display;
 
     use super::*;
-    use crate::{assert::Assert, starlark_type, values::StarlarkValue};
+    use crate as starlark;
+    use crate::{
+        assert::Assert,
+        starlark_type,
+        values::{dict::Dict, StarlarkValue},
+    };
 
     #[test]
     fn test_send_sync()
@@ -409,4 +567,200 @@ assert_eq(magic.my_type, "magic")
 assert_eq(magic.my_value, 42)"#,
         );
     }
+
+    #[test]
+    #[should_panic(expected = "Method or attribute `foo` is defined more than once")]
+    fn test_methods_duplicate_name() {
+        #[starlark_module]
+        fn attributes(builder: &mut MethodsBuilder) {
+            #[starlark(attribute)]
+            fn foo(this: i32) -> i32 {
+                Ok(this)
+            }
+        }
+
+        #[starlark_module]
+        fn methods(builder: &mut MethodsBuilder) {
+            fn foo(this: i32) -> i32 {
+                Ok(this + 1)
+            }
+        }
+
+        MethodsBuilder::new().with(attributes).with(methods);
+    }
+
+    #[test]
+    fn test_get_kind() {
+        #[starlark_module]
+        fn globals(builder: &mut GlobalsBuilder) {
+            fn double(x: i32) -> i32 {
+                Ok(x * 2)
+            }
+
+            const ANSWER: i32 = 42;
+        }
+
+        #[starlark_module]
+        fn methods(builder: &mut MethodsBuilder) {
+            fn incr(this: i32) -> i32 {
+                Ok(this + 1)
+            }
+
+            #[starlark(attribute)]
+            fn size(this: i32) -> i32 {
+                Ok(this)
+            }
+        }
+
+        let globals = GlobalsBuilder::new()
+            .with(globals)
+            .with_struct("ns", |x| x.set("x", 1))
+            .build();
+        let mut names = globals.names();
+        names.sort();
+        assert_eq!(names, &["ANSWER", "double", "ns"]);
+        assert_eq!(Some(ValueKind::Function), globals.get_kind("double"));
+        assert_eq!(Some(ValueKind::Constant), globals.get_kind("ANSWER"));
+        assert_eq!(Some(ValueKind::Constant), globals.get_kind("ns"));
+        assert_eq!(None, globals.get_kind("missing"));
+
+        let methods = MethodsBuilder::new().with(methods).build();
+        let mut names = methods.names();
+        names.sort();
+        assert_eq!(names, &["incr", "size"]);
+        assert_eq!(Some(ValueKind::Function), methods.get_kind("incr"));
+        assert_eq!(Some(ValueKind::Attribute), methods.get_kind("size"));
+    }
+
+    #[test]
+    fn test_struct() {
+        #[starlark_module]
+        fn ns(builder: &mut GlobalsBuilder) {
+            fn double(x: i32) -> i32 {
+                Ok(x * 2)
+            }
+
+            const ANSWER: i32 = 42;
+        }
+
+        let mut a = Assert::new();
+        a.globals_add(|x| x.struct_("ns", ns));
+        a.pass(
+            r#"
+assert_eq(ns.double(3), 6)
+assert_eq(ns.ANSWER, 42)
+assert_eq(type(ns), "struct")"#,
+        );
+        a.fail("ns.missing()", "no attribute");
+    }
+
+    #[test]
+    fn test_alloc_complex() {
+        let mut a = Assert::new();
+        a.globals_add(|x| {
+            let codes = x
+                .alloc_complex(|heap| {
+                    let mut codes = Dict::default();
+                    for (name, code) in [("ok", 200), ("not_found", 404)] {
+                        let name = heap.alloc_str_hashed(name);
+                        codes.insert_hashed(name, Value::new_int(code));
+                    }
+                    Ok(heap.alloc(codes))
+                })
+                .unwrap();
+            x.set("CODES", codes);
+        });
+        a.pass(
+            r#"
+assert_eq(CODES, {"ok": 200, "not_found": 404})
+assert_eq(CODES["not_found"], 404)"#,
+        );
+        a.fail("CODES['teapot'] = 418", "Immutable");
+    }
+
+    #[test]
+    fn test_method_kwargs_map() {
+        #[starlark_module]
+        fn methods(builder: &mut MethodsBuilder) {
+            fn configure(this: Value, kwargs: SmallMap<String, Value>) -> String {
+                let options: Vec<String> =
+                    kwargs.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+                Ok(format!("{}({})", this, options.join(", ")))
+            }
+        }
+
+        #[derive(Debug, Display)]
+        #[display(fmt = "Tool")]
+        struct Tool;
+        starlark_simple_value!(Tool);
+        impl<'v> StarlarkValue<'v> for Tool {
+            starlark_type!("tool");
+            fn get_methods(&self) -> Option<&'static Methods> {
+                static RES: MethodsStatic = MethodsStatic::new();
+                RES.methods(methods)
+            }
+        }
+
+        let mut a = Assert::new();
+        a.globals_add(|x| x.set("tool", Tool));
+        // Keyword arguments are kept in the order they were passed.
+        a.eq(
+            "'Tool(z=1, a=\"x\", m=[True])'",
+            "tool.configure(z = 1, a = 'x', m = [True])",
+        );
+        a.eq("'Tool()'", "tool.configure()");
+        a.fail("tool.configure(1)", "extra positional");
+    }
+
+    #[test]
+    fn test_inherit() {
+        #[starlark_module]
+        fn math(builder: &mut GlobalsBuilder) {
+            fn double(x: i32) -> i32 {
+                Ok(x * 2)
+            }
+
+            const ANSWER: i32 = 42;
+        }
+
+        #[starlark_module]
+        fn text(builder: &mut GlobalsBuilder) {
+            fn shout(x: &str) -> String {
+                Ok(x.to_uppercase())
+            }
+        }
+
+        let math = GlobalsBuilder::new().with(math).build();
+        let text = GlobalsBuilder::new().with(text).build();
+
+        let mut a = Assert::new();
+        a.globals_add(|x| {
+            x.inherit(&math).unwrap();
+            x.inherit(&text).unwrap();
+            x.struct_("strings", |x| x.inherit(&text).unwrap());
+        });
+        a.pass(
+            r#"
+assert_eq(double(ANSWER), 84)
+assert_eq(shout("hi"), "HI")
+assert_eq(strings.shout("ns"), "NS")"#,
+        );
+
+        let mut builder = GlobalsBuilder::new();
+        builder.set("ANSWER", 1);
+        assert_eq!(
+            "Global `ANSWER` is already defined",
+            builder.inherit(&math).unwrap_err().to_string()
+        );
+        // A failed `inherit` doesn't add anything.
+        assert_eq!(builder.build().names(), &["ANSWER"]);
+
+        let mut builder = GlobalsBuilder::new();
+        builder.set("ANSWER", 1);
+        builder.inherit_override(&math);
+        let globals = builder.build();
+        let answer = globals.get_frozen("ANSWER").unwrap();
+        assert_eq!(Some(42), answer.to_value().unpack_int());
+        assert_eq!(Some(ValueKind::Function), globals.get_kind("double"));
+    }
 }
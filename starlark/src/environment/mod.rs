@@ -27,9 +27,11 @@ mod module_dump;
 mod modules;
 pub(crate) mod names;
 pub(crate) mod slots;
+mod snapshot;
 
 pub use globals::*;
 pub use modules::*;
+pub use snapshot::*;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -52,4 +54,6 @@ pub(crate) enum EnvironmentError {
     ModuleSymbolIsNotExported(String),
     #[error("No imports are available, you tried `{0}` (no call to `Evaluator.set_loader`)")]
     NoImportsAvailable(String),
+    #[error("Global `{0}` is already defined")]
+    GlobalAlreadyDefined(String),
 }
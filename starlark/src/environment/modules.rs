@@ -33,6 +33,7 @@ use crate::{
         EnvironmentError,
     },
     errors::did_you_mean::did_you_mean,
+    eval::OptimizeOptions,
     syntax::ast::Visibility,
     values::{
         docs,
@@ -227,8 +228,18 @@ impl Default for Module {
 impl Module {
     /// Create a new module environment with no contents.
     pub fn new() -> Self {
+        Self::with_heap(Heap::new())
+    }
+
+    /// Create a new module whose [`heap`](Module::heap) has room for about `bytes` bytes
+    /// of values up front, see [`Heap::with_capacity`].
+    pub fn with_heap_capacity(bytes: usize) -> Self {
+        Self::with_heap(Heap::with_capacity(bytes))
+    }
+
+    fn with_heap(heap: Heap) -> Self {
         Self {
-            heap: Heap::new(),
+            heap,
             frozen_heap: FrozenHeap::new(),
             names: MutableNames::new(),
             slots: MutableSlots::new(),
@@ -246,7 +257,7 @@ impl Module {
         &self.frozen_heap
     }
 
-    pub(crate) fn names(&self) -> &MutableNames {
+    pub(crate) fn mutable_names(&self) -> &MutableNames {
         &self.names
     }
 
@@ -272,8 +283,69 @@ impl Module {
             })
     }
 
+    /// Names of the exported variables which have been assigned, in the order of definition.
+    /// Unlike [`FrozenModule::names`], can be used while the module is still being evaluated,
+    /// e.g. to show the variables defined so far after each statement in a REPL.
+    pub fn names(&self) -> Vec<String> {
+        self.mutable_names()
+            .all_names()
+            .into_iter()
+            .filter_map(|(name, _)| self.get(&name).map(|_| name))
+            .collect()
+    }
+
     /// Freeze the environment, all its value will become immutable afterwards.
     pub fn freeze(self) -> anyhow::Result<FrozenModule> {
+        self.freeze_impl(false, &OptimizeOptions::default())
+    }
+
+    /// Like [`freeze`](Module::freeze), but all equal strings, and all equal tuples of
+    /// strings and ints, are stored only once on the resulting frozen heap. Makes freezing
+    /// a little slower, but reduces memory for modules holding many copies of the same
+    /// strings or tuples, e.g. large generated configs or dicts keyed by coordinates.
+    pub fn freeze_with_interning(self) -> anyhow::Result<FrozenModule> {
+        self.freeze_impl(true, &OptimizeOptions::default())
+    }
+
+    /// Like [`freeze`](Module::freeze), but only perform the given optimizations
+    /// of function bodies, e.g. to measure the effect of each one.
+    pub fn freeze_with_optimize_options(
+        self,
+        options: &OptimizeOptions,
+    ) -> anyhow::Result<FrozenModule> {
+        self.freeze_impl(false, options)
+    }
+
+    /// Like [`freeze`](Module::freeze), but only keep the module variables `names`
+    /// and the values reachable from them. All other variables are dropped with the
+    /// unfrozen heap, e.g. large intermediate structures used to compute the exports.
+    ///
+    /// Functions are kept as they are, so a kept function which reads a dropped module
+    /// variable will fail with a "referenced before assignment" error when called.
+    /// Fails if any of `names` is not assigned in the module.
+    pub fn freeze_exported(self, names: &[&str]) -> anyhow::Result<FrozenModule> {
+        let mut keep = vec![false; self.names.slot_count() as usize];
+        for name in names {
+            match self.names.get_name(name) {
+                Some((slot, _)) if self.slots().get_slot(slot).is_some() => {
+                    keep[slot.0 as usize] = true
+                }
+                _ => return Err(EnvironmentError::ModuleHasNoSymbol((*name).to_owned()).into()),
+            }
+        }
+        for (i, value) in self.slots().get_slots_mut().iter_mut().enumerate() {
+            if keep.get(i) != Some(&true) {
+                *value = None;
+            }
+        }
+        self.freeze_impl(false, &OptimizeOptions::default())
+    }
+
+    fn freeze_impl(
+        self,
+        interning: bool,
+        options: &OptimizeOptions,
+    ) -> anyhow::Result<FrozenModule> {
         let Module {
             names,
             slots,
@@ -285,7 +357,10 @@ impl Module {
         // Note that we even freeze anonymous slots, since they are accessed by
         // slot-index in the code, and we don't walk into them, so don't know if
         // they are used.
-        let freezer = Freezer::new(frozen_heap);
+        let mut freezer = Freezer::new(frozen_heap);
+        if interning {
+            freezer = freezer.with_interning();
+        }
         let slots = slots.freeze(&freezer)?;
         let rest = FrozenModuleRef(Arc::new(FrozenModuleData {
             names: names.freeze(),
@@ -294,7 +369,7 @@ impl Module {
         }));
         let frozen_module_ref = freezer.heap.alloc_simple_frozen_ref(rest.dupe());
         for frozen_def in freezer.frozen_defs.borrow().as_slice() {
-            frozen_def.post_freeze(frozen_module_ref, &freezer.heap);
+            frozen_def.post_freeze(frozen_module_ref, &freezer.heap, options);
         }
         // The values MUST be alive up until this point (as the above line uses them),
         // but can now be dropped
@@ -306,6 +381,11 @@ impl Module {
     /// Set the value of a variable in the environment.
     /// Modifying these variables while executing is ongoing can have
     /// surprising effects.
+    ///
+    /// Calling this before evaluation pre-declares a module variable (e.g. `name` in
+    /// a Bazel `BUILD` file): the script sees it as if it had been assigned at the
+    /// top level, so it can be read and reassigned, and it is exported on freeze.
+    /// Unlike [`Globals`](crate::environment::Globals), it lives in the module itself.
     pub fn set<'v>(&'v self, name: &str, value: Value<'v>) {
         let slot = self.names.add_name(name);
         let slots = self.slots();
@@ -379,3 +459,141 @@ where
     FrozenModule: Send + Sync,
 {
 }
+
+#[test]
+fn test_freeze_with_interning() {
+    fn freeze(interning: bool) -> FrozenModule {
+        let module = Module::new();
+        {
+            let heap = module.heap();
+            // Strings built at runtime, so each one is a separate heap object.
+            let xs: Vec<Value> = (0..100)
+                .map(|i| heap.alloc_str(&format!("repeated {}", i % 2)))
+                .collect();
+            module.set("xs", heap.alloc_list(&xs));
+        }
+        if interning {
+            module.freeze_with_interning().unwrap()
+        } else {
+            module.freeze().unwrap()
+        }
+    }
+
+    let plain = freeze(false);
+    let interned = freeze(true);
+    assert!(interned.frozen_heap().allocated_bytes() < plain.frozen_heap().allocated_bytes());
+
+    let heap = Heap::new();
+    let xs = interned.get("xs").unwrap();
+    let xs = xs.value().iterate_collect(&heap).unwrap();
+    assert_eq!(Some("repeated 0"), xs[0].unpack_str());
+    assert_eq!(Some("repeated 1"), xs[1].unpack_str());
+    assert!(xs[0].ptr_eq(xs[2]));
+    assert!(!xs[0].ptr_eq(xs[1]));
+}
+
+#[test]
+fn test_freeze_with_interning_tuples() {
+    use crate::{
+        environment::Globals,
+        eval::Evaluator,
+        syntax::{AstModule, Dialect},
+    };
+
+    fn freeze(interning: bool) -> FrozenModule {
+        let module = Module::new();
+        {
+            let mut eval = Evaluator::new(&module);
+            let ast = AstModule::parse(
+                "grid.star",
+                r#"
+# Keys are built at runtime, so each tuple is a separate heap object.
+grid = [{(x, "y" + str(y)): i for x in range(10) for y in range(10)} for i in range(10)]
+# A tuple which is part of a cycle is frozen without interning.
+cycle = []
+cycle.append((cycle, 1))
+"#
+                .to_owned(),
+                &Dialect::Standard,
+            )
+            .unwrap();
+            eval.eval_module(ast, &Globals::standard()).unwrap();
+        }
+        if interning {
+            module.freeze_with_interning().unwrap()
+        } else {
+            module.freeze().unwrap()
+        }
+    }
+
+    let plain = freeze(false);
+    let interned = freeze(true);
+    assert!(interned.frozen_heap().allocated_bytes() < plain.frozen_heap().allocated_bytes());
+
+    let heap = Heap::new();
+    let grid = interned.get("grid").unwrap();
+    let grid = grid.value().iterate_collect(&heap).unwrap();
+    let keys0 = grid[0].iterate_collect(&heap).unwrap();
+    let keys1 = grid[1].iterate_collect(&heap).unwrap();
+    assert_eq!(100, keys0.len());
+    assert_eq!(keys0[13].to_repr(), "(1, \"y3\")");
+    assert!(keys0[13].ptr_eq(keys1[13]));
+    assert!(!keys0[13].ptr_eq(keys0[14]));
+
+    let cycle = interned.get("cycle").unwrap();
+    let cycle = cycle.value();
+    let tuple = cycle.iterate_collect(&heap).unwrap()[0];
+    assert!(tuple.iterate_collect(&heap).unwrap()[0].ptr_eq(cycle));
+}
+
+#[test]
+fn test_freeze_exported() {
+    use crate::{
+        environment::Globals,
+        eval::Evaluator,
+        syntax::{AstModule, Dialect},
+    };
+
+    fn module() -> Module {
+        let module = Module::new();
+        {
+            let mut eval = Evaluator::new(&module);
+            let ast = AstModule::parse(
+                "config.star",
+                r#"
+_table = ["entry " + str(i) for i in range(1000)]
+size = len(_table)
+first = _table[0]
+"#
+                .to_owned(),
+                &Dialect::Standard,
+            )
+            .unwrap();
+            eval.eval_module(ast, &Globals::standard()).unwrap();
+        }
+        module
+    }
+
+    let all = module().freeze().unwrap();
+    let exported = module().freeze_exported(&["size", "first"]).unwrap();
+    assert_eq!(
+        1000,
+        exported.get("size").unwrap().value().unpack_int().unwrap()
+    );
+    assert_eq!(
+        "entry 0",
+        exported.get("first").unwrap().value().unpack_str().unwrap()
+    );
+    // The private intermediate was not frozen.
+    assert!(all.get_any_visibility("_table").is_some());
+    assert!(exported.get_any_visibility("_table").is_none());
+    assert!(exported.frozen_heap().allocated_bytes() * 10 < all.frozen_heap().allocated_bytes());
+
+    assert_eq!(
+        "Module has no symbol `missing`",
+        module()
+            .freeze_exported(&["size", "missing"])
+            .unwrap_err()
+            .to_string()
+    );
+}
@@ -0,0 +1,179 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::{
+    collections::SmallMap,
+    environment::FrozenModule,
+    values::{OwnedFrozenValue, Value},
+};
+
+/// A change to an exported binding between two [`ModuleSnapshot`]s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindingChange {
+    /// The binding is only present in the newer snapshot.
+    Added(String),
+    /// The binding is only present in the older snapshot.
+    Removed(String),
+    /// The binding is present in both snapshots, with different values.
+    Changed(String),
+}
+
+/// The exported bindings of a [`FrozenModule`], obtained with [`FrozenModule::snapshot`].
+/// Snapshots of two evaluations can be compared with [`diff`](ModuleSnapshot::diff),
+/// e.g. to skip downstream work when a module is re-evaluated with the same results.
+///
+/// The snapshot keeps the values alive, and they are compared with `==`. Values which
+/// don't define equality, like functions, are only equal to themselves, so a `def`
+/// evaluated again is always reported as changed.
+#[derive(Debug, Clone)]
+pub struct ModuleSnapshot {
+    bindings: SmallMap<String, OwnedFrozenValue>,
+}
+
+impl FrozenModule {
+    /// Capture the exported bindings of this module.
+    pub fn snapshot(&self) -> ModuleSnapshot {
+        let mut bindings = SmallMap::new();
+        for name in self.names() {
+            // Only exported variables which have been assigned.
+            if let Some(value) = self.get(name) {
+                bindings.insert(name.to_owned(), value);
+            }
+        }
+        ModuleSnapshot { bindings }
+    }
+}
+
+fn value_equals(x: Value, y: Value) -> bool {
+    // `1 == 1.0` in Starlark, but changing a binding from one to the other is a change.
+    // Equality fails only on too deep recursion, assume those values changed.
+    x.get_type() == y.get_type() && x.equals(y).unwrap_or(false)
+}
+
+impl ModuleSnapshot {
+    /// Names of the bindings in this snapshot.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.bindings.keys().map(|x| x.as_str())
+    }
+
+    /// The bindings which were added, removed or changed going from `self` to `newer`.
+    /// Bindings of `self` are listed first, in order of definition, followed by the
+    /// bindings only present in `newer`.
+    pub fn diff(&self, newer: &ModuleSnapshot) -> Vec<BindingChange> {
+        let mut res = Vec::new();
+        for (name, value) in &self.bindings {
+            match newer.bindings.get(name) {
+                None => res.push(BindingChange::Removed(name.clone())),
+                Some(new_value) if !value_equals(value.value(), new_value.value()) => {
+                    res.push(BindingChange::Changed(name.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+        for name in newer.bindings.keys() {
+            if !self.bindings.contains_key(name) {
+                res.push(BindingChange::Added(name.clone()));
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        environment::{Globals, Module},
+        eval::Evaluator,
+        syntax::{AstModule, Dialect},
+    };
+
+    fn eval(code: &str) -> FrozenModule {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        let ast = AstModule::parse("config.star", code.to_owned(), &Dialect::Extended).unwrap();
+        eval.eval_module(ast, &Globals::standard()).unwrap();
+        module.freeze().unwrap()
+    }
+
+    fn snapshot(code: &str) -> ModuleSnapshot {
+        eval(code).snapshot()
+    }
+
+    #[test]
+    fn test_snapshot_diff() {
+        let old = snapshot(
+            r#"
+name = "x"
+srcs = ["a.c", "b.c"]
+deps = {"y": 1}
+removed = 1
+_private = 1
+"#,
+        );
+        let new = snapshot(
+            r#"
+name = "x"
+srcs = ["a.c", "c.c"]
+deps = {"y": 1}
+added = 1
+_private = 2
+"#,
+        );
+        assert_eq!(
+            old.diff(&new),
+            vec![
+                BindingChange::Changed("srcs".to_owned()),
+                BindingChange::Removed("removed".to_owned()),
+                BindingChange::Added("added".to_owned()),
+            ]
+        );
+        assert!(old.diff(&old).is_empty());
+        assert_eq!(
+            old.names().collect::<Vec<_>>(),
+            vec!["name", "srcs", "deps", "removed"]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_diff_values() {
+        let old = eval(
+            r#"
+def f(x): return x + 1
+n = 1
+g = f
+"#,
+        );
+        let new = eval(
+            r#"
+def f(x): return x + 2
+n = 1.0
+g = f
+"#,
+        );
+        // Functions are compared by identity, even if only their body changed.
+        assert_eq!(
+            old.snapshot().diff(&new.snapshot()),
+            vec![
+                BindingChange::Changed("f".to_owned()),
+                BindingChange::Changed("n".to_owned()),
+                BindingChange::Changed("g".to_owned()),
+            ]
+        );
+        assert!(old.snapshot().diff(&old.snapshot()).is_empty());
+    }
+}
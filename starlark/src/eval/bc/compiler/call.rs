@@ -35,7 +35,7 @@ use crate::{
             instr_impl::{
                 InstrCall, InstrCallFrozen, InstrCallFrozenDef, InstrCallFrozenDefPos,
                 InstrCallFrozenNative, InstrCallFrozenNativePos, InstrCallFrozenPos,
-                InstrCallMethod, InstrCallMethodPos, InstrCallPos,
+                InstrCallMethod, InstrCallMethodPos, InstrCallPos, MethodSymbol,
             },
             writer::BcWriter,
         },
@@ -182,14 +182,15 @@ impl Spanned<CallCompiled> {
                 this.write_bc(bc);
                 if let Some(pos) = args.pos_only() {
                     write_exprs(pos, bc);
-                    let symbol = symbol.clone();
+                    let symbol = MethodSymbol::new(symbol.clone());
                     bc.write_instr::<InstrCallMethodPos>(
                         span,
                         (ArgPopsStack1, ArgPopsStack(pos.len() as u32), symbol, span),
                     );
                 } else {
                     let args = args.write_bc(span, bc);
-                    bc.write_instr::<InstrCallMethod>(span, (ArgPopsStack1, symbol.clone(), args));
+                    let symbol = MethodSymbol::new(symbol.clone());
+                    bc.write_instr::<InstrCallMethod>(span, (ArgPopsStack1, symbol, args));
                 }
             }
         }
@@ -22,8 +22,8 @@ use crate::{
     eval::{
         bc::{
             instr_impl::{
-                InstrComprDictInsert, InstrComprListAppend, InstrContinue, InstrDictNew,
-                InstrListNew,
+                InstrComprDictInsert, InstrComprListAppend, InstrComprListNewSized, InstrContinue,
+                InstrDictNew, InstrListNew,
             },
             writer::BcWriter,
         },
@@ -39,6 +39,16 @@ impl ClauseCompiled {
         term: impl FnOnce(&mut BcWriter),
     ) {
         self.over.write_bc(bc);
+        self.write_bc_loop(bc, rem, term);
+    }
+
+    /// Write the loop, assuming the iterable is already on the stack.
+    fn write_bc_loop(
+        &self,
+        bc: &mut BcWriter,
+        rem: &[ClauseCompiled],
+        term: impl FnOnce(&mut BcWriter),
+    ) {
         bc.write_for(self.over_span, |bc| {
             self.var.write_bc(bc);
             for c in &self.ifs {
@@ -65,12 +75,20 @@ impl ComprCompiled {
         let ss = bc.stack_size();
         match *self {
             ComprCompiled::List(box ref expr, ref clauses) => {
-                bc.write_instr::<InstrListNew>(span, ());
                 let (first, rem) = clauses.split_last().unwrap();
-                first.write_bc(bc, rem, |bc| {
+                let term = |bc: &mut BcWriter| {
                     expr.write_bc(bc);
                     bc.write_instr::<InstrComprListAppend>(expr.span, ());
-                });
+                };
+                if rem.is_empty() && first.ifs.is_empty() {
+                    // One element per element of the iterable, so size the list up front.
+                    first.over.write_bc(bc);
+                    bc.write_instr::<InstrComprListNewSized>(span, ());
+                    first.write_bc_loop(bc, rem, term);
+                } else {
+                    bc.write_instr::<InstrListNew>(span, ());
+                    first.write_bc(bc, rem, term);
+                }
             }
             ComprCompiled::Dict(box (ref k, ref v), ref clauses) => {
                 bc.write_instr::<InstrDictNew>(span, ());
@@ -21,8 +21,8 @@ use crate::{
         bc::{
             bytecode::Bc,
             instr_impl::{
-                InstrBeforeStmt, InstrBreak, InstrContinue, InstrPossibleGc, InstrReturn,
-                InstrReturnNone,
+                InstrAssertFailed, InstrBeforeStmt, InstrBreak, InstrContinue, InstrPossibleGc,
+                InstrReturn, InstrReturnNone,
             },
             writer::BcWriter,
         },
@@ -31,6 +31,7 @@ use crate::{
             stmt::{StmtCompileContext, StmtCompiledValue, StmtsCompiled},
         },
     },
+    values::FrozenValue,
 };
 
 impl StmtsCompiled {
@@ -159,12 +160,17 @@ impl Spanned<StmtCompiledValue> {
             StmtCompiledValue::If(box (ref c, ref t, ref f)) => {
                 Self::write_if_else(c, MaybeNot::Id, t, f, compiler, bc);
             }
-            StmtCompiledValue::For(box (ref assign, ref over, ref body)) => {
+            StmtCompiledValue::For(box (ref assign, ref over, ref body, ref else_block)) => {
                 over.write_bc(bc);
-                bc.write_for(span, |bc| {
+                let write_body = |bc: &mut BcWriter| {
                     assign.write_bc(bc);
                     body.write_bc(compiler, bc);
-                });
+                };
+                if else_block.is_empty() {
+                    bc.write_for(span, write_body);
+                } else {
+                    bc.write_for_else(span, write_body, |bc| else_block.write_bc(compiler, bc));
+                }
             }
             StmtCompiledValue::Break => {
                 bc.write_instr::<InstrBreak>(span, ());
@@ -172,6 +178,13 @@ impl Spanned<StmtCompiledValue> {
             StmtCompiledValue::Continue => {
                 bc.write_instr::<InstrContinue>(span, ());
             }
+            StmtCompiledValue::AssertFailed(ref message) => {
+                match message {
+                    Some(message) => message.write_bc(bc),
+                    None => bc.write_const(span, FrozenValue::new_none()),
+                }
+                bc.write_instr::<InstrAssertFailed>(span, ());
+            }
         }
     }
 }
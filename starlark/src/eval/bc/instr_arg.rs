@@ -33,7 +33,7 @@ use crate::{
             addr::{BcAddr, BcAddrOffset, BcPtrAddr},
             compiler::call::ArgsCompiledValueBc,
             instr::BcInstr,
-            instr_impl::InstrDefData,
+            instr_impl::{InstrDefData, MethodSymbol},
             opcode::{BcOpcode, BcOpcodeHandler},
         },
         runtime::slots::LocalSlotId,
@@ -514,6 +514,22 @@ impl BcInstrArg for Symbol {
     }
 }
 
+impl BcInstrArg for MethodSymbol {
+    fn fmt_append(param: &Self, ip: BcAddr, f: &mut dyn Write) -> fmt::Result {
+        BcInstrArg::fmt_append(&param.symbol, ip, f)
+    }
+
+    fn visit_jump_addr(_param: &Self, _consumer: &mut dyn FnMut(BcAddrOffset)) {}
+
+    fn pops_stack(_param: &Self) -> u32 {
+        0
+    }
+
+    fn pushes_stack(_param: &Self) -> u32 {
+        0
+    }
+}
+
 impl BcInstrArg for Box<[FrozenValue]> {
     fn fmt_append(param: &Self, _ip: BcAddr, f: &mut dyn Write) -> fmt::Result {
         write!(f, " [")?;
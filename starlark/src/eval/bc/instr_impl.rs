@@ -17,14 +17,15 @@
 
 //! Instruction implementations.
 
-use std::{cmp::Ordering, marker, mem::MaybeUninit};
+use std::{cmp::Ordering, marker, mem::MaybeUninit, ptr};
 
 use gazebo::coerce::coerce;
+use once_cell::sync::OnceCell;
 
 use crate::{
     codemap::{Span, Spanned},
     collections::{symbol_map::Symbol, Hashed, SmallMap},
-    environment::slots::ModuleSlotId,
+    environment::{slots::ModuleSlotId, Methods},
     eval::{
         bc::{
             addr::{BcAddr, BcAddrOffset, BcPtrAddr},
@@ -42,10 +43,10 @@ use crate::{
         compiler::{add_span_to_expr_error, expr_throw, scope::Captured, EvalException},
         fragment::{
             def::{DefInfo, ParameterCompiled},
-            expr::{get_attr_hashed_bind, get_attr_hashed_raw, EvalError},
+            expr::{get_attr_hashed_bind, get_attr_no_attr_error, EvalError},
             stmt::{add_assign, before_stmt, possible_gc, AssignError},
         },
-        runtime::slots::LocalSlotId,
+        runtime::{evaluator::EvaluatorError, slots::LocalSlotId},
         Arguments, Def, Evaluator, FrozenDef, ParametersSpec,
     },
     values::{
@@ -55,7 +56,7 @@ use crate::{
         string::interpolation::{format_one, percent_s_one},
         typed::FrozenValueTyped,
         typing::TypeCompiled,
-        FrozenRef, FrozenStringValue, FrozenValue, Heap, StarlarkValue, Value,
+        FrozenRef, FrozenStringValue, FrozenValue, Heap, StarlarkValue, Value, ValueError,
     },
 };
 
@@ -649,7 +650,7 @@ pub(crate) type InstrBitNot = InstrUnOp<InstrBitNotImpl>;
 impl InstrUnOpImpl for InstrNotImpl {
     #[inline(always)]
     fn eval<'v>(v: Value<'v>, _heap: &'v Heap) -> Result<Value<'v>, anyhow::Error> {
-        Ok(Value::new_bool(!v.to_bool()))
+        Ok(Value::new_bool(!v.try_to_bool()?))
     }
 }
 
@@ -896,6 +897,9 @@ impl InstrNoFlowAddSpanImpl for InstrFormatOneImpl {
 }
 
 pub(crate) trait InstrCompareImpl: 'static {
+    /// The operator, for error messages.
+    const OP: &'static str;
+
     fn eval_compare(ordering: Ordering) -> bool;
 }
 
@@ -904,8 +908,26 @@ pub(crate) struct InstrCompare<I: InstrCompareImpl>(marker::PhantomData<I>);
 impl<I: InstrCompareImpl> InstrBinOpImpl for InstrCompare<I> {
     #[inline(always)]
     fn eval<'v>(v0: Value<'v>, v1: Value<'v>, _heap: &'v Heap) -> Result<Value<'v>, anyhow::Error> {
-        Ok(Value::new_bool(I::eval_compare(v0.compare(v1)?)))
+        match v0.compare(v1) {
+            Ok(ordering) => Ok(Value::new_bool(I::eval_compare(ordering))),
+            Err(e) => Err(compare_error(e, I::OP)),
+        }
+    }
+}
+
+/// Values which can't be ordered fail with the operation `compare`.
+/// Replace it with the operator used, so `1 < "a"` reports `<`.
+#[cold]
+#[inline(never)]
+fn compare_error(mut e: anyhow::Error, op: &str) -> anyhow::Error {
+    if let Some(ValueError::OperationNotSupportedBinary { op: e_op, .. }) =
+        e.downcast_mut::<ValueError>()
+    {
+        if e_op == "compare" {
+            *e_op = op.to_owned();
+        }
     }
+    e
 }
 
 pub(crate) struct InstrLessImpl;
@@ -919,6 +941,8 @@ pub(crate) type InstrLessOrEqual = InstrBinOp<InstrCompare<InstrLessOrEqualImpl>
 pub(crate) type InstrGreaterOrEqual = InstrBinOp<InstrCompare<InstrGreaterOrEqualImpl>>;
 
 impl InstrCompareImpl for InstrLessImpl {
+    const OP: &'static str = "<";
+
     #[inline(always)]
     fn eval_compare(ordering: Ordering) -> bool {
         ordering == Ordering::Less
@@ -926,6 +950,8 @@ impl InstrCompareImpl for InstrLessImpl {
 }
 
 impl InstrCompareImpl for InstrGreaterImpl {
+    const OP: &'static str = ">";
+
     #[inline(always)]
     fn eval_compare(ordering: Ordering) -> bool {
         ordering == Ordering::Greater
@@ -933,6 +959,8 @@ impl InstrCompareImpl for InstrGreaterImpl {
 }
 
 impl InstrCompareImpl for InstrLessOrEqualImpl {
+    const OP: &'static str = "<=";
+
     #[inline(always)]
     fn eval_compare(ordering: Ordering) -> bool {
         ordering != Ordering::Greater
@@ -940,6 +968,8 @@ impl InstrCompareImpl for InstrLessOrEqualImpl {
 }
 
 impl InstrCompareImpl for InstrGreaterOrEqualImpl {
+    const OP: &'static str = ">=";
+
     #[inline(always)]
     fn eval_compare(ordering: Ordering) -> bool {
         ordering != Ordering::Less
@@ -951,8 +981,8 @@ pub(crate) type InstrType = InstrUnOp<InstrTypeImpl>;
 
 impl InstrUnOpImpl for InstrTypeImpl {
     #[inline(always)]
-    fn eval<'v>(v: Value<'v>, _heap: &'v Heap) -> Result<Value<'v>, anyhow::Error> {
-        Ok(v.get_type_value().unpack().to_value())
+    fn eval<'v>(v: Value<'v>, heap: &'v Heap) -> Result<Value<'v>, anyhow::Error> {
+        Ok(v.get_type_value_dynamic(heap))
     }
 }
 
@@ -972,7 +1002,7 @@ impl InstrNoFlowImpl for InstrTypeIsImpl {
         t: &FrozenStringValue,
         v: Value<'v>,
     ) -> Result<Value<'v>, EvalException> {
-        Ok(Value::new_bool(v.get_type_value() == *t))
+        Ok(Value::new_bool(v.type_is(*t)))
     }
 }
 
@@ -994,6 +1024,7 @@ pub(crate) struct InstrDictConstKeysImpl;
 pub(crate) struct InstrDictNPopImpl;
 pub(crate) struct InstrListNewImpl;
 pub(crate) struct InstrDictNewImpl;
+pub(crate) struct InstrComprListNewSizedImpl;
 pub(crate) struct InstrComprListAppendImpl;
 pub(crate) struct InstrComprDictInsertImpl;
 
@@ -1005,6 +1036,7 @@ pub(crate) type InstrDictNew = InstrNoFlow<InstrDictNewImpl>;
 pub(crate) type InstrDictOfConsts = InstrNoFlow<InstrDictOfConstsImpl>;
 pub(crate) type InstrDictConstKeys = InstrNoFlow<InstrDictConstKeysImpl>;
 pub(crate) type InstrDictNPop = InstrNoFlow<InstrDictNPopImpl>;
+pub(crate) type InstrComprListNewSized = InstrNoFlow<InstrComprListNewSizedImpl>;
 pub(crate) type InstrComprListAppend = InstrNoFlow<InstrComprListAppendImpl>;
 pub(crate) type InstrComprDictInsert = InstrNoFlowAddSpan<InstrComprDictInsertImpl>;
 
@@ -1168,6 +1200,28 @@ impl InstrNoFlowImpl for InstrDictNewImpl {
     }
 }
 
+impl InstrNoFlowImpl for InstrComprListNewSizedImpl {
+    type Pop<'v> = Value<'v>;
+    type Push<'v> = [Value<'v>; 2];
+    type Arg = ();
+
+    /// Pop the iterable of a comprehension, push a list with capacity for its length,
+    /// then push the iterable back.
+    #[inline(always)]
+    fn run_with_args<'v>(
+        eval: &mut Evaluator<'v, '_>,
+        _stack: &mut BcStackPtr<'v, '_>,
+        _: BcPtrAddr,
+        (): &(),
+        over: Value<'v>,
+    ) -> Result<[Value<'v>; 2], EvalException> {
+        // If the length is unknown, the list grows as usual,
+        // and if the value is not iterable, the loop reports the error.
+        let capacity = over.length().map_or(0, |len| len as usize);
+        Ok([eval.heap().alloc_list_with_capacity(capacity), over])
+    }
+}
+
 impl InstrNoFlowImpl for InstrComprListAppendImpl {
     type Pop<'v> = [Value<'v>; 2];
     type Push<'v> = Value<'v>;
@@ -1196,16 +1250,17 @@ impl InstrNoFlowAddSpanImpl for InstrComprDictInsertImpl {
 
     #[inline(always)]
     fn run_with_args<'v>(
-        _eval: &mut Evaluator<'v, '_>,
+        eval: &mut Evaluator<'v, '_>,
         _stack: &mut BcStackPtr<'v, '_>,
         (): &(),
         [dict, key, value]: [Value<'v>; 3],
     ) -> Result<Value<'v>, anyhow::Error> {
         let key = key.get_hashed()?;
-        Dict::from_value_mut(dict)
-            .unwrap()
-            .unwrap()
-            .insert_hashed(key, value);
+        let mut d = Dict::from_value_mut(dict).unwrap().unwrap();
+        if eval.error_on_duplicate_comprehension_keys && d.get_hashed(key).is_some() {
+            return Err(EvalError::DuplicateDictionaryKey(key.key().to_repr()).into());
+        }
+        d.insert_hashed(key, value);
         Ok(dict)
     }
 }
@@ -1237,16 +1292,16 @@ impl BcInstr for InstrIfBr {
 
     #[inline(always)]
     fn run<'v, 'b>(
-        _eval: &mut Evaluator<'v, '_>,
+        eval: &mut Evaluator<'v, '_>,
         stack: &mut BcStackPtr<'v, '_>,
         ip: BcPtrAddr<'b>,
         target: &BcAddrOffset,
     ) -> InstrControl<'v, 'b> {
         let cond = stack.pop();
-        if cond.to_bool() {
-            InstrControl::Next(ip.add_rel(*target))
-        } else {
-            InstrControl::Next(ip.add_instr::<Self>())
+        match cond.try_to_bool() {
+            Ok(true) => InstrControl::Next(ip.add_rel(*target)),
+            Ok(false) => InstrControl::Next(ip.add_instr::<Self>()),
+            Err(e) => InstrControl::Err(Bc::wrap_error_for_instr_ptr(ip, e, eval)),
         }
     }
 }
@@ -1258,21 +1313,24 @@ impl BcInstr for InstrIfNotBr {
 
     #[inline(always)]
     fn run<'v, 'b>(
-        _eval: &mut Evaluator<'v, '_>,
+        eval: &mut Evaluator<'v, '_>,
         stack: &mut BcStackPtr<'v, '_>,
         ip: BcPtrAddr<'b>,
         target: &BcAddrOffset,
     ) -> InstrControl<'v, 'b> {
         let cond = stack.pop();
-        if !cond.to_bool() {
-            InstrControl::Next(ip.add_rel(*target))
-        } else {
-            InstrControl::Next(ip.add_instr::<Self>())
+        match cond.try_to_bool() {
+            Ok(false) => InstrControl::Next(ip.add_rel(*target)),
+            Ok(true) => InstrControl::Next(ip.add_instr::<Self>()),
+            Err(e) => InstrControl::Err(Bc::wrap_error_for_instr_ptr(ip, e, eval)),
         }
     }
 }
 
 pub(crate) struct InstrForLoop;
+/// Loop with `else` block. Args are the start of `else` block, where the loop jumps
+/// after iterating the whole collection, and the end of `else` block for `break`.
+pub(crate) struct InstrForLoopElse;
 pub(crate) struct InstrBreak;
 pub(crate) struct InstrContinue;
 
@@ -1287,42 +1345,81 @@ impl BcInstr for InstrForLoop {
         ip: BcPtrAddr<'b>,
         loop_end: &BcAddrOffset,
     ) -> InstrControl<'v, 'b> {
-        let ss = stack.stack_offset();
+        run_for_loop::<Self>(eval, stack, ip, *loop_end, *loop_end)
+    }
+}
 
-        let collection = stack.pop();
+impl BcInstr for InstrForLoopElse {
+    type Pop<'v> = Value<'v>;
+    type Push<'v> = Value<'v>;
+    type Arg = (BcAddrOffset, BcAddrOffset);
 
-        enum LoopResult<'v> {
-            Ok,
-            Return(Value<'v>),
-            Err(EvalException),
-        }
+    fn run<'v, 'b>(
+        eval: &mut Evaluator<'v, '_>,
+        stack: &mut BcStackPtr<'v, '_>,
+        ip: BcPtrAddr<'b>,
+        (else_start, else_end): &(BcAddrOffset, BcAddrOffset),
+    ) -> InstrControl<'v, 'b> {
+        run_for_loop::<Self>(eval, stack, ip, *else_start, *else_end)
+    }
+}
 
-        let iter_ret = collection.with_iterator(eval.heap(), |iter| {
-            let loop_start = ip.add_instr::<Self>();
-            for item in iter {
-                stack.push(item);
-                debug_assert!(stack.stack_offset() == ss);
-                match run_block(eval, stack, loop_start) {
-                    RunBlockResult::Continue => {}
-                    RunBlockResult::Break => return LoopResult::Ok,
-                    RunBlockResult::Return(v) => return LoopResult::Return(v),
-                    RunBlockResult::Err(e) => return LoopResult::Err(e),
+/// Run the loop body following the instruction `I` for each element of the collection,
+/// then jump to `completed`, or to `broken` if the loop is stopped by `break`.
+#[inline(always)]
+fn run_for_loop<'v, 'b, I: BcInstr>(
+    eval: &mut Evaluator<'v, '_>,
+    stack: &mut BcStackPtr<'v, '_>,
+    ip: BcPtrAddr<'b>,
+    completed: BcAddrOffset,
+    broken: BcAddrOffset,
+) -> InstrControl<'v, 'b> {
+    let ss = stack.stack_offset();
+
+    let collection = stack.pop();
+
+    enum LoopResult<'v> {
+        Completed,
+        Broken,
+        Return(Value<'v>),
+        Err(EvalException),
+    }
+
+    let iter_ret = collection.with_iterator(eval.heap(), |iter| {
+        let loop_start = ip.add_instr::<I>();
+        for item in iter {
+            if let Some(max) = eval.max_object_count {
+                if eval.heap().allocated_count() > max {
+                    let e = EvaluatorError::ObjectCountExceeded(max);
+                    return LoopResult::Err(Bc::wrap_error_for_instr_ptr(ip, e.into(), eval));
                 }
             }
-            LoopResult::Ok
-        });
-        match iter_ret {
-            Ok(LoopResult::Ok) => {
-                debug_assert!(stack.stack_offset() + 1 == ss);
-                InstrControl::Next(ip.add_rel(*loop_end))
+            stack.push(item);
+            debug_assert!(stack.stack_offset() == ss);
+            match run_block(eval, stack, loop_start) {
+                RunBlockResult::Continue => {}
+                RunBlockResult::Break => return LoopResult::Broken,
+                RunBlockResult::Return(v) => return LoopResult::Return(v),
+                RunBlockResult::Err(e) => return LoopResult::Err(e),
             }
-            Ok(LoopResult::Return(v)) => {
-                debug_assert!(stack.stack_offset() + 1 == ss);
-                InstrControl::Return(v)
-            }
-            Ok(LoopResult::Err(e)) => InstrControl::Err(e),
-            Err(e) => InstrControl::Err(Bc::wrap_error_for_instr_ptr(ip, e, eval)),
         }
+        LoopResult::Completed
+    });
+    match iter_ret {
+        Ok(LoopResult::Completed) => {
+            debug_assert!(stack.stack_offset() + 1 == ss);
+            InstrControl::Next(ip.add_rel(completed))
+        }
+        Ok(LoopResult::Broken) => {
+            debug_assert!(stack.stack_offset() + 1 == ss);
+            InstrControl::Next(ip.add_rel(broken))
+        }
+        Ok(LoopResult::Return(v)) => {
+            debug_assert!(stack.stack_offset() + 1 == ss);
+            InstrControl::Return(v)
+        }
+        Ok(LoopResult::Err(e)) => InstrControl::Err(e),
+        Err(e) => InstrControl::Err(Bc::wrap_error_for_instr_ptr(ip, e, eval)),
     }
 }
 
@@ -1576,7 +1673,7 @@ impl InstrNoFlowAddSpanImpl for InstrCallImpl {
         (_pop1, args): &Self::Arg,
         _pops: (),
     ) -> Result<Value<'v>, anyhow::Error> {
-        let arguments = stack.pop_args(args);
+        let arguments = stack.pop_args(args, eval.heap());
         let f = stack.pop();
         f.invoke(Some(args.span), arguments, eval)
     }
@@ -1612,7 +1709,7 @@ impl<F: BcFrozenCallable> InstrNoFlowAddSpanImpl for InstrCallFrozenGenericImpl<
         (fun, args): &Self::Arg,
         _pops: (),
     ) -> Result<Value<'v>, anyhow::Error> {
-        let arguments = stack.pop_args(args);
+        let arguments = stack.pop_args(args, eval.heap());
         fun.bc_invoke(args.span, arguments, eval)
     }
 }
@@ -1634,10 +1731,52 @@ impl<F: BcFrozenCallable> InstrNoFlowAddSpanImpl for InstrCallFrozenGenericPosIm
     }
 }
 
+/// Name of the method called by `InstrCallMethod` or `InstrCallMethodPos`,
+/// with the method resolved in [`Methods`] of the receiver type.
+///
+/// Method call sites are usually monomorphic, so we remember the method found
+/// for the first type, and skip the lookup in its [`Methods`] when the call site
+/// is executed again with a value of the same type.
+pub(crate) struct MethodSymbol {
+    pub(crate) symbol: Symbol,
+    cache: OnceCell<(&'static Methods, FrozenValue)>,
+}
+
+impl MethodSymbol {
+    pub(crate) fn new(symbol: Symbol) -> MethodSymbol {
+        MethodSymbol {
+            symbol,
+            cache: OnceCell::new(),
+        }
+    }
+
+    /// Same as `get_attr_hashed_raw`, but uses the cache.
+    #[inline(always)]
+    fn get_method<'v>(&self, this: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        let aref = this.get_ref();
+        if let Some(methods) = aref.get_methods() {
+            if let Some((cached_methods, method)) = self.cache.get() {
+                if ptr::eq(*cached_methods, methods) {
+                    return Ok(method.to_value());
+                }
+            }
+            if let Some(method) = methods.get_frozen_symbol(&self.symbol) {
+                // Fails if the cache is already set for another type, that's fine.
+                let _ = self.cache.set((methods, method));
+                return Ok(method.to_value());
+            }
+        }
+        match aref.get_attr(self.symbol.as_str(), heap) {
+            None => Err(get_attr_no_attr_error(this, &self.symbol)),
+            Some(x) => Ok(x),
+        }
+    }
+}
+
 impl InstrNoFlowAddSpanImpl for InstrCallMethodImpl {
     type Pop<'v> = ();
     type Push<'v> = Value<'v>;
-    type Arg = (ArgPopsStack1, Symbol, ArgsCompiledValueBc);
+    type Arg = (ArgPopsStack1, MethodSymbol, ArgsCompiledValueBc);
 
     #[inline(always)]
     fn run_with_args<'v>(
@@ -1646,10 +1785,10 @@ impl InstrNoFlowAddSpanImpl for InstrCallMethodImpl {
         (_pop1, symbol, args): &Self::Arg,
         _pops: (),
     ) -> Result<Value<'v>, anyhow::Error> {
-        let arguments = stack.pop_args(args);
+        let arguments = stack.pop_args(args, eval.heap());
         let this = stack.pop();
         // TODO: wrong span: should be span of `object.method`, not of the whole expression
-        let method = get_attr_hashed_raw(this, symbol, eval.heap())?;
+        let method = symbol.get_method(this, eval.heap())?;
         method.invoke_method(this, Some(args.span), arguments, eval)
     }
 }
@@ -1657,7 +1796,7 @@ impl InstrNoFlowAddSpanImpl for InstrCallMethodImpl {
 impl InstrNoFlowAddSpanImpl for InstrCallMethodPosImpl {
     type Pop<'v> = ();
     type Push<'v> = Value<'v>;
-    type Arg = (ArgPopsStack1, ArgPopsStack, Symbol, Span);
+    type Arg = (ArgPopsStack1, ArgPopsStack, MethodSymbol, Span);
 
     #[inline(always)]
     fn run_with_args<'v>(
@@ -1669,11 +1808,35 @@ impl InstrNoFlowAddSpanImpl for InstrCallMethodPosImpl {
         let arguments = stack.pop_args_pos(*npops);
         let this = stack.pop();
         // TODO: wrong span: should be span of `object.method`, not of the whole expression
-        let method = get_attr_hashed_raw(this, symbol, eval.heap())?;
+        let method = symbol.get_method(this, eval.heap())?;
         method.invoke_method(this, Some(*span), arguments, eval)
     }
 }
 
+pub(crate) struct InstrAssertFailedImpl;
+pub(crate) type InstrAssertFailed = InstrNoFlowAddSpan<InstrAssertFailedImpl>;
+
+impl InstrNoFlowAddSpanImpl for InstrAssertFailedImpl {
+    // The message, or `None` if the `assert` statement has none.
+    type Pop<'v> = Value<'v>;
+    type Push<'v> = ();
+    type Arg = ();
+
+    #[cold]
+    fn run_with_args<'v>(
+        _eval: &mut Evaluator<'v, '_>,
+        _stack: &mut BcStackPtr<'v, '_>,
+        (): &(),
+        message: Value<'v>,
+    ) -> Result<(), anyhow::Error> {
+        Err(if message.is_none() {
+            EvaluatorError::AssertionFailed.into()
+        } else {
+            EvaluatorError::AssertionFailedWithMessage(message.to_str()).into()
+        })
+    }
+}
+
 pub(crate) struct InstrPossibleGcImpl;
 pub(crate) struct InstrBeforeStmtImpl;
 pub(crate) struct InstrProfileBcImpl;
@@ -1711,8 +1874,7 @@ impl InstrNoFlowImpl for InstrBeforeStmtImpl {
         span: &Span,
         (): (),
     ) -> Result<(), EvalException> {
-        before_stmt(*span, eval);
-        Ok(())
+        before_stmt(*span, eval).map_err(|e| add_span_to_expr_error(e, *span, eval))
     }
 }
 
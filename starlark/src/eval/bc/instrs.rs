@@ -32,7 +32,7 @@ use crate::{
     eval::bc::{
         addr::{BcAddr, BcAddrOffset, BcPtrAddr},
         instr::BcInstr,
-        instr_impl::{InstrEnd, InstrForLoop},
+        instr_impl::{InstrEnd, InstrForLoop, InstrForLoopElse},
         opcode::{BcOpcode, BcOpcodeHandler},
         repr::{BcInstrHeader, BcInstrRepr, BC_INSTR_ALIGN},
     },
@@ -236,6 +236,10 @@ impl BcInstrs {
                 let for_loop = ptr.get_instr::<InstrForLoop>();
                 loop_ends.push(ip.offset(for_loop.arg));
             }
+            if opcode == BcOpcode::ForLoopElse {
+                let for_loop = ptr.get_instr::<InstrForLoopElse>();
+                loop_ends.push(ip.offset(for_loop.arg.0));
+            }
         }
         Ok(())
     }
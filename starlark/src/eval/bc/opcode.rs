@@ -91,12 +91,14 @@ pub(crate) enum BcOpcode {
     DictNPop,
     DictOfConsts,
     DictConstKeys,
+    ComprListNewSized,
     ComprListAppend,
     ComprDictInsert,
     Br,
     IfBr,
     IfNotBr,
     ForLoop,
+    ForLoopElse,
     Break,
     Continue,
     Return,
@@ -112,6 +114,7 @@ pub(crate) enum BcOpcode {
     CallMethod,
     CallMethodPos,
     Def,
+    AssertFailed,
     PossibleGc,
     BeforeStmt,
     ProfileBc,
@@ -22,6 +22,7 @@ use std::{cell::Cell, marker, mem, mem::MaybeUninit, ptr, slice};
 use gazebo::coerce::coerce_ref;
 
 use crate::{
+    collections::SmallMap,
     eval::{
         bc::{
             compiler::call::ArgsCompiledValueBc,
@@ -30,9 +31,35 @@ use crate::{
         },
         Arguments, Evaluator,
     },
-    values::Value,
+    values::{dict::Dict, Heap, Value},
 };
 
+/// Convert a `**kwargs` argument which is a mapping, but not a dict, to a dict,
+/// using [`kwargs_items`](crate::values::StarlarkValue::kwargs_items).
+/// Any other value is returned unchanged, and rejected when the arguments are unpacked.
+fn kwargs_to_dict<'v>(kwargs: Value<'v>, heap: &'v Heap) -> Value<'v> {
+    #[cold]
+    #[inline(never)]
+    fn convert<'v>(kwargs: Value<'v>, heap: &'v Heap) -> Value<'v> {
+        match kwargs.get_ref().kwargs_items() {
+            None => kwargs,
+            Some(items) => {
+                let mut res = SmallMap::with_capacity(items.len());
+                for (k, v) in items {
+                    res.insert_hashed(heap.alloc_str_hashed(&k), v);
+                }
+                heap.alloc(Dict::new(res))
+            }
+        }
+    }
+
+    if Dict::from_value(kwargs).is_some() {
+        kwargs
+    } else {
+        convert(kwargs, heap)
+    }
+}
+
 /// Valid pointer range for the stack.
 ///
 /// Only used when debugging assertions enabled.
@@ -142,8 +169,16 @@ impl<'v, 's> BcStackPtr<'v, 's> {
         unsafe { ptr::read(self.ptr.get() as *const [Value; N]) }
     }
 
-    pub(crate) fn pop_args<'a>(&'a self, a: &'a ArgsCompiledValueBc) -> Arguments<'v, 'a> {
-        let kwargs = if a.kwargs { Some(self.pop()) } else { None };
+    pub(crate) fn pop_args<'a>(
+        &'a self,
+        a: &'a ArgsCompiledValueBc,
+        heap: &'v Heap,
+    ) -> Arguments<'v, 'a> {
+        let kwargs = if a.kwargs {
+            Some(kwargs_to_dict(self.pop(), heap))
+        } else {
+            None
+        };
         let args = if a.args { Some(self.pop()) } else { None };
         let pos_named = self.pop_slice(ArgPopsStack(a.pos_named));
         let (pos, named) = pos_named.split_at(pos_named.len() - a.names.len());
@@ -20,7 +20,7 @@
 use std::{
     cmp,
     fmt::{Debug, Display},
-    mem,
+    mem, ptr,
 };
 
 use crate::{
@@ -32,8 +32,9 @@ use crate::{
             instr::BcInstr,
             instr_impl::{
                 InstrBr, InstrConst, InstrConst2, InstrConst3, InstrConst4, InstrContinue,
-                InstrForLoop, InstrIfBr, InstrIfNotBr, InstrLoadLocal, InstrLoadLocal2,
-                InstrLoadLocal3, InstrLoadLocal4, InstrLoadLocalAndConst, InstrProfileBc,
+                InstrForLoop, InstrForLoopElse, InstrIfBr, InstrIfNotBr, InstrLoadLocal,
+                InstrLoadLocal2, InstrLoadLocal3, InstrLoadLocal4, InstrLoadLocalAndConst,
+                InstrProfileBc,
             },
             instrs::{BcInstrsWriter, PatchAddr},
             opcode::BcOpcode,
@@ -309,6 +310,39 @@ impl BcWriter {
         self.patch_addr(end_patch);
     }
 
+    /// Write for loop with `else` block, which is executed when the loop is not stopped
+    /// by `break`.
+    pub(crate) fn write_for_else(
+        &mut self,
+        span: Span,
+        body: impl FnOnce(&mut Self),
+        else_block: impl FnOnce(&mut Self),
+    ) {
+        let arg = self.write_instr_ret_arg::<InstrForLoopElse>(
+            span,
+            (BcAddrOffset::FORWARD, BcAddrOffset::FORWARD),
+        );
+        let (instr_start, arg) = arg;
+        let (else_patch, end_patch) = unsafe {
+            (
+                self.instrs
+                    .addr_to_patch((instr_start, ptr::addr_of!((*arg).0))),
+                self.instrs
+                    .addr_to_patch((instr_start, ptr::addr_of!((*arg).1))),
+            )
+        };
+        let ss = self.stack_size();
+        body(self);
+        assert!(
+            self.stack_size() + 1 == ss,
+            "Loop body must consume stack variable"
+        );
+        self.write_instr::<InstrContinue>(span, ());
+        self.patch_addr(else_patch);
+        else_block(self);
+        self.patch_addr(end_patch);
+    }
+
     fn stack_add(&mut self, add: u32) {
         self.stack_size += add;
         self.max_stack_size = cmp::max(self.max_stack_size, self.stack_size);
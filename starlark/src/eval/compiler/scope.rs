@@ -22,6 +22,7 @@ use indexmap::map::IndexMap;
 
 use crate::{
     codemap::CodeMap,
+    collections::SmallSet,
     environment::{names::MutableNames, slots::ModuleSlotId, EnvironmentError, Globals, Module},
     errors::{did_you_mean::did_you_mean, Diagnostic},
     eval::runtime::slots::LocalSlotId,
@@ -34,7 +35,7 @@ use crate::{
         payload_map::AstPayloadFunction,
         uniplate::VisitMut,
     },
-    values::{FrozenRef, FrozenValue},
+    values::{FrozenHeap, FrozenRef, FrozenValue},
 };
 
 pub(crate) struct Scope<'a> {
@@ -47,7 +48,13 @@ pub(crate) struct Scope<'a> {
     unscopes: Vec<Unscope>,
     codemap: CodeMap,
     globals: FrozenRef<Globals>,
+    // Heap for globals produced by the dynamic resolver of `globals`
+    frozen_heap: &'a FrozenHeap,
+    // Globals produced by the dynamic resolver so far
+    dynamic_globals: HashMap<String, Option<FrozenValue>>,
     pub(crate) errors: Vec<anyhow::Error>,
+    // Names resolved to globals, in the order of first reference.
+    pub(crate) used_globals: SmallSet<String>,
 }
 
 struct UnscopeBinding {
@@ -168,6 +175,7 @@ impl<'a> Scope<'a> {
         mut scope_data: ScopeData,
         code: &mut CstStmt,
         globals: FrozenRef<Globals>,
+        frozen_heap: &'a FrozenHeap,
         codemap: CodeMap,
     ) -> Self {
         // Not really important, sanity check
@@ -203,7 +211,10 @@ impl<'a> Scope<'a> {
             unscopes: Vec::new(),
             codemap,
             globals,
+            frozen_heap,
+            dynamic_globals: HashMap::new(),
             errors: Vec::new(),
+            used_globals: SmallSet::new(),
         };
         scope.resolve_idents(code);
         scope
@@ -365,17 +376,34 @@ impl<'a> Scope<'a> {
         )
     }
 
+    fn get_global(&mut self, name: &str) -> Option<FrozenValue> {
+        if let Some(v) = self.globals.get_frozen(name) {
+            return Some(v);
+        }
+        if let Some(v) = self.dynamic_globals.get(name) {
+            return *v;
+        }
+        let v = self.globals.resolve_dynamic(name, self.frozen_heap);
+        self.dynamic_globals.insert(name.to_owned(), v);
+        v
+    }
+
     fn resolve_ident(&mut self, ident: &AstString, resolved_ident: &mut Option<ResolvedIdent>) {
         assert!(resolved_ident.is_none());
         *resolved_ident = Some(match self.get_name(ident) {
             None => {
                 // Must be a global, since we know all variables
-                match self.globals.get_frozen(ident) {
+                match self.get_global(ident) {
                     None => {
                         self.errors.push(self.variable_not_found_err(ident));
                         return;
                     }
-                    Some(v) => ResolvedIdent::Global(v),
+                    Some(v) => {
+                        if !self.used_globals.contains(ident.node.as_str()) {
+                            self.used_globals.insert(ident.node.clone());
+                        }
+                        ResolvedIdent::Global(v)
+                    }
                 }
             }
             Some(slot) => ResolvedIdent::Slot(slot),
@@ -516,9 +544,12 @@ impl Stmt {
             StmtP::Assign(dest, _) | StmtP::AssignModify(dest, _, _) => {
                 Assign::collect_defines_lvalue(dest, in_loop, scope_data, result);
             }
-            StmtP::For(dest, box (_, body)) => {
+            StmtP::For(dest, box (_, body, else_block)) => {
                 Assign::collect_defines_lvalue(dest, InLoop::Yes, scope_data, result);
                 StmtP::collect_defines(body, InLoop::Yes, scope_data, result);
+                if let Some(else_block) = else_block {
+                    StmtP::collect_defines(else_block, in_loop, scope_data, result);
+                }
             }
             StmtP::Def(name, ..) => AssignIdent::collect_assign_ident(
                 name,
@@ -800,13 +831,14 @@ mod test {
             uniplate::Visit,
             AstModule, Dialect,
         },
-        values::FrozenRef,
+        values::{FrozenHeap, FrozenRef},
     };
 
     fn test_with_module(program: &str, expected: &str, module: &MutableNames) {
         let ast = AstModule::parse("t.star", program.to_owned(), &Dialect::Extended).unwrap();
         let mut scope_data = ScopeData::new();
         let root_scope_id = scope_data.new_scope().0;
+        let frozen_heap = FrozenHeap::new();
         let mut cst = ast
             .statement
             .into_map_payload(&mut CompilerAstMap(&mut scope_data));
@@ -816,6 +848,7 @@ mod test {
             scope_data,
             &mut cst,
             FrozenRef::new(Globals::empty()),
+            &frozen_heap,
             ast.codemap,
         );
         assert!(scope.errors.is_empty());
@@ -17,11 +17,16 @@
 
 //! Compile function calls.
 
-use gazebo::{coerce::coerce, prelude::*};
+use std::mem;
+
+use gazebo::{coerce::coerce, prelude::*, variants::VariantName};
+use thiserror::Error;
 
 use crate::{
+    analysis::types::{LintT, LintWarning},
     codemap::{Span, Spanned},
     collections::symbol_map::Symbol,
+    environment::Module,
     eval::{
         compiler::{
             scope::{CstArgument, CstExpr},
@@ -32,16 +37,29 @@ use crate::{
             expr::{ExprCompiledValue, MaybeNot},
             stmt::OptimizeOnFreezeContext,
         },
-        Arguments, FrozenDef,
+        Arguments, Evaluator, FrozenDef,
     },
     gazebo::prelude::SliceExt,
     syntax::ast::{ArgumentP, AstString, ExprP},
     values::{
-        function::NativeFunction, string::interpolation::parse_format_one, FrozenStringValue,
-        FrozenValue, FrozenValueTyped, StarlarkValue, ValueLike,
+        function::NativeFunction,
+        string::{interpolation::parse_format_one, repr::repr_ascii_guard},
+        FrozenHeap, FrozenStringValue, FrozenValue, FrozenValueTyped, StarlarkValue, ValueLike,
     },
 };
 
+#[derive(Error, Debug, VariantName)]
+pub(crate) enum CallWarning {
+    #[error("Function `{0}` is marked `speculative_exec_safe`, but attempted {1}")]
+    SpeculativeExecUnsafe(String, String),
+}
+
+impl LintWarning for CallWarning {
+    fn is_serious(&self) -> bool {
+        true
+    }
+}
+
 #[derive(Default, Clone, Debug)]
 pub(crate) struct ArgsCompiledValue {
     pub(crate) pos_named: Vec<Spanned<ExprCompiledValue>>,
@@ -60,24 +78,107 @@ pub(crate) enum CallCompiled {
     Method(Box<(Spanned<ExprCompiledValue>, Symbol, ArgsCompiledValue)>),
 }
 
+impl CallCompiled {
+    pub(crate) fn visit_exprs<'a>(&'a self, f: &mut dyn FnMut(&'a Spanned<ExprCompiledValue>)) {
+        match self {
+            CallCompiled::Call(box (fun, args)) => {
+                f(fun);
+                args.visit_exprs(f);
+            }
+            CallCompiled::Method(box (this, _, args)) => {
+                f(this);
+                args.visit_exprs(f);
+            }
+        }
+    }
+}
+
 impl Spanned<CallCompiled> {
     pub(crate) fn optimize_on_freeze(&self, ctx: &OptimizeOnFreezeContext) -> ExprCompiledValue {
-        ExprCompiledValue::Call(self.map(|call| match *call {
+        match self.node {
             CallCompiled::Call(box (ref fun, ref args)) => {
                 let fun = fun.optimize_on_freeze(ctx);
                 let args = args.optimize_on_freeze(ctx);
-                CallCompiled::Call(box (fun, args))
+                // Module globals referenced by the function are now known,
+                // so the call may have become speculatively executable.
+                if let Some(native) = fun
+                    .as_value()
+                    .filter(|_| ctx.options.fold_constants)
+                    .and_then(speculative_exec_safe_native)
+                {
+                    let module = Module::new();
+                    let mut eval = Evaluator::new(&module);
+                    // Use the configuration of the evaluator which compiled the function.
+                    eval.repr_ascii = ctx.compile_context.repr_ascii;
+                    // There is no evaluator to report a warning to when freezing,
+                    // a mis-annotated function is just not folded.
+                    if let Ok(Some(v)) =
+                        speculative_exec(self.span, native, &args, &mut eval, ctx.heap)
+                    {
+                        return v;
+                    }
+                }
+                ExprCompiledValue::Call(Spanned {
+                    span: self.span,
+                    node: CallCompiled::Call(box (fun, args)),
+                })
             }
             CallCompiled::Method(box (ref this, ref field, ref args)) => {
                 let this = this.optimize_on_freeze(ctx);
                 let field = field.clone();
                 let args = args.optimize_on_freeze(ctx);
-                CallCompiled::Method(box (this, field, args))
+                ExprCompiledValue::Call(Spanned {
+                    span: self.span,
+                    node: CallCompiled::Method(box (this, field, args)),
+                })
             }
-        }))
+        }
     }
 }
 
+/// Native function marked `speculative_exec_safe`, if `fun` is one.
+fn speculative_exec_safe_native(
+    fun: FrozenValue,
+) -> Option<FrozenValueTyped<'static, NativeFunction>> {
+    FrozenValueTyped::<NativeFunction>::new(fun).filter(|fun| fun.speculative_exec_safe)
+}
+
+/// Call a native function now if all call arguments are frozen values,
+/// and convert the result to a constant expression if possible.
+///
+/// The result is discarded if the call fails
+/// or if the result cannot be copied to the frozen `heap`.
+/// Mutable results are never shared between evaluations of the expression:
+/// a list is rebuilt each time, and other mutable values are discarded.
+/// If the function attempted an operation not allowed during speculative execution,
+/// the result is discarded too, and a warning about the wrong annotation is returned.
+fn speculative_exec<'v>(
+    span: Span,
+    fun: FrozenValueTyped<'static, NativeFunction>,
+    args: &ArgsCompiledValue,
+    eval: &mut Evaluator<'v, '_>,
+    heap: &FrozenHeap,
+) -> Result<Option<ExprCompiledValue>, CallWarning> {
+    args.all_values(|arguments| {
+        // Outside of evaluation (i.e. on freeze) the setting is not applied yet.
+        let _repr_ascii = repr_ascii_guard(eval.repr_ascii);
+        let prev = eval.speculative_exec.replace(Vec::new());
+        let v = fun.invoke(fun.to_value(), None, arguments, eval);
+        let violations = mem::replace(&mut eval.speculative_exec, prev).unwrap_or_default();
+        if !violations.is_empty() {
+            return Err(CallWarning::SpeculativeExecUnsafe(
+                fun.to_value().to_string(),
+                violations.join(", "),
+            ));
+        }
+        match v {
+            Ok(v) => Ok(ExprCompiledValue::try_value(span, v, heap)),
+            Err(_) => Ok(None),
+        }
+    })
+    .unwrap_or(Ok(None))
+}
+
 impl ArgsCompiledValue {
     pub(crate) fn pos_only(&self) -> Option<&[Spanned<ExprCompiledValue>]> {
         if self.names.is_empty() && self.args.is_none() && self.kwargs.is_none() {
@@ -87,6 +188,12 @@ impl ArgsCompiledValue {
         }
     }
 
+    pub(crate) fn visit_exprs<'a>(&'a self, f: &mut dyn FnMut(&'a Spanned<ExprCompiledValue>)) {
+        self.pos_named.iter().for_each(&mut *f);
+        self.args.iter().for_each(&mut *f);
+        self.kwargs.iter().for_each(f);
+    }
+
     fn split_pos_names(&self) -> (&[Spanned<ExprCompiledValue>], &[Spanned<ExprCompiledValue>]) {
         self.pos_named
             .as_slice()
@@ -167,17 +274,15 @@ impl Compiler<'_, '_, '_> {
     ) -> ExprCompiledValue {
         let args = self.args(args);
 
-        if let Some(fun) = FrozenValueTyped::<NativeFunction>::new(fun) {
-            // Try execute the native function speculatively.
-            if fun.speculative_exec_safe {
-                // Only if all call arguments are frozen values.
-                if let Some(Some(v)) = args.all_values(|arguments| {
-                    let v = fun
-                        .invoke(fun.to_value(), None, arguments, self.eval)
-                        .ok()?;
-                    ExprCompiledValue::try_value(span, v, self.eval.module_env.frozen_heap())
-                }) {
-                    return v;
+        // Try execute the native function speculatively.
+        if let Some(native) = speculative_exec_safe_native(fun) {
+            let module_env = self.eval.module_env;
+            match speculative_exec(span, native, &args, self.eval, module_env.frozen_heap()) {
+                Ok(Some(v)) => return v,
+                Ok(None) => {}
+                Err(warning) => {
+                    let warning = LintT::new(&self.codemap, span, warning).erase();
+                    self.eval.warnings.push(warning);
                 }
             }
         }
@@ -205,7 +310,7 @@ impl Compiler<'_, '_, '_> {
             self.fn_type(args.pop().unwrap().node.into_expr())
         } else if left == self.constants.fn_len && one_positional {
             let x = self.expr(args.pop().unwrap().node.into_expr());
-            ExprCompiledValue::Len(box x)
+            ExprCompiledValue::len(x)
         } else {
             if one_positional {
                 // Try to inline a function like `lambda x: type(x) == "y"`.
@@ -132,6 +132,23 @@ pub(crate) enum ComprCompiled {
 }
 
 impl ComprCompiled {
+    pub(crate) fn visit_exprs<'a>(&'a self, f: &mut dyn FnMut(&'a Spanned<ExprCompiledValue>)) {
+        let clauses = match self {
+            ComprCompiled::List(box x, clauses) => {
+                f(x);
+                clauses
+            }
+            ComprCompiled::Dict(box (k, v), clauses) => {
+                f(k);
+                f(v);
+                clauses
+            }
+        };
+        for c in clauses {
+            c.visit_exprs(f);
+        }
+    }
+
     pub(crate) fn optimize_on_freeze(&self, ctx: &OptimizeOnFreezeContext) -> ExprCompiledValue {
         ExprCompiledValue::Compr(match self {
             ComprCompiled::List(box ref x, ref clauses) => ComprCompiled::List(
@@ -155,6 +172,12 @@ pub(crate) struct ClauseCompiled {
 }
 
 impl ClauseCompiled {
+    fn visit_exprs<'a>(&'a self, f: &mut dyn FnMut(&'a Spanned<ExprCompiledValue>)) {
+        self.var.node.visit_exprs(f);
+        f(&self.over);
+        self.ifs.iter().for_each(f);
+    }
+
     fn optimize_on_freeze(&self, ctx: &OptimizeOnFreezeContext) -> ClauseCompiled {
         let ClauseCompiled {
             ref var,
@@ -21,6 +21,7 @@ use std::{
     cell::UnsafeCell,
     collections::HashMap,
     fmt::{self, Display, Write},
+    intrinsics::unlikely,
     mem, ptr,
 };
 
@@ -42,7 +43,10 @@ use crate::{
         },
         fragment::{
             expr::{ExprCompiledValue, MaybeNot},
-            stmt::{OptimizeOnFreezeContext, StmtCompileContext, StmtCompiledValue, StmtsCompiled},
+            stmt::{
+                OptimizeOnFreezeContext, OptimizeOptions, StmtCompileContext, StmtCompiledValue,
+                StmtsCompiled,
+            },
         },
         runtime::{
             arguments::{ParameterKind, ParametersSpec},
@@ -55,7 +59,7 @@ use crate::{
     values::{
         docs,
         docs::{DocItem, DocString},
-        function::FUNCTION_TYPE,
+        function::{collect_function_repr, FUNCTION_TYPE},
         typing::TypeCompiled,
         AtomicFrozenRefOption, Freeze, Freezer, FrozenHeap, FrozenRef, FrozenStringValue,
         FrozenValue, StarlarkValue, Trace, Tracer, Value, ValueLike,
@@ -225,6 +229,28 @@ pub(crate) struct DefCompiled {
     pub(crate) info: FrozenRef<DefInfo>,
 }
 
+impl DefCompiled {
+    /// Visit the parameter types and defaults and the return type, but not the body.
+    pub(crate) fn visit_exprs<'a>(&'a self, f: &mut dyn FnMut(&'a Spanned<ExprCompiledValue>)) {
+        for param in &self.params {
+            match &param.node {
+                ParameterCompiled::WithDefaultValue(_, ty, default) => {
+                    ty.iter().for_each(&mut *f);
+                    f(default);
+                }
+                p => {
+                    if let Some(ty) = p.ty() {
+                        f(ty);
+                    }
+                }
+            }
+        }
+        if let Some(return_type) = &self.return_type {
+            f(return_type);
+        }
+    }
+}
+
 /// Function body suitable for inlining.
 #[derive(Debug)]
 pub(crate) enum InlineDefBody {
@@ -580,6 +606,9 @@ where
         args: Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
+        if unlikely(eval.speculative_exec.is_some()) {
+            eval.speculative_exec_violation(|| format!("call to `{}`", self))?;
+        }
         let local_slots = self.def_info.scope_names.used.len() as u32;
         let slot_base = eval.local_variables.reserve(local_slots);
         let slots = eval.local_variables.get_slots_at(slot_base);
@@ -587,6 +616,14 @@ where
         eval.with_call_stack(me, location, |eval| self.invoke_raw(slot_base, eval))
     }
 
+    fn collect_repr(&self, collector: &mut String) {
+        let docs = match self.docs() {
+            Some(DocItem::Function(docs)) => Some(docs),
+            _ => None,
+        };
+        collect_function_repr(&self.to_string(), docs.as_ref(), collector)
+    }
+
     fn documentation(&self) -> Option<DocItem> {
         self.docs()
     }
@@ -680,7 +717,18 @@ where
 }
 
 impl FrozenDef {
-    pub(crate) fn post_freeze(&self, module: FrozenRef<FrozenModuleRef>, heap: &FrozenHeap) {
+    /// Visit the expressions used directly by the statements of the function body,
+    /// as compiled before [`post_freeze`](FrozenDef::post_freeze) optimizations.
+    pub(crate) fn visit_exprs<'a>(&'a self, f: &mut dyn FnMut(&'a Spanned<ExprCompiledValue>)) {
+        self.def_info.body_stmts.visit_exprs(f)
+    }
+
+    pub(crate) fn post_freeze(
+        &self,
+        module: FrozenRef<FrozenModuleRef>,
+        heap: &FrozenHeap,
+        options: &OptimizeOptions,
+    ) {
         // Module passed to this function is not always module where the function is declared:
         // A function can be created in a frozen module and frozen later in another module.
         // `def_module` variable contains a module where this `def` is declared.
@@ -700,6 +748,8 @@ impl FrozenDef {
             .optimize_on_freeze(&OptimizeOnFreezeContext {
                 module: def_module.as_ref(),
                 heap,
+                options,
+                compile_context: &self.def_info.stmt_compile_context,
             })
             .as_bc(&self.def_info.stmt_compile_context);
 
@@ -16,9 +16,15 @@
  */
 
 //! Evaluation of an expression.
-use std::cmp::Ordering;
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    convert::TryInto,
+    fmt,
+    fmt::{Debug, Formatter},
+};
 
-use gazebo::prelude::*;
+use gazebo::{prelude::*, variants::VariantName};
 use thiserror::Error;
 
 use crate::{
@@ -36,16 +42,22 @@ use crate::{
             stmt::OptimizeOnFreezeContext,
         },
         runtime::slots::LocalSlotId,
+        FrozenDef,
     },
     syntax::ast::{AstExprP, AstLiteral, AstPayload, AstString, BinOp, ExprP, StmtP},
     values::{
         function::BoundMethodGen,
         string::interpolation::parse_percent_s_one,
         types::{
-            float::StarlarkFloat, list::List, range::Range, tuple::Tuple,
+            dict::FrozenDict,
+            float::StarlarkFloat,
+            list::{FrozenList, List},
+            range::Range,
+            tuple::{FrozenTuple, Tuple},
             unbound::MaybeUnboundValue,
         },
-        FrozenHeap, FrozenStringValue, FrozenValue, Heap, Value, ValueError, ValueLike,
+        FrozenHeap, FrozenStringValue, FrozenValue, FrozenValueTyped, Heap, Value, ValueError,
+        ValueLike,
     },
 };
 
@@ -109,7 +121,7 @@ pub(crate) enum ExprBinOp {
     RightShift,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, VariantName)]
 pub(crate) enum ExprCompiledValue {
     Value(FrozenValue),
     /// Read local non-captured variable.
@@ -193,10 +205,118 @@ impl ExprCompiledValue {
         }
     }
 
+    /// Is expression a constant with a defined truth value?
+    /// Constants whose truth value is an error are not folded,
+    /// so the error is reported when the expression is evaluated.
+    pub(crate) fn as_value_with_bool(&self) -> Option<(FrozenValue, bool)> {
+        let x = self.as_value()?;
+        Some((x, x.to_value().try_to_bool().ok()?))
+    }
+
     /// Is expression a constant string?
     pub(crate) fn as_string(&self) -> Option<FrozenStringValue> {
         FrozenStringValue::new(self.as_value()?)
     }
+
+    /// Visit the expressions this expression is directly made of.
+    /// The body of a nested `def` is not visited.
+    pub(crate) fn visit_exprs<'a>(&'a self, f: &mut dyn FnMut(&'a Spanned<ExprCompiledValue>)) {
+        match self {
+            ExprCompiledValue::Value(..)
+            | ExprCompiledValue::Local(..)
+            | ExprCompiledValue::LocalCaptured(..)
+            | ExprCompiledValue::Module(..) => {}
+            ExprCompiledValue::Equals(box (l, r), _)
+            | ExprCompiledValue::Compare(box (l, r), _)
+            | ExprCompiledValue::ArrayIndirection(box (l, r))
+            | ExprCompiledValue::And(box (l, r))
+            | ExprCompiledValue::Or(box (l, r))
+            | ExprCompiledValue::Op(_, box (l, r)) => {
+                f(l);
+                f(r);
+            }
+            ExprCompiledValue::Type(box x)
+            | ExprCompiledValue::Len(box x)
+            | ExprCompiledValue::TypeIs(box x, _, _)
+            | ExprCompiledValue::Dot(box x, _)
+            | ExprCompiledValue::Not(box x)
+            | ExprCompiledValue::Minus(box x)
+            | ExprCompiledValue::Plus(box x)
+            | ExprCompiledValue::BitNot(box x)
+            | ExprCompiledValue::PercentSOne(box (_, x, _))
+            | ExprCompiledValue::FormatOne(box (_, x, _)) => f(x),
+            ExprCompiledValue::Tuple(xs) | ExprCompiledValue::List(xs) => xs.iter().for_each(f),
+            ExprCompiledValue::Dict(xs) => {
+                for (k, v) in xs {
+                    f(k);
+                    f(v);
+                }
+            }
+            ExprCompiledValue::Compr(compr) => compr.visit_exprs(f),
+            ExprCompiledValue::If(box (cond, t, e)) => {
+                f(cond);
+                f(t);
+                f(e);
+            }
+            ExprCompiledValue::Slice(box (x, start, stop, stride)) => {
+                f(x);
+                start.iter().for_each(&mut *f);
+                stop.iter().for_each(&mut *f);
+                stride.iter().for_each(f);
+            }
+            ExprCompiledValue::Call(call) => call.node.visit_exprs(f),
+            ExprCompiledValue::Def(def) => def.visit_exprs(f),
+        }
+    }
+}
+
+/// Read-only view of an expression in the compiled representation of a function body,
+/// to experiment with analyses and optimizations outside of this crate.
+///
+/// This API is unstable: the compiled representation changes with the optimizer,
+/// so the kinds of expressions and the shape of the tree may change in any release.
+#[derive(Clone, Copy, Dupe)]
+pub struct CompiledExpr<'a>(&'a Spanned<ExprCompiledValue>);
+
+impl<'a> Debug for CompiledExpr<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0.node, f)
+    }
+}
+
+impl<'a> CompiledExpr<'a> {
+    /// Visit the expressions used directly by the statements of a frozen `def` or `lambda`,
+    /// as compiled before the optimizations done on freeze.
+    /// Returns `false` if the value is not a function defined in Starlark.
+    pub fn visit_def(def: FrozenValue, f: &mut dyn FnMut(CompiledExpr)) -> bool {
+        match FrozenValueTyped::<FrozenDef>::new(def) {
+            None => false,
+            Some(def) => {
+                def.as_ref().visit_exprs(&mut |x| f(CompiledExpr(x)));
+                true
+            }
+        }
+    }
+
+    /// Kind of the expression, e.g. `Call` or `Value` for a constant.
+    pub fn kind(&self) -> &'static str {
+        self.0.node.variant_name()
+    }
+
+    /// The value of a constant expression.
+    pub fn as_value(&self) -> Option<FrozenValue> {
+        self.0.node.as_value()
+    }
+
+    /// Location of the expression in the file where the function is defined.
+    pub fn span(&self) -> Span {
+        self.0.span
+    }
+
+    /// Visit the expressions this expression is directly made of.
+    pub fn visit_exprs(&self, f: &mut dyn FnMut(CompiledExpr<'a>)) {
+        self.0.node.visit_exprs(&mut |x| f(CompiledExpr(x)))
+    }
 }
 
 impl Spanned<ExprCompiledValue> {
@@ -209,6 +329,9 @@ impl Spanned<ExprCompiledValue> {
             ref e @ (ExprCompiledValue::Value(..)
             | ExprCompiledValue::Local(..)
             | ExprCompiledValue::LocalCaptured(..)) => e.clone(),
+            ExprCompiledValue::Module(slot) if !ctx.options.inline_module_variables => {
+                ExprCompiledValue::Module(slot)
+            }
             ExprCompiledValue::Module(slot) => {
                 match ctx.module.get_module_data().get_slot(slot) {
                     None => {
@@ -231,9 +354,7 @@ impl Spanned<ExprCompiledValue> {
             ExprCompiledValue::Type(box ref e) => {
                 ExprCompiledValue::Type(box e.optimize_on_freeze(ctx))
             }
-            ExprCompiledValue::Len(box ref e) => {
-                ExprCompiledValue::Len(box e.optimize_on_freeze(ctx))
-            }
+            ExprCompiledValue::Len(box ref e) => ExprCompiledValue::len(e.optimize_on_freeze(ctx)),
             ExprCompiledValue::TypeIs(box ref e, t, maybe_not) => {
                 ExprCompiledValue::TypeIs(box e.optimize_on_freeze(ctx), t, maybe_not)
             }
@@ -295,7 +416,7 @@ impl Spanned<ExprCompiledValue> {
             ExprCompiledValue::Op(op, box (ref l, ref r)) => {
                 let l = l.optimize_on_freeze(ctx);
                 let r = r.optimize_on_freeze(ctx);
-                ExprCompiledValue::Op(op, box (l, r))
+                ExprCompiledValue::op(op, l, r, ctx.heap)
             }
             ExprCompiledValue::PercentSOne(box (before, ref arg, after)) => {
                 let arg = arg.optimize_on_freeze(ctx);
@@ -314,18 +435,15 @@ impl Spanned<ExprCompiledValue> {
 
 impl ExprCompiledValue {
     fn not(span: Span, expr: Spanned<ExprCompiledValue>) -> Spanned<ExprCompiledValue> {
-        match expr {
-            Spanned {
-                node: ExprCompiledValue::Value(x),
-                ..
-            } => Spanned {
-                node: value!(FrozenValue::new_bool(!x.to_value().to_bool())),
+        if let Some((_, x)) = expr.as_value_with_bool() {
+            return Spanned {
+                node: value!(FrozenValue::new_bool(!x)),
                 span,
-            },
-            expr => Spanned {
-                node: ExprCompiledValue::Not(box expr),
-                span,
-            },
+            };
+        }
+        Spanned {
+            node: ExprCompiledValue::Not(box expr),
+            span,
         }
     }
 
@@ -334,8 +452,8 @@ impl ExprCompiledValue {
         r: Spanned<ExprCompiledValue>,
     ) -> Spanned<ExprCompiledValue> {
         let l_span = l.span;
-        if let Some(l) = l.as_value() {
-            if l.to_value().to_bool() {
+        if let Some((l, l_bool)) = l.as_value_with_bool() {
+            if l_bool {
                 Spanned {
                     node: value!(l),
                     span: l_span,
@@ -357,8 +475,8 @@ impl ExprCompiledValue {
         r: Spanned<ExprCompiledValue>,
     ) -> Spanned<ExprCompiledValue> {
         let l_span = l.span;
-        if let Some(l) = l.as_value() {
-            if !l.to_value().to_bool() {
+        if let Some((l, l_bool)) = l.as_value_with_bool() {
+            if !l_bool {
                 Spanned {
                     node: value!(l),
                     span: l_span,
@@ -380,17 +498,10 @@ impl ExprCompiledValue {
         t: Spanned<ExprCompiledValue>,
         f: Spanned<ExprCompiledValue>,
     ) -> Spanned<ExprCompiledValue> {
+        if let Some((_, cond)) = cond.as_value_with_bool() {
+            return if cond { t } else { f };
+        }
         match cond {
-            Spanned {
-                node: ExprCompiledValue::Value(cond),
-                ..
-            } => {
-                if cond.to_value().to_bool() {
-                    t
-                } else {
-                    f
-                }
-            }
             Spanned {
                 node: ExprCompiledValue::Not(box cond),
                 ..
@@ -416,6 +527,94 @@ impl ExprCompiledValue {
         }
     }
 
+    /// Binary operation, computed now if both operands are constant numbers.
+    /// Operations which fail (e.g. division by zero) are left to fail at runtime.
+    fn op(
+        op: ExprBinOp,
+        l: Spanned<ExprCompiledValue>,
+        r: Spanned<ExprCompiledValue>,
+        heap: &FrozenHeap,
+    ) -> ExprCompiledValue {
+        if let (Some(lv), Some(rv)) = (l.as_value(), r.as_value()) {
+            let span = l.span.merge(r.span);
+            if let Some(v) = Self::try_op(op, lv, rv, span, heap) {
+                return v;
+            }
+        }
+        ExprCompiledValue::Op(op, box (l, r))
+    }
+
+    fn try_op(
+        op: ExprBinOp,
+        l: FrozenValue,
+        r: FrozenValue,
+        span: Span,
+        heap: &FrozenHeap,
+    ) -> Option<ExprCompiledValue> {
+        let (l, r) = (l.to_value(), r.to_value());
+        // Other values may produce large results, or have side effects.
+        l.unpack_num()?;
+        r.unpack_num()?;
+        let temp = Heap::new();
+        let v = match op {
+            ExprBinOp::In | ExprBinOp::NotIn => return None,
+            ExprBinOp::Sub => l.sub(r, &temp),
+            ExprBinOp::Add => l.add(r, &temp),
+            ExprBinOp::Multiply => l.mul(r, &temp),
+            ExprBinOp::Percent => l.percent(r, &temp),
+            ExprBinOp::Divide => l.div(r, &temp),
+            ExprBinOp::FloorDivide => l.floor_div(r, &temp),
+            ExprBinOp::BitAnd => l.bit_and(r),
+            ExprBinOp::BitOr => l.bit_or(r),
+            ExprBinOp::BitXor => l.bit_xor(r),
+            ExprBinOp::LeftShift => l.left_shift(r),
+            ExprBinOp::RightShift => l.right_shift(r),
+        };
+        Self::try_value(span, v.ok()?, heap)
+    }
+
+    /// `len(expr)`, computed now if `expr` is a builtin container or a literal
+    /// with all items constant.
+    pub(crate) fn len(expr: Spanned<ExprCompiledValue>) -> ExprCompiledValue {
+        match Self::try_len(&expr.node) {
+            Some(len) => value!(FrozenValue::new_int(len)),
+            None => ExprCompiledValue::Len(box expr),
+        }
+    }
+
+    fn try_len(expr: &ExprCompiledValue) -> Option<i32> {
+        match expr {
+            ExprCompiledValue::Value(v) => {
+                // Other values may implement `length` with side effects or errors.
+                let v = *v;
+                if v.unpack_str().is_some()
+                    || v.downcast_ref::<FrozenTuple>().is_some()
+                    || v.downcast_ref::<FrozenList>().is_some()
+                    || v.downcast_ref::<FrozenDict>().is_some()
+                {
+                    v.to_value().length().ok()
+                } else {
+                    None
+                }
+            }
+            ExprCompiledValue::List(xs) if xs.iter().all(|x| x.as_value().is_some()) => {
+                xs.len().try_into().ok()
+            }
+            ExprCompiledValue::Dict(kvs) => {
+                // Repeated keys are an error at runtime.
+                let mut keys = HashSet::new();
+                for (k, v) in kvs {
+                    v.as_value()?;
+                    if !keys.insert(k.as_value()?.get_hashed().ok()?) {
+                        return None;
+                    }
+                }
+                kvs.len().try_into().ok()
+            }
+            _ => None,
+        }
+    }
+
     fn try_values(
         span: Span,
         values: &[Value],
@@ -607,7 +806,7 @@ impl<P: AstPayload> ExprP<P> {
 
 #[cold]
 #[inline(never)]
-fn get_attr_no_attr_error<'v>(x: Value<'v>, attribute: &Symbol) -> anyhow::Error {
+pub(crate) fn get_attr_no_attr_error<'v>(x: Value<'v>, attribute: &Symbol) -> anyhow::Error {
     match did_you_mean(attribute.as_str(), x.dir_attr().iter().map(|s| s.as_str())) {
         None => ValueError::NoAttr(x.get_type().to_owned(), attribute.as_str().to_owned()).into(),
         Some(better) => ValueError::NoAttrDidYouMean(
@@ -751,7 +950,16 @@ impl Compiler<'_, '_, '_> {
                 return ExprCompiledValue::PercentSOne(box (before, r, after));
             }
         }
-        ExprCompiledValue::Op(ExprBinOp::Percent, box (l, r))
+        self.op(ExprBinOp::Percent, l, r)
+    }
+
+    fn op(
+        &mut self,
+        op: ExprBinOp,
+        l: Spanned<ExprCompiledValue>,
+        r: Spanned<ExprCompiledValue>,
+    ) -> ExprCompiledValue {
+        ExprCompiledValue::op(op, l, r, self.eval.module_env.frozen_heap())
     }
 
     pub(crate) fn expr(&mut self, expr: CstExpr) -> Spanned<ExprCompiledValue> {
@@ -856,21 +1064,17 @@ impl Compiler<'_, '_, '_> {
                         BinOp::GreaterOrEqual => eval_compare(l, r, CompareOp::GreaterOrEqual),
                         BinOp::In => ExprCompiledValue::Op(ExprBinOp::In, box (l, r)),
                         BinOp::NotIn => ExprCompiledValue::Op(ExprBinOp::NotIn, box (l, r)),
-                        BinOp::Subtract => ExprCompiledValue::Op(ExprBinOp::Sub, box (l, r)),
-                        BinOp::Add => ExprCompiledValue::Op(ExprBinOp::Add, box (l, r)),
-                        BinOp::Multiply => ExprCompiledValue::Op(ExprBinOp::Multiply, box (l, r)),
+                        BinOp::Subtract => self.op(ExprBinOp::Sub, l, r),
+                        BinOp::Add => self.op(ExprBinOp::Add, l, r),
+                        BinOp::Multiply => self.op(ExprBinOp::Multiply, l, r),
                         BinOp::Percent => self.percent(l, r),
-                        BinOp::Divide => ExprCompiledValue::Op(ExprBinOp::Divide, box (l, r)),
-                        BinOp::FloorDivide => {
-                            ExprCompiledValue::Op(ExprBinOp::FloorDivide, box (l, r))
-                        }
-                        BinOp::BitAnd => ExprCompiledValue::Op(ExprBinOp::BitAnd, box (l, r)),
-                        BinOp::BitOr => ExprCompiledValue::Op(ExprBinOp::BitOr, box (l, r)),
-                        BinOp::BitXor => ExprCompiledValue::Op(ExprBinOp::BitXor, box (l, r)),
-                        BinOp::LeftShift => ExprCompiledValue::Op(ExprBinOp::LeftShift, box (l, r)),
-                        BinOp::RightShift => {
-                            ExprCompiledValue::Op(ExprBinOp::RightShift, box (l, r))
-                        }
+                        BinOp::Divide => self.op(ExprBinOp::Divide, l, r),
+                        BinOp::FloorDivide => self.op(ExprBinOp::FloorDivide, l, r),
+                        BinOp::BitAnd => self.op(ExprBinOp::BitAnd, l, r),
+                        BinOp::BitOr => self.op(ExprBinOp::BitOr, l, r),
+                        BinOp::BitXor => self.op(ExprBinOp::BitXor, l, r),
+                        BinOp::LeftShift => self.op(ExprBinOp::LeftShift, l, r),
+                        BinOp::RightShift => self.op(ExprBinOp::RightShift, l, r),
                     }
                 }
             }
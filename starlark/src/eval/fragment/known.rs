@@ -51,7 +51,8 @@ impl Compiler<'_, '_, '_> {
     pub fn fn_type(&mut self, expr: CstExpr) -> ExprCompiledValue {
         let span = expr.span;
         match self.expr(expr).node {
-            ExprCompiledValue::Value(x) => {
+            // Dynamic type names are computed by `type()` at runtime.
+            ExprCompiledValue::Value(x) if x.to_value().get_ref().get_type_dynamic().is_none() => {
                 ExprCompiledValue::Value(x.to_value().get_type_value().unpack())
             }
             ExprCompiledValue::List(xs) if xs.is_empty() => {
@@ -39,7 +39,7 @@ use crate::{
         },
         fragment::{expr::ExprCompiledValue, known::list_to_tuple},
         runtime::{
-            evaluator::{Evaluator, GC_THRESHOLD},
+            evaluator::{Evaluator, EvaluatorError, GC_THRESHOLD},
             slots::LocalSlotId,
         },
     },
@@ -55,6 +55,19 @@ pub(crate) enum AssignModifyLhs {
     Module(Spanned<ModuleSlotId>),
 }
 
+impl AssignModifyLhs {
+    fn visit_exprs<'a>(&'a self, f: &mut dyn FnMut(&'a Spanned<ExprCompiledValue>)) {
+        match self {
+            AssignModifyLhs::Dot(object, _) => f(object),
+            AssignModifyLhs::Array(array, index) => {
+                f(array);
+                f(index);
+            }
+            AssignModifyLhs::Local(..) | AssignModifyLhs::Module(..) => {}
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) enum StmtCompiledValue {
     PossibleGc,
@@ -63,26 +76,109 @@ pub(crate) enum StmtCompiledValue {
     Assign(Spanned<AssignCompiledValue>, Spanned<ExprCompiledValue>),
     AssignModify(AssignModifyLhs, AssignOp, Spanned<ExprCompiledValue>),
     If(Box<(Spanned<ExprCompiledValue>, StmtsCompiled, StmtsCompiled)>),
+    /// Order: variable, collection, body, `else` block (empty if there is no `else`).
     For(
         Box<(
             Spanned<AssignCompiledValue>,
             Spanned<ExprCompiledValue>,
             StmtsCompiled,
+            StmtsCompiled,
         )>,
     ),
     Break,
     Continue,
+    /// Fail with an optional message, the false branch of an `assert` statement.
+    AssertFailed(Option<Spanned<ExprCompiledValue>>),
+}
+
+impl StmtCompiledValue {
+    /// Visit the expressions used directly by this statement and its nested statements.
+    fn visit_exprs<'a>(&'a self, f: &mut dyn FnMut(&'a Spanned<ExprCompiledValue>)) {
+        match self {
+            StmtCompiledValue::PossibleGc
+            | StmtCompiledValue::Break
+            | StmtCompiledValue::Continue => {}
+            StmtCompiledValue::Return(e) | StmtCompiledValue::AssertFailed(e) => {
+                e.iter().for_each(f)
+            }
+            StmtCompiledValue::Expr(e) => f(e),
+            StmtCompiledValue::Assign(lhs, rhs) => {
+                lhs.node.visit_exprs(f);
+                f(rhs);
+            }
+            StmtCompiledValue::AssignModify(lhs, _, rhs) => {
+                lhs.visit_exprs(f);
+                f(rhs);
+            }
+            StmtCompiledValue::If(box (cond, t, e)) => {
+                f(cond);
+                t.visit_exprs(f);
+                e.visit_exprs(f);
+            }
+            StmtCompiledValue::For(box (var, over, body, else_block)) => {
+                var.node.visit_exprs(f);
+                f(over);
+                body.visit_exprs(f);
+                else_block.visit_exprs(f);
+            }
+        }
+    }
 }
 
 #[derive(Debug, Default)]
 pub(crate) struct StmtCompileContext {
     pub(crate) has_before_stmt: bool,
     pub(crate) bc_profile: bool,
+    // Evaluator setting the code was compiled with,
+    // used when calls are executed speculatively on freeze
+    pub(crate) repr_ascii: bool,
+}
+
+/// Which optimizations are performed on function bodies when a module is frozen.
+///
+/// Once a module is frozen, all its variables are known, so `def` bodies are
+/// optimized again against the frozen values. These options only control that pass:
+/// optimizations done when the code is first compiled are always performed.
+/// The optimized bytecode can be inspected with
+/// [`FrozenModule::dump_debug`](crate::environment::FrozenModule::dump_debug).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptimizeOptions {
+    /// Replace references to module variables with their frozen values.
+    pub inline_module_variables: bool,
+    /// Call functions marked `speculative_exec_safe` whose arguments are all constants,
+    /// and replace the call with its result.
+    pub fold_constants: bool,
+    /// Remove branches of `if` statements whose condition is a constant.
+    pub eliminate_dead_code: bool,
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        OptimizeOptions::ALL
+    }
+}
+
+impl OptimizeOptions {
+    /// Perform all optimizations, the default.
+    pub const ALL: OptimizeOptions = OptimizeOptions {
+        inline_module_variables: true,
+        fold_constants: true,
+        eliminate_dead_code: true,
+    };
+
+    /// Perform no optimizations on freeze.
+    pub const NONE: OptimizeOptions = OptimizeOptions {
+        inline_module_variables: false,
+        fold_constants: false,
+        eliminate_dead_code: false,
+    };
 }
 
 pub(crate) struct OptimizeOnFreezeContext<'a> {
     pub(crate) module: &'a FrozenModuleRef,
     pub(crate) heap: &'a FrozenHeap,
+    pub(crate) options: &'a OptimizeOptions,
+    pub(crate) compile_context: &'a StmtCompileContext,
 }
 
 impl Spanned<StmtCompiledValue> {
@@ -109,15 +205,22 @@ impl Spanned<StmtCompiledValue> {
                 let cond = cond.optimize_on_freeze(ctx);
                 let t = t.optimize_on_freeze(ctx);
                 let f = f.optimize_on_freeze(ctx);
+                if !ctx.options.eliminate_dead_code && !(t.is_empty() && f.is_empty()) {
+                    return StmtsCompiled::one(Spanned {
+                        span,
+                        node: StmtCompiledValue::If(box (cond, t, f)),
+                    });
+                }
                 Self::if_stmt(span, cond, t, f)
             }
-            StmtCompiledValue::For(box (ref var, ref over, ref body)) => {
+            StmtCompiledValue::For(box (ref var, ref over, ref body, ref else_block)) => {
                 let var = var.optimize_on_freeze(ctx);
                 let over = over.optimize_on_freeze(ctx);
                 let body = body.optimize_on_freeze(ctx);
+                let else_block = else_block.optimize_on_freeze(ctx);
                 StmtsCompiled::one(Spanned {
                     span,
-                    node: StmtCompiledValue::For(box (var, over, body)),
+                    node: StmtCompiledValue::For(box (var, over, body, else_block)),
                 })
             }
             ref s @ (StmtCompiledValue::PossibleGc
@@ -131,6 +234,12 @@ impl Spanned<StmtCompiledValue> {
                 span,
                 node: s.clone(),
             }),
+            StmtCompiledValue::AssertFailed(ref message) => StmtsCompiled::one(Spanned {
+                span,
+                node: StmtCompiledValue::AssertFailed(
+                    message.as_ref().map(|m| m.optimize_on_freeze(ctx)),
+                ),
+            }),
         }
     }
 
@@ -153,17 +262,10 @@ impl Spanned<StmtCompiledValue> {
         t: StmtsCompiled,
         f: StmtsCompiled,
     ) -> StmtsCompiled {
+        if let Some((_, cond)) = cond.as_value_with_bool() {
+            return if cond { t } else { f };
+        }
         match cond {
-            Spanned {
-                node: ExprCompiledValue::Value(cond),
-                ..
-            } => {
-                if cond.to_value().to_bool() {
-                    t
-                } else {
-                    f
-                }
-            }
             Spanned {
                 node: ExprCompiledValue::Not(box cond),
                 ..
@@ -246,6 +348,14 @@ impl StmtsCompiled {
         self.0.extend(right.0);
     }
 
+    /// Visit the expressions used directly by the statements, see
+    /// [`CompiledExpr`](crate::eval::CompiledExpr) for the public API.
+    pub(crate) fn visit_exprs<'a>(&'a self, f: &mut dyn FnMut(&'a Spanned<ExprCompiledValue>)) {
+        for stmt in self.stmts() {
+            stmt.node.visit_exprs(f);
+        }
+    }
+
     pub(crate) fn optimize_on_freeze(&self, ctx: &OptimizeOnFreezeContext) -> StmtsCompiled {
         let mut stmts = StmtsCompiled::empty();
         match &self.0 {
@@ -293,6 +403,24 @@ pub(crate) enum AssignCompiledValue {
     Module(ModuleSlotId, String),
 }
 
+impl AssignCompiledValue {
+    pub(crate) fn visit_exprs<'a>(&'a self, f: &mut dyn FnMut(&'a Spanned<ExprCompiledValue>)) {
+        match self {
+            AssignCompiledValue::Dot(object, _) => f(object),
+            AssignCompiledValue::ArrayIndirection(array, index) => {
+                f(array);
+                f(index);
+            }
+            AssignCompiledValue::Tuple(xs) => {
+                for x in xs {
+                    x.node.visit_exprs(f);
+                }
+            }
+            AssignCompiledValue::Local(..) | AssignCompiledValue::Module(..) => {}
+        }
+    }
+}
+
 impl Spanned<AssignCompiledValue> {
     pub(crate) fn optimize_on_freeze(
         &self,
@@ -424,12 +552,18 @@ impl Compiler<'_, '_, '_> {
 // This function should be called before every meaningful statement.
 // The purposes are GC, profiling and debugging.
 //
-// This function is called only if `before_stmt` is set before compilation start.
-pub(crate) fn before_stmt(span: Span, eval: &mut Evaluator) {
-    assert!(
-        !eval.before_stmt.is_empty(),
-        "this code should not be called if `before_stmt` is set"
-    );
+// This function is called only if `before_stmt` or an instruction budget
+// is set before compilation start.
+pub(crate) fn before_stmt(span: Span, eval: &mut Evaluator) -> anyhow::Result<()> {
+    if let Some(budget) = eval.instruction_budget {
+        eval.instruction_count += 1;
+        if eval.instruction_count > budget {
+            return Err(EvaluatorError::InstructionBudgetExhausted(budget).into());
+        }
+    }
+    if eval.before_stmt.is_empty() {
+        return Ok(());
+    }
     let fs = mem::take(&mut eval.before_stmt);
     for f in &fs {
         f(span, eval)
@@ -439,6 +573,7 @@ pub(crate) fn before_stmt(span: Span, eval: &mut Evaluator) {
         added.is_empty(),
         "`before_stmt` cannot be modified during evaluation"
     );
+    Ok(())
 }
 
 // There are two requirements to perform a GC:
@@ -540,6 +675,7 @@ impl Compiler<'_, '_, '_> {
         StmtCompileContext {
             has_before_stmt: self.has_before_stmt,
             bc_profile: self.bc_profile,
+            repr_ascii: self.eval.repr_ascii,
         }
     }
 
@@ -607,6 +743,24 @@ impl Compiler<'_, '_, '_> {
         <Spanned<StmtCompiledValue>>::if_stmt(span, cond, then_block, else_block)
     }
 
+    fn stmt_assert(
+        &mut self,
+        span: Span,
+        cond: CstExpr,
+        message: Option<Box<CstExpr>>,
+    ) -> StmtsCompiled {
+        if !self.eval.assertions_enabled {
+            return StmtsCompiled::empty();
+        }
+        let cond = self.expr(cond);
+        let message = message.map(|m| self.expr(*m));
+        let failed = StmtsCompiled::one(Spanned {
+            span,
+            node: StmtCompiledValue::AssertFailed(message),
+        });
+        <Spanned<StmtCompiledValue>>::if_stmt(span, cond, StmtsCompiled::empty(), failed)
+    }
+
     fn stmt_expr(&mut self, expr: CstExpr) -> StmtsCompiled {
         let expr = self.expr(expr);
         <Spanned<StmtCompiledValue>>::expr(expr)
@@ -629,14 +783,18 @@ impl Compiler<'_, '_, '_> {
                     node: StmtCompiledValue::Assign(lhs, rhs),
                 })
             }
-            StmtP::For(var, box (over, body)) => {
+            StmtP::For(var, box (over, body, else_block)) => {
                 let over = list_to_tuple(over);
                 let var = self.assign(var);
                 let over = self.expr(over);
                 let st = self.stmt(body, false);
+                let else_st = match else_block {
+                    Some(else_block) => self.stmt(else_block, allow_gc),
+                    None => StmtsCompiled::empty(),
+                };
                 StmtsCompiled::one(Spanned {
                     span,
-                    node: StmtCompiledValue::For(box (var, over, st)),
+                    node: StmtCompiledValue::For(box (var, over, st, else_st)),
                 })
             }
             StmtP::Return(e) => StmtsCompiled::one(Spanned {
@@ -669,6 +827,7 @@ impl Compiler<'_, '_, '_> {
             }
             StmtP::Load(..) => unreachable!(),
             StmtP::Pass => StmtsCompiled::empty(),
+            StmtP::Assert(cond, message) => self.stmt_assert(span, cond, message),
             StmtP::Break => StmtsCompiled::one(Spanned {
                 span,
                 node: StmtCompiledValue::Break,
@@ -22,25 +22,26 @@ use std::{convert::TryInto, intrinsics::unlikely, mem};
 
 pub(crate) use compiler::scope::ScopeNames;
 pub(crate) use fragment::def::{Def, FrozenDef};
+pub use fragment::{expr::CompiledExpr, stmt::OptimizeOptions};
 use gazebo::{cast, prelude::*};
 pub use runtime::{
-    arguments::{Arguments, ParametersParser, ParametersSpec},
+    arguments::{Arguments, OwnedArguments, ParametersParser, ParametersSpec},
     evaluator::Evaluator,
-    file_loader::{FileLoader, ReturnFileLoader},
+    file_loader::{CallbackFileLoader, FileLoader, ReturnFileLoader},
 };
 
 use crate::{
     collections::symbol_map::Symbol,
-    environment::Globals,
+    environment::{Globals, Module},
     eval::{
         compiler::{
-            scope::{CompilerAstMap, Scope, ScopeData},
+            scope::{CompilerAstMap, Scope, ScopeData, ScopeId},
             Compiler, Constants,
         },
         fragment::def::DefInfo,
     },
-    syntax::ast::AstModule,
-    values::{docs::DocString, Value},
+    syntax::ast::{AstModule, StmtP},
+    values::{docs::DocString, string::repr::repr_ascii_guard, OwnedFrozenValue, Value},
 };
 
 pub(crate) mod bc;
@@ -54,7 +55,15 @@ mod tests;
 impl<'v, 'a> Evaluator<'v, 'a> {
     /// Evaluate an [`AstModule`] with this [`Evaluator`], modifying the in-scope
     /// [`Module`](crate::environment::Module) as appropriate.
+    ///
+    /// Compilation is interleaved with evaluation: each top-level statement is compiled
+    /// just before it runs, with variables resolved to slots of the current
+    /// [`Module`](crate::environment::Module) and constants allocated on its frozen heap.
+    /// As a result the compiled code is tied to that module and cannot be reused to evaluate
+    /// against a fresh one, so the [`AstModule`] is consumed.
     pub fn eval_module(&mut self, ast: AstModule, globals: &Globals) -> anyhow::Result<Value<'v>> {
+        let _repr_ascii = repr_ascii_guard(self.repr_ascii);
+
         let AstModule { codemap, statement } = ast;
 
         let globals = self.module_env.frozen_heap().alloc_any(globals.dupe());
@@ -70,11 +79,12 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         }
 
         let mut scope = Scope::enter_module(
-            self.module_env.names(),
+            self.module_env.mutable_names(),
             root_scope_id,
             scope_data,
             &mut statement,
             globals,
+            self.module_env.frozen_heap(),
             codemap.dupe(),
         );
 
@@ -85,6 +95,12 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             return Err(e);
         }
 
+        if let Some(observer) = &mut self.global_access_observer {
+            for name in scope.used_globals.iter() {
+                observer(name);
+            }
+        }
+
         let span = statement.span;
 
         let (module_slots, scope_names, scope_data) = scope.exit_module();
@@ -125,7 +141,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             globals,
             codemap: codemap.dupe(),
             constants: Constants::new(),
-            has_before_stmt: !self.before_stmt.is_empty(),
+            has_before_stmt: !self.before_stmt.is_empty() || self.instruction_budget.is_some(),
             bc_profile: self.bc_profile.enabled(),
             eval: self,
         };
@@ -146,6 +162,11 @@ impl<'v, 'a> Evaluator<'v, 'a> {
     }
 
     /// Evaluate a function stored in a [`Value`], passing in `positional` and `named` arguments.
+    ///
+    /// This is also the way to call back into Starlark from a native function,
+    /// using the `eval` it was given: the callee pushes its own frame on the
+    /// call stack, so stack depth limits and error backtraces work as for a
+    /// call made from Starlark.
     pub fn eval_function(
         &mut self,
         function: Value<'v>,
@@ -161,6 +182,65 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             args: None,
             kwargs: None,
         };
+        let _repr_ascii = repr_ascii_guard(self.repr_ascii);
         function.invoke(None, params, self)
     }
 }
+
+impl AstModule {
+    /// If this module is a single expression whose value the compiler can compute
+    /// without running it, such as `1 + 2 * 3`, return that value, otherwise [`None`].
+    ///
+    /// The expression is compiled against a fresh [`Module`]
+    /// but never evaluated, so references to variables not defined in `globals`,
+    /// or operations which would fail at runtime (e.g. `1 // 0`), give [`None`].
+    pub fn eval_const_expr(self, globals: &Globals) -> Option<OwnedFrozenValue> {
+        let AstModule { codemap, statement } = self;
+        if !matches!(statement.node, StmtP::Expression(_)) {
+            return None;
+        }
+
+        let module = Module::new();
+        let value = {
+            let mut eval = Evaluator::new(&module);
+            let globals = module.frozen_heap().alloc_any(globals.dupe());
+            let mut scope_data = ScopeData::new();
+            let root_scope_id = scope_data.new_scope().0;
+            let mut statement = statement.into_map_payload(&mut CompilerAstMap(&mut scope_data));
+            let scope = Scope::enter_module(
+                module.mutable_names(),
+                root_scope_id,
+                scope_data,
+                &mut statement,
+                globals,
+                module.frozen_heap(),
+                codemap.dupe(),
+            );
+            if !scope.errors.is_empty() {
+                return None;
+            }
+            let (_, _, scope_data) = scope.exit_module();
+            let expr = match statement.node {
+                StmtP::Expression(expr) => expr,
+                _ => unreachable!(),
+            };
+
+            let mut compiler = Compiler {
+                scope_data,
+                locals: Vec::new(),
+                globals,
+                codemap,
+                constants: Constants::new(),
+                has_before_stmt: false,
+                bc_profile: false,
+                eval: &mut eval,
+            };
+            compiler.enter_scope(ScopeId::module());
+            compiler.expr(expr).as_value()?
+        };
+
+        let module = module.freeze().ok()?;
+        // Safe because the value was allocated on the frozen heap of the module.
+        Some(unsafe { OwnedFrozenValue::new(module.frozen_heap().dupe(), value) })
+    }
+}
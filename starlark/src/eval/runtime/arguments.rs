@@ -100,8 +100,9 @@ impl<'v> ParameterKind<Value<'v>> {
     }
 }
 
-/// Define a list of parameters. This code assumes that all names are distinct and that
-/// `*args`/`**kwargs` occur in well-formed locations.
+/// Define a list of parameters. Adding a parameter whose name is already used
+/// (ignoring the `$` prefix of positional-only parameters) panics.
+/// This code assumes that `*args`/`**kwargs` occur in well-formed locations.
 // V = Value, or FrozenValue
 #[derive(Debug, Clone, Trace)]
 #[repr(C)]
@@ -157,6 +158,15 @@ impl<V> ParametersSpec<V> {
         // Regular arguments cannot follow `**kwargs`, but can follow `*args`.
         assert!(self.kwargs.is_none());
 
+        let bare = name.trim_start_match('$');
+        assert!(
+            self.names.get_str(bare).is_none()
+                && self.names.get_str(&format!("${}", bare)).is_none(),
+            "Duplicate parameter `{}` in `{}`",
+            bare,
+            self.function_name
+        );
+
         let i = self.kinds.len();
         self.kinds.push(val);
         let old = self.names.insert(name, i);
@@ -170,21 +180,21 @@ impl<V> ParametersSpec<V> {
 
     /// Add a required parameter. Will be an error if the caller doesn't supply
     /// it. If you want to supply a position-only argument, prepend a `$` to
-    /// the name.
+    /// the name. Panics if a parameter with this name already exists.
     pub fn required(&mut self, name: &str) {
         self.add(name, ParameterKind::Required);
     }
 
     /// Add an optional parameter. Will be None if the caller doesn't supply it.
     /// If you want to supply a position-only argument, prepend a `$` to the
-    /// name.
+    /// name. Panics if a parameter with this name already exists.
     pub fn optional(&mut self, name: &str) {
         self.add(name, ParameterKind::Optional);
     }
 
     /// Add an optional parameter. Will be the default value if the caller
     /// doesn't supply it. If you want to supply a position-only argument,
-    /// prepend a `$` to the name.
+    /// prepend a `$` to the name. Panics if a parameter with this name already exists.
     pub fn defaulted(&mut self, name: &str, val: V) {
         self.add(name, ParameterKind::Defaulted(val));
     }
@@ -630,6 +640,9 @@ impl<'v, 'a> ParametersParser<'v, 'a> {
     /// Obtain the next parameter, corresponding to [`ParametersSpec::optional`].
     /// It is an error to request more parameters than were specified.
     /// The `name` is only used for error messages.
+    ///
+    /// Returns [`None`] if the parameter was not supplied. A parameter explicitly passed as
+    /// `None` is unpacked as `T`, so use `T = Value` or `T = NoneOr<_>` to observe it.
     pub fn next_opt<T: UnpackValue<'v>>(&mut self, name: &str) -> anyhow::Result<Option<T>> {
         match self.get_next() {
             None => Ok(None),
@@ -714,6 +727,14 @@ impl<'v, 'a> Arguments<'v, 'a> {
         Ok(Dict::new(coerce(self.names_map()?)))
     }
 
+    /// Unpack all named arguments (both explicit and in `**kwargs`) into an iterator
+    /// of `(name, value)` pairs, in the order they were passed.
+    ///
+    /// This operation fails if named argument names are not unique.
+    pub fn named_args(&self) -> anyhow::Result<impl Iterator<Item = (&'v str, Value<'v>)>> {
+        Ok(self.names_map()?.into_iter().map(|(k, v)| (k.as_str(), v)))
+    }
+
     /// Unpack all positional parameters into an iterator.
     pub fn positions<'b>(
         &'b self,
@@ -831,6 +852,9 @@ impl<'v, 'a> Arguments<'v, 'a> {
     /// Collect exactly `REQUIRED` positional arguments, plus at most `OPTIONAL` positional arguments
     /// from the [`Arguments`], failing if there are too many/few arguments. Ignores named arguments.
     /// The `OPTIONAL` array will never have a [`Some`] after a [`None`].
+    ///
+    /// An entry in the `OPTIONAL` array is [`None`] if the argument was not supplied,
+    /// and `Some(v)` if it was, even if `v` is the Starlark `None` value.
     #[inline(always)]
     pub fn optional<const REQUIRED: usize, const OPTIONAL: usize>(
         &self,
@@ -898,6 +922,61 @@ impl<'v, 'a> Arguments<'v, 'a> {
     }
 }
 
+impl<'v> Arguments<'v, '_> {
+    /// Copy the arguments so they can be kept after the call, e.g. to replay it later.
+    ///
+    /// `*args` and `**kwargs` are expanded into positional and named arguments,
+    /// so mutating the collections passed there later does not change the copy.
+    pub fn into_owned(self, heap: &'v Heap) -> anyhow::Result<OwnedArguments<'v>> {
+        let pos = self.positions(heap)?.collect();
+        let mut named = self.named.to_vec();
+        let mut names = self.names.to_vec();
+        if let Some(kwargs) = self.unpack_kwargs()? {
+            for (k, v) in kwargs.iter() {
+                let k = Arguments::unpack_kwargs_key_as_value(k)?;
+                names.push((Symbol::new(k.as_str()), k));
+                named.push(v);
+            }
+        }
+        Ok(OwnedArguments { pos, named, names })
+    }
+}
+
+/// Call arguments which own their storage, created by [`Arguments::into_owned`].
+#[derive(Debug, Clone, Trace)]
+pub struct OwnedArguments<'v> {
+    pos: Vec<Value<'v>>,
+    named: Vec<Value<'v>>,
+    names: Vec<(Symbol, StringValue<'v>)>,
+}
+
+impl<'v> OwnedArguments<'v> {
+    /// Create arguments from positional and named arguments, e.g. those obtained from
+    /// [`Arguments::positions`] and [`Arguments::named_args`] and then modified,
+    /// so that a call can be forwarded to another function.
+    pub fn new(pos: Vec<Value<'v>>, named: Vec<(&str, Value<'v>)>, heap: &'v Heap) -> Self {
+        let (names, named) = named
+            .into_iter()
+            .map(|(k, v)| {
+                let name = StringValue::new(heap.alloc_str(k)).unwrap();
+                ((Symbol::new(k), name), v)
+            })
+            .unzip();
+        OwnedArguments { pos, named, names }
+    }
+
+    /// Borrow as [`Arguments`], e.g. to pass to [`Value::invoke`].
+    pub fn as_arguments(&self) -> Arguments<'v, '_> {
+        Arguments {
+            pos: &self.pos,
+            named: &self.named,
+            names: &self.names,
+            args: None,
+            kwargs: None,
+        }
+    }
+}
+
 impl Arguments<'_, '_> {
     /// Utility for checking a `this` parameter matches what you expect.
     pub fn check_this<'v, T: UnpackValue<'v>>(this: Value<'v>) -> anyhow::Result<T> {
@@ -938,6 +1017,22 @@ impl Arguments<'_, '_> {
 mod test {
     use super::*;
 
+    #[test]
+    #[should_panic(expected = "Duplicate parameter `x` in `f`")]
+    fn test_duplicate_parameter() {
+        let mut p = ParametersSpec::<Value>::new("f".to_owned());
+        p.required("x");
+        p.defaulted("x", Value::new_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate parameter `x` in `f`")]
+    fn test_duplicate_positional_only_parameter() {
+        let mut p = ParametersSpec::<Value>::new("f".to_owned());
+        p.required("$x");
+        p.optional("x");
+    }
+
     #[test]
     fn test_parameter_unpack() {
         let heap = Heap::new();
@@ -55,7 +55,7 @@ impl CheapFrame<'_> {
 
     fn to_frame(&self) -> Frame {
         Frame {
-            name: self.function.to_repr(),
+            name: self.function.to_string(),
             location: self.location(),
         }
     }
@@ -29,7 +29,7 @@ use crate::{
     codemap::{FileSpan, Span},
     collections::{alloca::Alloca, string_pool::StringPool},
     environment::{slots::ModuleSlotId, EnvironmentError, FrozenModuleRef, Module},
-    errors::{Diagnostic, Frame},
+    errors::{Diagnostic, Frame, Lint},
     eval::{
         fragment::def::DefInfo,
         runtime::{
@@ -60,6 +60,16 @@ pub(crate) enum EvaluatorError {
     FlameProfilingNotEnabled,
     #[error("Can't call `write_bc_profile` unless you first call `enable_bc_profile`.")]
     BcProfilingNotEnabled,
+    #[error("Instruction budget exhausted after executing {0} statements")]
+    InstructionBudgetExhausted(u64),
+    #[error("Too many values allocated on the heap, the limit is {0}")]
+    ObjectCountExceeded(usize),
+    #[error("Assertion failed")]
+    AssertionFailed,
+    #[error("Assertion failed: {0}")]
+    AssertionFailedWithMessage(String),
+    #[error("Not allowed during speculative execution: {0}")]
+    SpeculativeExecViolation(String),
 }
 
 /// Number of bytes to allocate between GC's.
@@ -91,14 +101,33 @@ pub struct Evaluator<'v, 'a> {
     pub(crate) next_gc_level: usize,
     // Extra functions to run on each statement, usually empty
     pub(crate) before_stmt: Vec<&'a dyn Fn(Span, &mut Evaluator<'v, 'a>)>,
+    // Maximum number of statements to execute, if limited
+    pub(crate) instruction_budget: Option<u64>,
+    // Number of statements executed so far, only counted if there is a budget
+    pub(crate) instruction_count: u64,
+    // Maximum number of values on the heap, if limited
+    pub(crate) max_object_count: Option<usize>,
+    // Are `assert` statements checked, otherwise they are not compiled
+    pub(crate) assertions_enabled: bool,
     // Used for line profiling
-    stmt_profile: StmtProfile,
+    pub(crate) stmt_profile: StmtProfile,
     // Bytecode profile.
     pub(crate) bc_profile: BcProfile,
     // Used for stack-like allocation
     alloca: Alloca,
     // Another stack-like allocation
     pub(crate) string_pool: StringPool,
+    // Should `repr()` escape non-ASCII characters
+    pub(crate) repr_ascii: bool,
+    // Should dict comprehensions fail on a repeated key instead of keeping the last value
+    pub(crate) error_on_duplicate_comprehension_keys: bool,
+    // `Some` while a `speculative_exec_safe` function is executed at compile time,
+    // collects the disallowed operations that function attempted
+    pub(crate) speculative_exec: Option<Vec<String>>,
+    // Warnings produced while compiling, returned by `take_warnings`
+    pub(crate) warnings: Vec<Lint>,
+    // Called with the names of globals referenced by each evaluated module
+    pub(crate) global_access_observer: Option<Box<dyn FnMut(&str) + 'a>>,
     /// Field that can be used for any purpose you want (can store types you define).
     /// Typically accessed via native functions you also define.
     pub extra: Option<&'a dyn AnyLifetime<'a>>,
@@ -145,8 +174,17 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             flame_profile: FlameProfile::new(),
             heap_or_flame_profile: false,
             before_stmt: Vec::new(),
+            instruction_budget: None,
+            instruction_count: 0,
+            max_object_count: None,
+            assertions_enabled: false,
             def_info: DefInfo::empty(), // Will be replaced before it is used
             string_pool: StringPool::default(),
+            repr_ascii: false,
+            error_on_duplicate_comprehension_keys: false,
+            speculative_exec: None,
+            warnings: Vec::new(),
+            global_access_observer: None,
             breakpoint_handler: None,
         }
     }
@@ -165,6 +203,24 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         self.loader = Some(loader);
     }
 
+    /// Make the `repr` of strings escape every non-ASCII character as `\u{...}`.
+    /// By default non-ASCII characters are written as UTF-8, as the Starlark
+    /// spec does. Control characters, quotes and backslashes are escaped either way.
+    ///
+    /// This applies to every string `repr` computed while this evaluator runs code,
+    /// e.g. `repr()`, `"%r"`, `"{!r}".format()` and `str()` of a list of strings.
+    /// Text written by other values is not changed.
+    pub fn set_repr_ascii(&mut self, ascii: bool) {
+        self.repr_ascii = ascii;
+    }
+
+    /// Make dict comprehensions such as `{k: v for k, v in pairs}` fail when a key is
+    /// produced more than once, reporting the key and its span. By default the last value
+    /// for a repeated key wins, as the Starlark spec requires.
+    pub fn set_error_on_duplicate_comprehension_keys(&mut self, error: bool) {
+        self.error_on_duplicate_comprehension_keys = error;
+    }
+
     /// Enable profiling, allowing [`Evaluator::write_heap_profile`] to be used.
     /// Has the side effect of disabling garbage-collection.
     ///
@@ -297,6 +353,62 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         self.before_stmt.push(f)
     }
 
+    /// Limit the number of statements this evaluator will execute. Once the budget is
+    /// exhausted, evaluation fails with an error reporting the budget. Statements are
+    /// counted at the same points as [`before_stmt`](Evaluator::before_stmt) callbacks,
+    /// so every iteration of a loop counts the statements in its body.
+    ///
+    /// Like [`before_stmt`](Evaluator::before_stmt), this only affects code compiled after it
+    /// is called, so it must be set before evaluation starts. Functions defined in
+    /// frozen modules loaded from elsewhere are not counted.
+    pub fn set_instruction_budget(&mut self, budget: u64) {
+        self.instruction_budget = Some(budget);
+    }
+
+    /// Limit the number of values on the heap, as counted by [`Heap::allocated_count`],
+    /// to protect against scripts which allocate many small values. Once the limit is
+    /// exceeded, evaluation fails with an error reporting the limit.
+    ///
+    /// To keep it cheap, the count is only checked on each iteration of a loop, including
+    /// the loops of comprehensions, so code with no loops may go somewhat over the limit.
+    pub fn set_max_object_count(&mut self, count: usize) {
+        self.max_object_count = Some(count);
+    }
+
+    /// Check `assert` statements, allowed by
+    /// [`Dialect::enable_assert_statement`](crate::syntax::Dialect::enable_assert_statement).
+    /// If enabled, `assert cond, message` fails when `cond` is false, with the message
+    /// if given. By default they are disabled, and not compiled at all, so neither the
+    /// condition nor the message is evaluated.
+    ///
+    /// Like [`before_stmt`](Evaluator::before_stmt), this only affects code compiled after it
+    /// is called, so it must be set before evaluation starts.
+    pub fn set_assertions_enabled(&mut self, enabled: bool) {
+        self.assertions_enabled = enabled;
+    }
+
+    /// Called with the name of each global (e.g. a builtin function) referenced by the
+    /// code evaluated from now on, e.g. to find which [`Globals`](crate::environment::Globals) a script needs.
+    ///
+    /// Globals are resolved when a module is compiled, before it runs, so the observer is
+    /// called once for each global name referenced anywhere in the module, including
+    /// in branches and functions which are never executed. Names are reported in the order
+    /// they first appear, separately for each [`eval_module`](Evaluator::eval_module) call.
+    /// Evaluation itself has no extra cost.
+    pub fn set_global_access_observer(&mut self, observer: Box<dyn FnMut(&str) + 'a>) {
+        self.global_access_observer = Some(observer);
+    }
+
+    /// Take the warnings produced while compiling the code evaluated so far.
+    ///
+    /// Currently the only warning is for a function marked `speculative_exec_safe`
+    /// which attempted an operation that is not allowed while it is executed at
+    /// compile time: the call is then left to run normally, but the annotation is wrong.
+    /// Calls folded when a module is frozen run without an evaluator, so are not reported.
+    pub fn take_warnings(&mut self) -> Vec<Lint> {
+        mem::take(&mut self.warnings)
+    }
+
     /// Given a [`Span`] resolve it to a concrete [`FileSpan`] using
     /// whatever module is currently at the top of the stack.
     /// This function can be used in conjunction with [`before_stmt`](Evaluator::before_stmt).
@@ -310,6 +422,23 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         true
     }
 
+    /// Fail if a function marked `speculative_exec_safe` is being executed speculatively,
+    /// recording the operation `what` it attempted so the result can be discarded.
+    #[cold]
+    pub(crate) fn speculative_exec_violation(
+        &mut self,
+        what: impl FnOnce() -> String,
+    ) -> anyhow::Result<()> {
+        match &mut self.speculative_exec {
+            None => Ok(()),
+            Some(violations) => {
+                let what = what();
+                violations.push(what.clone());
+                Err(EvaluatorError::SpeculativeExecViolation(what).into())
+            }
+        }
+    }
+
     /// Called to add an entry to the call stack, by the function being invoked.
     /// Called for all types of function, including those written in Rust.
     #[inline(always)]
@@ -391,7 +520,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         #[inline(never)]
         fn error<'v>(eval: &Evaluator<'v, '_>, slot: ModuleSlotId) -> anyhow::Error {
             let name = match &eval.module_variables {
-                None => eval.module_env.names().get_slot(slot),
+                None => eval.module_env.mutable_names().get_slot(slot),
                 Some(e) => e.0.get_slot_name(slot),
             }
             .unwrap_or_else(|| "<unknown>".to_owned());
@@ -463,6 +592,9 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         name: &str,
         value: Value<'v>,
     ) -> anyhow::Result<()> {
+        if unlikely(self.speculative_exec.is_some()) {
+            self.speculative_exec_violation(|| format!("assignment to `{}`", name))?;
+        }
         value.export_as(name, self);
         self.module_env.set(name, value);
         Ok(())
@@ -503,8 +635,18 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         self.local_variables.set_slot(slot, value_captured);
     }
 
-    /// Cause a GC to be triggered next time it's possible.
-    pub(crate) fn trigger_gc(&mut self) {
+    /// Cause a GC to be triggered next time it's possible, freeing the values on the
+    /// [`heap`](Evaluator::heap) which are not reachable from the module variables or the
+    /// current call stack.
+    ///
+    /// The roots are only all known before a top-level statement of a module, so the
+    /// collection happens there, during this or the next [`eval_module`](Evaluator::eval_module),
+    /// which makes it safe to call at any time, including from a native function.
+    /// As with the collections which happen automatically, a [`Value`] kept outside the module,
+    /// such as the result of an earlier [`eval_module`](Evaluator::eval_module), is invalid
+    /// afterwards. Does nothing if GC is [disabled](Evaluator::disable_gc)
+    /// or [`extra_v`](Evaluator::extra_v) is set.
+    pub fn trigger_gc(&mut self) {
         // We will GC next time we can, since the threshold is if 0 or more bytes are allocated
         self.next_gc_level = 0;
     }
@@ -18,10 +18,11 @@
 //! Define variants of the evaluation function with different support
 //! for the `load(...)` statement.
 
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap, iter};
 
 use anyhow::anyhow;
 use gazebo::prelude::*;
+use itertools::Itertools;
 
 use crate::environment::FrozenModule;
 
@@ -51,3 +52,62 @@ impl<'a> FileLoader for ReturnFileLoader<'a> {
         }
     }
 }
+
+/// [`FileLoader`] that produces modules on demand by calling a function.
+///
+/// The function is given the path to load and this loader, which it should use to resolve
+/// the `load()` statements of the module it produces (typically via
+/// [`Evaluator::set_loader`](crate::eval::Evaluator::set_loader)).
+/// Each module is produced at most once. A module which loads itself, directly or
+/// through other modules, is reported as an error listing the cycle,
+/// e.g. `import cycle: a.star -> b.star -> a.star`.
+pub struct CallbackFileLoader<F> {
+    callback: F,
+    /// The modules currently being produced, outermost first.
+    loading: RefCell<Vec<String>>,
+    loaded: RefCell<HashMap<String, FrozenModule>>,
+}
+
+impl<F> CallbackFileLoader<F>
+where
+    F: Fn(&str, &dyn FileLoader) -> anyhow::Result<FrozenModule>,
+{
+    /// Create a loader which calls `callback` to produce the modules it has not yet loaded.
+    pub fn new(callback: F) -> Self {
+        Self {
+            callback,
+            loading: RefCell::new(Vec::new()),
+            loaded: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+impl<F> FileLoader for CallbackFileLoader<F>
+where
+    F: Fn(&str, &dyn FileLoader) -> anyhow::Result<FrozenModule>,
+{
+    fn load(&self, path: &str) -> anyhow::Result<FrozenModule> {
+        if let Some(module) = self.loaded.borrow().get(path) {
+            return Ok(module.dupe());
+        }
+        {
+            let loading = self.loading.borrow();
+            if let Some(i) = loading.iter().position(|x| x == path) {
+                let cycle = loading[i..]
+                    .iter()
+                    .map(|x| x.as_str())
+                    .chain(iter::once(path))
+                    .join(" -> ");
+                return Err(anyhow!("import cycle: {}", cycle));
+            }
+        }
+        self.loading.borrow_mut().push(path.to_owned());
+        let res = (self.callback)(path, self);
+        self.loading.borrow_mut().pop();
+        let module = res?;
+        self.loaded
+            .borrow_mut()
+            .insert(path.to_owned(), module.dupe());
+        Ok(module)
+    }
+}
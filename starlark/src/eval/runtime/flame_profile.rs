@@ -200,7 +200,7 @@ impl<'v> FlameProfile<'v> {
         // Need to write out lines which look like:
         // root;calls1;calls2 1
         // All the numbers at the end must be whole numbers (we use milliseconds)
-        let names = x.values.map(|x| x.to_repr());
+        let names = x.values.map(|x| x.to_string());
         Stacks::new(&names, &x.frames).render(file)
     }
 }
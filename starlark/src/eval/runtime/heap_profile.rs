@@ -138,7 +138,7 @@ impl FunctionIds {
         match self.values.entry(x.ptr_value()) {
             Entry::Occupied(v) => *v.get(),
             Entry::Vacant(outer) => {
-                let s = x.to_str();
+                let s = x.to_string();
                 match self.strings.entry(s) {
                     Entry::Occupied(inner) => {
                         let res = *inner.get();
@@ -17,8 +17,7 @@
 
 use std::{
     collections::{hash_map::Entry, HashMap},
-    fs::File,
-    io::Write,
+    fs,
     path::Path,
     ptr,
     sync::Arc,
@@ -111,13 +110,7 @@ impl StmtProfileData {
     }
 
     fn write(&self, filename: &Path, now: Instant) -> anyhow::Result<()> {
-        let file = File::create(filename).with_context(|| {
-            format!(
-                "When creating line profile output file `{}`",
-                filename.display()
-            )
-        })?;
-        self.write_to(file, now).with_context(|| {
+        fs::write(filename, self.gen_csv(now)).with_context(|| {
             format!(
                 "When writing to line profile output file `{}`",
                 filename.display()
@@ -125,7 +118,8 @@ impl StmtProfileData {
         })
     }
 
-    fn write_to(&self, mut file: impl Write, now: Instant) -> anyhow::Result<()> {
+    // Every statement is reported with its resolved `file:line:col` span.
+    fn gen_csv(&self, now: Instant) -> String {
         // The statement that was running last won't have been properly updated.
         // However, at this point, we have probably run some post-execution code,
         // so it probably wouldn't have a "fair" timing anyway.
@@ -170,9 +164,7 @@ impl StmtProfileData {
             csv.finish_row();
         }
 
-        file.write_all(csv.finish().as_bytes())?;
-
-        Ok(())
+        csv.finish()
     }
 }
 
@@ -196,4 +188,52 @@ impl StmtProfile {
         let now = Instant::now();
         self.0.as_ref().map(|data| data.write(filename, now))
     }
+
+    // None = not applicable because not enabled
+    #[cfg(test)]
+    fn gen_csv(&self) -> Option<String> {
+        let now = Instant::now();
+        self.0.as_ref().map(|data| data.gen_csv(now))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        environment::{Globals, Module},
+        eval::Evaluator,
+        syntax::{AstModule, Dialect},
+    };
+
+    #[test]
+    fn test_multiline_function_spans() {
+        let module = Module::new();
+        let globals = Globals::standard();
+        let mut eval = Evaluator::new(&module);
+        eval.enable_stmt_profile();
+        let program = "\
+def f(x):
+    y = x + 1
+    return y * 2
+f(1)
+f(2)
+";
+        eval.eval_module(
+            AstModule::parse("multi.star", program.to_owned(), &Dialect::Standard).unwrap(),
+            &globals,
+        )
+        .unwrap();
+        let csv = eval.stmt_profile.gen_csv().unwrap();
+        let row = |span: &str| {
+            let prefix = format!("\"multi.star\",{},", span);
+            csv.lines()
+                .find(|l| l.starts_with(&prefix))
+                .unwrap_or_else(|| panic!("no row for {} in {:?}", span, csv))
+                .to_owned()
+        };
+        assert!(row("2:5-14").ends_with(",2"));
+        assert!(row("3:5-17").ends_with(",2"));
+        assert!(row("4:1-5").ends_with(",1"));
+        assert!(row("5:1-5").ends_with(",1"));
+    }
 }
@@ -18,20 +18,28 @@
 //! Bytecode generation tests.
 
 use crate::{
-    assert,
+    self as starlark, assert,
     assert::Assert,
-    eval::{bc::opcode::BcOpcode, FrozenDef},
+    environment::{Globals, GlobalsBuilder, Module},
+    eval::{bc::opcode::BcOpcode, Evaluator, FrozenDef, OptimizeOptions},
+    syntax::{AstModule, Dialect},
+    values::Value,
 };
 
-fn test_instrs(expected: &[BcOpcode], def_program: &str) {
+/// Opcodes of the function `test` defined in `program`, after the module is frozen.
+fn def_opcodes(program: &str) -> Vec<BcOpcode> {
     let mut a = Assert::new();
     let def = a
-        .module("instrs.star", def_program)
+        .module("instrs.star", program)
         .get("test")
         .unwrap()
         .downcast::<FrozenDef>()
         .unwrap();
-    let mut opcodes = def.bc().instrs.opcodes();
+    def.bc().instrs.opcodes()
+}
+
+fn test_instrs(expected: &[BcOpcode], def_program: &str) {
+    let mut opcodes = def_opcodes(def_program);
     assert_eq!(Some(BcOpcode::End), opcodes.pop());
     assert_eq!(expected, opcodes);
 }
@@ -84,3 +92,267 @@ fn test_spec_exec_list() {
         "def test(): return list((10, 20))",
     )
 }
+
+#[test]
+fn test_not_const() {
+    // `not` of a constant is folded to a constant bool.
+    test_instrs(
+        &[BcOpcode::Const, BcOpcode::Return],
+        "def test(): return not 'x'",
+    );
+}
+
+#[test]
+fn test_not_const_eval() {
+    assert::pass(
+        r#"
+def test():
+    return (not "x", not "", not [], not (1,))
+
+assert_eq((False, True, True, False), test())
+"#,
+    );
+}
+
+#[test]
+fn test_arith_const() {
+    // Arithmetic on constant numbers is folded to a constant.
+    test_instrs(
+        &[BcOpcode::Const, BcOpcode::Return],
+        "def test(): return 1 + 2 * 3",
+    );
+    // But errors are reported when the expression is evaluated.
+    assert!(def_opcodes("def test(): return 1 // 0").contains(&BcOpcode::FloorDivide));
+    assert::fail("def test(): return 1 // 0\ntest()", "divide by zero");
+}
+
+#[test]
+fn test_list_comprehension_sized() {
+    // A single `for` without `if` yields one element per iteration.
+    let sized = def_opcodes("def test(xs): return [x * 2 for x in xs]");
+    assert!(sized.contains(&BcOpcode::ComprListNewSized));
+    assert!(!sized.contains(&BcOpcode::ListNew));
+    for program in [
+        "def test(xs): return [x for x in xs if x]",
+        "def test(xs): return [y for x in xs for y in x]",
+    ] {
+        let growing = def_opcodes(program);
+        assert!(growing.contains(&BcOpcode::ListNew));
+        assert!(!growing.contains(&BcOpcode::ComprListNewSized));
+    }
+
+    assert::eq("[0, 2, 4]", "[x * 2 for x in range(3)]");
+    assert::eq("[1, 2]", "[x for x in {1: 'a', 2: 'b'}]");
+    assert::fail("[x for x in 'abc']", "not supported");
+}
+
+#[test]
+fn test_len_const() {
+    for program in [
+        "def test(): return len('abc')",
+        "def test(): return len('bär')",
+        "def test(): return len([1, 2, 3])",
+        "def test(): return len((1, 2, 3))",
+        "def test(): return len({1: 'a', 2: 'b', 3: 'c'})",
+        // Values of module variables are known when the module is frozen.
+        "L = [1, 2, 3]\ndef test(): return len(L)",
+    ] {
+        test_instrs(&[BcOpcode::Const, BcOpcode::Return], program);
+        assert::eq("3", &format!("{}\ntest()", program));
+    }
+
+    assert!(def_opcodes("def test(x): return len([x, 1])").contains(&BcOpcode::Len));
+    assert::fail("len({1: 2, 1: 3})", "repeated");
+}
+
+#[test]
+fn test_call_native_folded_on_freeze() {
+    // `N` is not known when `test` is compiled, but is frozen with the module,
+    // so the call to the pure builtin is replaced with its result.
+    test_instrs(
+        &[BcOpcode::Const, BcOpcode::Return],
+        "N = 3\ndef test(): return str(N)",
+    );
+    assert::eq("'3'", "N = 3\ndef test(): return str(N)\ntest()");
+    // Failing calls are left to fail at runtime.
+    assert::fail(
+        "N = -1\ndef test(): return chr(N)\ntest()",
+        "not a valid UTF-8 codepoint",
+    );
+}
+
+#[test]
+fn test_call_native_folded_on_freeze_not_shared() {
+    // A folded result is not shared between calls, so it can't be observed to be reused:
+    // a list is rebuilt by each call, and a dict is not folded at all.
+    test_instrs(
+        &[BcOpcode::ListOfConsts, BcOpcode::Return],
+        "T = (1, 2)\ndef test(): return list(T)",
+    );
+    assert!(def_opcodes("T = (1, 2)\ndef test(): return dict(a = T)")
+        .contains(&BcOpcode::CallFrozenNative));
+    let mut a = Assert::new();
+    a.module(
+        "m",
+        "T = (1, 2)\ndef l(): return list(T)\ndef d(): return dict(a = T)",
+    );
+    a.pass(
+        r#"
+load("m", "l", "d")
+x = l()
+x.append(3)
+assert_eq(l(), [1, 2])
+y = d()
+y["b"] = 1
+assert_eq(d(), {"a": (1, 2)})
+"#,
+    );
+}
+
+#[test]
+fn test_repr_folded_on_freeze() {
+    test_instrs(
+        &[BcOpcode::Const, BcOpcode::Return],
+        "S = 'é'\ndef test(): return repr(S)",
+    );
+    // Folded with the configuration of the evaluator which compiled the function,
+    // both when compiling (`g`) and when freezing (`f`).
+    let mut a = Assert::new();
+    a.setup_eval(|eval| eval.set_repr_ascii(true));
+    a.module(
+        "m",
+        "S = 'é'\ndef f(): return repr(S)\ndef g(): return repr('é')",
+    );
+    a.eq("'\"\\\\u{e9}\"'", "load('m', 'f')\nf()");
+    a.eq("'\"\\\\u{e9}\"'", "load('m', 'g')\ng()");
+}
+
+#[test]
+fn test_speculative_exec_violation() {
+    #[starlark_module]
+    fn mis_annotated(builder: &mut GlobalsBuilder) {
+        // Wrongly annotated: calls its argument, which may do anything.
+        #[starlark(speculative_exec_safe)]
+        fn apply(ref f: Value) -> Value<'v> {
+            f.invoke_pos(None, &[], eval)
+        }
+    }
+
+    // The speculative call is stopped and the call is left to runtime.
+    let mut a = Assert::new();
+    a.globals_add(mis_annotated);
+    let program = "def g(): return 1\ndef test(): return apply(g)";
+    let def = a
+        .module("instrs.star", program)
+        .get("test")
+        .unwrap()
+        .downcast::<FrozenDef>()
+        .unwrap();
+    assert_ne!(
+        vec![BcOpcode::Const, BcOpcode::Return, BcOpcode::End],
+        def.bc().instrs.opcodes()
+    );
+    a.eq("1", &format!("{}\ntest()", program));
+
+    // Disallowed operations are recorded.
+    let globals = GlobalsBuilder::extended().with(mis_annotated).build();
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.speculative_exec = Some(Vec::new());
+    let ast = AstModule::parse(
+        "x.star",
+        "def g(): return 1\napply(g)".to_owned(),
+        &Dialect::Standard,
+    )
+    .unwrap();
+    let err = eval.eval_module(ast, &globals).unwrap_err();
+    assert!(err.to_string().contains("speculative"), "{}", err);
+    assert_eq!(
+        Some(vec!["call to `g()`".to_owned()]),
+        eval.speculative_exec
+    );
+
+    // Calls executed speculatively when compiling report the wrong annotation as a warning.
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    let ast = AstModule::parse(
+        "x.star",
+        "def test(): return apply(fail)".to_owned(),
+        &Dialect::Standard,
+    )
+    .unwrap();
+    eval.eval_module(ast, &globals).unwrap();
+    let warnings = eval.take_warnings();
+    assert_eq!(1, warnings.len(), "{:?}", warnings);
+    assert_eq!("speculative-exec-unsafe", warnings[0].short_name);
+    assert_eq!("apply(fail)", warnings[0].original);
+    assert_eq!(
+        "Function `apply` is marked `speculative_exec_safe`, but attempted call to `fail`",
+        warnings[0].problem
+    );
+    assert!(eval.take_warnings().is_empty());
+}
+
+#[test]
+fn test_optimize_options() {
+    fn opcodes(options: &OptimizeOptions) -> Vec<BcOpcode> {
+        let program = "N = 3\nF = False\ndef test():\n  if F:\n    return 1\n  return str(N)";
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        let ast = AstModule::parse("x.star", program.to_owned(), &Dialect::Standard).unwrap();
+        eval.eval_module(ast, &Globals::standard()).unwrap();
+        let module = module.freeze_with_optimize_options(options).unwrap();
+        let def = module.get("test").unwrap().downcast::<FrozenDef>().unwrap();
+        def.bc().instrs.opcodes()
+    }
+
+    assert_eq!(
+        vec![BcOpcode::Const, BcOpcode::Return, BcOpcode::End],
+        opcodes(&OptimizeOptions::ALL)
+    );
+    let none = opcodes(&OptimizeOptions::NONE);
+    assert!(none.contains(&BcOpcode::LoadModule));
+    assert!(none.contains(&BcOpcode::IfNotBr));
+    // Variables are inlined and the call is folded, but the dead branch is kept.
+    let no_dce = opcodes(&OptimizeOptions {
+        eliminate_dead_code: false,
+        ..OptimizeOptions::ALL
+    });
+    assert!(!no_dce.contains(&BcOpcode::LoadModule));
+    assert!(no_dce.contains(&BcOpcode::IfNotBr));
+    assert!(!no_dce.contains(&BcOpcode::CallFrozenNativePos));
+    // Without folding, the call to the now known builtin remains.
+    let no_fold = opcodes(&OptimizeOptions {
+        fold_constants: false,
+        ..OptimizeOptions::ALL
+    });
+    assert!(!no_fold.contains(&BcOpcode::IfNotBr));
+    assert!(no_fold.contains(&BcOpcode::CallFrozenNativePos));
+}
+
+#[test]
+fn test_optimize_on_freeze_nested() {
+    // The optimization on freeze is a single bottom-up pass, and each expression is rebuilt
+    // from its optimized children, so folding an inner call exposes the outer one to folding
+    // in the same pass, and a condition folded to a constant removes its dead branch.
+    let program = r#"
+N = 3
+def test():
+    if len(str(N * 11)) != 2:
+        fail("unreachable")
+    return str(len(str(N * 1000)) + 1)
+"#;
+    test_instrs(&[BcOpcode::Const, BcOpcode::Return], program);
+    assert::eq("'5'", &format!("{}\ntest()", program));
+}
+
+#[test]
+fn test_for_else() {
+    let with_else = def_opcodes("def test(xs):\n  for x in xs:\n    break\n  else:\n    return 1");
+    assert!(with_else.contains(&BcOpcode::ForLoopElse));
+    assert!(!with_else.contains(&BcOpcode::ForLoop));
+    // Loops without `else` use the plain loop instruction.
+    let without_else = def_opcodes("def test(xs):\n  for x in xs:\n    break");
+    assert!(without_else.contains(&BcOpcode::ForLoop));
+    assert!(!without_else.contains(&BcOpcode::ForLoopElse));
+}
@@ -15,7 +15,7 @@
  * limitations under the License.
  */
 
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 
 use crate::{
     environment::{Globals, Module},
@@ -44,3 +44,30 @@ f()            # 2
     evaluator.eval_module(ast, &globals).unwrap();
     assert_eq!(4, counter.get());
 }
+
+#[test]
+fn before_stmt_local_variables() {
+    let module = Module::new();
+    let globals = Globals::new();
+    let mut evaluator = Evaluator::new(&module);
+    let seen = RefCell::new(Vec::new());
+    let before_stmt = |_span, eval: &mut Evaluator<'_, '_>| {
+        let locals = eval.local_variables();
+        if let Some(b) = locals.get("b") {
+            let a = locals.get("a").and_then(|a| a.unpack_int());
+            seen.borrow_mut().push((a, b.unpack_int()));
+        }
+    };
+    evaluator.before_stmt(&before_stmt);
+
+    let program = "\
+def f(a):
+  b = a + 1
+  return b
+f(10)
+";
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended).unwrap();
+    evaluator.eval_module(ast, &globals).unwrap();
+    // `b` is only visible once assigned, and top-level statements see module variables.
+    assert_eq!(vec![(Some(10), Some(11))], *seen.borrow());
+}
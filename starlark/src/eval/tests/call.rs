@@ -17,7 +17,13 @@
 
 //! Test call expression and parameter binding.
 
-use crate::{assert, assert::Assert};
+use derive_more::Display;
+
+use crate::{
+    self as starlark, assert,
+    assert::Assert,
+    values::{Heap, StarlarkValue, Value},
+};
 
 #[test]
 fn funcall_test() {
@@ -234,3 +240,78 @@ f(1)
         "Missing parameter `y`",
     );
 }
+
+#[test]
+fn test_kwargs_custom_mapping() {
+    #[derive(Debug, Display)]
+    #[display(fmt = "config")]
+    struct Config;
+    starlark_simple_value!(Config);
+
+    impl<'v> StarlarkValue<'v> for Config {
+        starlark_type!("config");
+        fn kwargs_items(&self) -> Option<Vec<(String, Value<'v>)>> {
+            Some(vec![
+                ("x".to_owned(), Value::new_int(1)),
+                ("y".to_owned(), Value::new_int(2)),
+            ])
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(|builder| builder.set("config", Config));
+    a.pass(
+        r#"
+def f(x, y, z = 0):
+    return (x, y, z)
+def g(**kwargs):
+    return kwargs
+assert_eq(f(**config), (1, 2, 0))
+assert_eq(f(z = 3, **config), (1, 2, 3))
+assert_eq(g(**config), {"x": 1, "y": 2})
+assert_eq(dict(**config), {"x": 1, "y": 2})
+"#,
+    );
+    a.fail(
+        "def f(x, y): pass\nf(x = 0, **config)",
+        "occurs both explicitly and in **kwargs",
+    );
+    a.fail("def f(**kwargs): pass\nf(**[1])", "not a dictionary");
+}
+
+#[test]
+fn test_args_custom_iterable() {
+    #[derive(Debug, Display)]
+    #[display(fmt = "count({})", _0)]
+    struct Count(i32);
+    starlark_simple_value!(Count);
+
+    impl<'v> StarlarkValue<'v> for Count {
+        starlark_type!("count");
+        fn iterate<'a>(
+            &'a self,
+            _heap: &'v Heap,
+        ) -> anyhow::Result<Box<dyn Iterator<Item = Value<'v>> + 'a>>
+        where
+            'v: 'a,
+        {
+            Ok(box (1..=self.0).map(Value::new_int))
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(|builder| builder.set("count", Count(3)));
+    a.pass(
+        r#"
+def f(a, b = 0, c = 0, *args):
+    return (a, b, c, args)
+assert_eq(f(*count), (1, 2, 3, ()))
+assert_eq(f(0, *count), (0, 1, 2, (3,)))
+assert_eq(f(*range(2, 7, 2)), (2, 4, 6, ()))
+assert_eq(f(*range(5)), (0, 1, 2, (3, 4)))
+assert_eq(max(*count), 3)
+assert_eq(list(*[count]), [1, 2, 3])
+"#,
+    );
+    a.fail("def f(*args): pass\nf(*1)", "not iterable");
+}
@@ -17,7 +17,7 @@
 
 //! Test dict and list comprehension.
 
-use crate::assert;
+use crate::{assert, assert::Assert};
 
 // comprehensions should work whether they are at the root, or under a def
 // but these are actually quite different locations semantically, so test both
@@ -65,6 +65,28 @@ fn test_dict() {
     check_comp(&["{x: 1 for x in [0,1,2]} == {0: 1, 1: 1, 2: 1}"]);
 }
 
+#[test]
+fn test_two_fors_with_if() {
+    // Later clauses see bindings of earlier ones, results are in iteration order.
+    check_comp(&["[x*y for x in [1,2,3] for y in [3,2,1] if x != y] == [3, 2, 6, 2, 6, 3]"]);
+    check_comp(&["[(x, y) for x in [1,2] if x > 1 for y in [x, x+1]] == [(2, 2), (2, 3)]"]);
+    // Filters are evaluated in order and stop at the first false one.
+    check_comp(&["[x for x in [0,1,2,4] if x != 0 if 4 // x > 1] == [1, 2]"]);
+    check_comp(&[
+        "seen = []",
+        "_ = [x for x in [1,2,3] if seen.append(('a', x)) == None if x != 2 if seen.append(('b', x)) == None]",
+        "seen == [('a', 1), ('b', 1), ('a', 2), ('a', 3), ('b', 3)]",
+    ]);
+}
+
+#[test]
+fn test_dict_with_if() {
+    check_comp(&["{x: y for x in [3,1,2] for y in [x*10] if x != 1} == {3: 30, 2: 20}"]);
+    check_comp(&[
+        "list({k: v for k, v in [('b', 1), ('a', 2), ('b', 3)] if v > 0}.items()) == [('b', 3), ('a', 2)]",
+    ]);
+}
+
 #[test]
 fn test_nested() {
     // Nested comprehensions
@@ -113,3 +135,16 @@ assert_eq(res, [1,2])
         "variable `x` referenced before assignment",
     );
 }
+
+#[test]
+fn test_dict_duplicate_keys() {
+    let program = "{k: v for k, v in [('a', 1), ('b', 2), ('a', 3)]}";
+    // Last value wins by default.
+    check_comp(&[&format!("{} == {{'a': 3, 'b': 2}}", program)]);
+
+    let mut a = Assert::new();
+    a.setup_eval(|eval| eval.set_error_on_duplicate_comprehension_keys(true));
+    a.fail(program, "key repeated for `\"a\"`");
+    a.fail(&format!("def f():\n  return {}\nf()", program), "repeated");
+    a.is_true("{k: v for k, v in [('a', 1), ('b', 2)]} == {'a': 1, 'b': 2}");
+}
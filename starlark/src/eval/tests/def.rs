@@ -17,7 +17,14 @@
 
 //! Test for `def` and `lambda`.
 
-use crate::{assert, assert::Assert, environment::Module, eval::Evaluator};
+use crate::{
+    assert,
+    assert::Assert,
+    environment::Module,
+    errors::Diagnostic,
+    eval::{CompiledExpr, Evaluator},
+    values::FrozenValue,
+};
 
 #[test]
 fn test_lambda() {
@@ -187,3 +194,61 @@ fn test_lambda_errors() {
     // Test from https://github.com/facebookexperimental/starlark-rust/issues/36
     assert::fail("lambda a,a:a", "duplicated parameter name");
 }
+
+#[test]
+fn test_repr() {
+    assert::eq(
+        "'<function f(a, b, *args, c=1, **kwargs)>'",
+        "def f(a, b, *args, c = 1, **kwargs): pass\nrepr(f)",
+    );
+    assert::eq(
+        "'<function g(x, *, y=[1, \"a\"])>'",
+        "def g(x, *, y = [1, 'a']): pass\nstr(g)",
+    );
+    assert::eq("'<function lambda(x)>'", "repr(lambda x: x)");
+    assert::eq("'<function len(a)>'", "repr(len)");
+    // Backtraces still only show the function name.
+    let e = assert::fail("def f(a = 1): fail('oops')\nf()", "oops");
+    let frames = &e.downcast_ref::<Diagnostic>().unwrap().call_stack;
+    assert!(frames.iter().any(|x| x.name == "f"));
+    assert!(frames.iter().all(|x| !x.name.contains("<function")));
+}
+
+#[test]
+fn test_visit_compiled_exprs() {
+    fn count(e: CompiledExpr, calls: &mut usize, consts: &mut Vec<String>) {
+        match e.kind() {
+            "Call" => *calls += 1,
+            "Value" => consts.push(e.as_value().unwrap().to_value().to_repr()),
+            _ => {}
+        }
+        e.visit_exprs(&mut |e| count(e, calls, consts));
+    }
+
+    let mut a = Assert::new();
+    let m = a.module(
+        "m.star",
+        r#"
+def f(x):
+    if x:
+        return str(x)
+    return [x, 1, "a"]
+"#,
+    );
+    let f = m.get("f").unwrap();
+    let mut calls = 0;
+    let mut consts = Vec::new();
+    assert!(CompiledExpr::visit_def(
+        f.value().unpack_frozen().unwrap(),
+        &mut |e| count(e, &mut calls, &mut consts)
+    ));
+    assert_eq!(1, calls);
+    // `str` itself is a constant, followed by `1` and `"a"`.
+    assert_eq!(3, consts.len());
+    assert_eq!(&["1", "\"a\""], &consts[1..]);
+
+    assert!(!CompiledExpr::visit_def(
+        FrozenValue::new_none(),
+        &mut |_| panic!("not a def")
+    ));
+}
@@ -105,7 +105,7 @@ fn test_export_as() {
     a.globals_add(exporter);
     a.module(
         "a",
-        "x = exporter(1); y = x; longer_name = exporter(2); arrayed = [exporter(3)]",
+        "x = exporter(1); y = x; longer_name = exporter(2); arrayed = [exporter(3)]; (p, q) = (exporter(4), exporter(5))",
     );
     // could reasonably be x=1 or y=1 twice, since the order
     // of calls to export_as is not defined
@@ -118,7 +118,12 @@ load('a', 'x', 'y', 'longer_name', 'arrayed')
 v = str((x, y, longer_name, arrayed[0]))
 v == '{}' or v == '{}'"#,
         opt1, opt2
-    ))
+    ));
+    a.is_true(
+        r#"
+load('a', 'p', 'q')
+repr(p) == 'p=4' and repr(q) == 'q=5'"#,
+    );
 }
 
 #[test]
@@ -433,6 +438,98 @@ fn test_derive_attrs() {
     a.eq("example.nested.foo", "\"bar\"");
 }
 
+#[test]
+fn test_iterate_snapshot() {
+    use crate::values::Heap;
+
+    #[derive(Debug, Display)]
+    #[display(fmt = "squares({})", _0)]
+    struct Squares(i32);
+    starlark_simple_value!(Squares);
+    impl<'v> StarlarkValue<'v> for Squares {
+        starlark_type!("squares");
+
+        fn iterate_snapshot(&self, _heap: &'v Heap) -> anyhow::Result<Vec<Value<'v>>> {
+            Ok((0..self.0).map(|x| Value::new_int(x * x)).collect())
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(|gb| gb.set("squares", Squares(4)));
+    a.pass(
+        r#"
+res = []
+for x in squares:
+    res.append(x)
+assert_eq(res, [0, 1, 4, 9])
+assert_eq([x for x in squares if x > 1], [4, 9])
+assert_eq(list(squares), [0, 1, 4, 9])
+"#,
+    );
+}
+
+#[test]
+fn test_dyn_attrs() {
+    use crate::values::{DynAttr, Heap};
+
+    #[derive(Debug, Display)]
+    #[display(fmt = "object")]
+    struct Object(Vec<(String, i32)>);
+    starlark_simple_value!(Object);
+    impl<'v> StarlarkValue<'v> for Object {
+        starlark_type!("object");
+        starlark_dyn_attrs!();
+    }
+    impl<'v> DynAttr<'v> for Object {
+        fn attr(&self, attribute: &str, _heap: &'v Heap) -> Option<Value<'v>> {
+            self.0
+                .iter()
+                .find(|(k, _)| k == attribute)
+                .map(|(_, v)| Value::new_int(*v))
+        }
+
+        fn attr_names(&self) -> Vec<String> {
+            self.0.iter().map(|(k, _)| k.clone()).collect()
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(|gb| {
+        gb.set(
+            "obj",
+            Object(vec![("x".to_owned(), 1), ("y".to_owned(), 2)]),
+        )
+    });
+    a.eq("dir(obj)", "['x', 'y']");
+    a.eq("obj.y", "2");
+    a.eq("getattr(obj, 'x')", "1");
+    a.is_true("not hasattr(obj, 'z')");
+    a.is_true("all([hasattr(obj, k) and getattr(obj, k) != None for k in dir(obj)])");
+    a.fail("obj.z", "has no attribute");
+}
+
+#[test]
+fn test_eval_function_from_native() {
+    #[starlark_module]
+    fn module(builder: &mut GlobalsBuilder) {
+        fn apply(f: Value<'v>, x: Value<'v>) -> Value<'v> {
+            eval.eval_function(f, &[x], &[])
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(module);
+    a.eq("42", "apply(lambda x: x * 2, 21)");
+    a.pass(
+        r#"
+def twice(x):
+    return apply(lambda y: y + 1, apply(lambda y: y + 1, x))
+assert_eq(apply(twice, 1), 3)
+"#,
+    );
+    a.fail("apply(lambda x: fail('oops ' + x), 'here')", "oops here");
+}
+
 #[test]
 fn test_eval_function() {
     let fun = assert::pass(
@@ -450,3 +547,168 @@ fun
         .unwrap();
     assert_eq!(v.unpack_str(), Some("(8, \"hello\", 1)"))
 }
+
+#[test]
+fn test_try_to_bool() {
+    #[derive(Debug, Display)]
+    #[display(fmt = "unresolved")]
+    struct Unresolved;
+    starlark_simple_value!(Unresolved);
+    impl<'v> StarlarkValue<'v> for Unresolved {
+        starlark_type!("unresolved");
+
+        fn try_to_bool(&self) -> anyhow::Result<bool> {
+            Err(anyhow::anyhow!("truth value of unresolved is undefined"))
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(|gb| gb.set("unresolved", Unresolved));
+    let msg = "truth value of unresolved is undefined";
+    a.fail("if unresolved:\n    pass", msg);
+    a.fail("not unresolved", msg);
+    a.fail("unresolved and 1", msg);
+    a.fail("unresolved or 1", msg);
+    a.fail("1 if unresolved else 2", msg);
+    a.fail("[x for x in [1] if unresolved]", msg);
+    a.fail("bool(unresolved)", msg);
+    a.fail("any([unresolved])", msg);
+    a.fail("all([unresolved])", msg);
+    a.fail("sorted([2, 1], reverse = unresolved)", msg);
+    a.is_true("bool(1) and not bool([])");
+}
+
+#[test]
+fn test_trace_custom_container() {
+    use crate::{
+        collections::SmallMap,
+        values::{Freezer, Heap, SimpleValue, Trace, Tracer},
+    };
+
+    #[derive(Debug, AnyLifetime, Display)]
+    #[display(fmt = "container")]
+    struct Container<'v> {
+        items: Vec<Value<'v>>,
+        named: SmallMap<String, Value<'v>>,
+        first: Option<Value<'v>>,
+    }
+
+    // Written by hand rather than derived, to check the public tracing API is enough.
+    unsafe impl<'v> Trace<'v> for Container<'v> {
+        fn trace(&mut self, tracer: &Tracer<'v>) {
+            self.items.trace(tracer);
+            self.named.trace(tracer);
+            if let Some(first) = &mut self.first {
+                tracer.trace(first);
+            }
+        }
+    }
+
+    impl<'v> StarlarkValue<'v> for Container<'v>
+    where
+        Self: AnyLifetime<'v>,
+    {
+        starlark_type!("container");
+
+        fn at(&self, index: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+            let index = index.unpack_int().unwrap() as usize;
+            Ok(self.items[index])
+        }
+
+        fn get_attr(&self, attribute: &str, _heap: &'v Heap) -> Option<Value<'v>> {
+            match attribute {
+                "first" => self.first,
+                _ => self.named.get(attribute).copied(),
+            }
+        }
+    }
+
+    #[derive(Debug, AnyLifetime, Display)]
+    #[display(fmt = "container")]
+    struct FrozenContainer;
+
+    impl<'v> StarlarkValue<'v> for FrozenContainer {
+        starlark_type!("container");
+    }
+
+    impl SimpleValue for FrozenContainer {}
+
+    impl<'v> Freeze for Container<'v> {
+        type Frozen = FrozenContainer;
+        fn freeze(self, _freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+            Ok(FrozenContainer)
+        }
+    }
+
+    #[starlark_module]
+    fn module(builder: &mut GlobalsBuilder) {
+        fn container(items: Vec<Value<'v>>, named: SmallMap<String, Value<'v>>) -> Value<'v> {
+            let first = items.first().copied();
+            Ok(heap.alloc_complex(Container {
+                items,
+                named,
+                first,
+            }))
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(module);
+    // The values are only reachable through the container when the garbage is collected.
+    a.pass(
+        r#"
+def make():
+    return container([str(i) * 2 for i in range(3)], {"a": ["x"] * 3})
+c = make()
+garbage_collect()
+assert_eq(c[1], "11")
+assert_eq(c[2], "22")
+assert_eq(c.first, "00")
+assert_eq(c.a, ["x", "x", "x"])
+"#,
+    );
+}
+
+#[test]
+fn test_repr_from_display() {
+    #[derive(Debug, Display)]
+    #[display(fmt = "point({}, {})", _0, _1)]
+    struct Point(i32, i32);
+    starlark_simple_value!(Point);
+
+    impl<'v> StarlarkValue<'v> for Point {
+        starlark_type!("point");
+    }
+
+    #[starlark_module]
+    fn module(builder: &mut GlobalsBuilder) {
+        fn point(x: i32, y: i32) -> Point {
+            Ok(Point(x, y))
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(module);
+    a.eq("'point(1, 2)'", "repr(point(1, 2))");
+    a.eq("'point(1, 2)'", "str(point(1, 2))");
+    a.eq("'<point(3, 4)>'", "'<{}>'.format(point(3, 4))");
+    a.eq("'[point(0, -1)]'", "repr([point(0, -1)])");
+}
+
+#[test]
+fn test_repr_ascii_custom_value() {
+    #[derive(Debug, Display)]
+    #[display(fmt = "µs")]
+    struct Micros;
+    starlark_simple_value!(Micros);
+
+    impl<'v> StarlarkValue<'v> for Micros {
+        starlark_type!("micros");
+    }
+
+    let mut a = Assert::new();
+    a.setup_eval(|eval| eval.set_repr_ascii(true));
+    a.globals_add(|gb| gb.set("micros", Micros));
+    // Only string literals are escaped, not the text other values write.
+    a.eq("'[µs, \"\\\\u{b5}s\"]'", "repr([micros, 'µs'])");
+}
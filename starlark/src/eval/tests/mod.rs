@@ -15,20 +15,27 @@
  * limitations under the License.
  */
 
-use std::{cell::RefCell, fmt::Write};
+use std::{
+    cell::RefCell,
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
 
 use derive_more::Display;
-use gazebo::any::AnyLifetime;
+use gazebo::{any::AnyLifetime, dupe::Dupe};
 
 use crate::{
     self as starlark,
     assert::{self, Assert},
-    collections::SmallMap,
+    collections::{symbol_map::Symbol, SmallMap},
     environment::{Globals, GlobalsBuilder, Module},
     errors::Diagnostic,
-    eval::Evaluator,
+    eval::{Arguments, CallbackFileLoader, Evaluator, FileLoader, OwnedArguments},
     syntax::{AstModule, Dialect},
-    values::{Freeze, Freezer, Heap, SimpleValue, StarlarkValue, Trace, UnpackValue, Value},
+    values::{
+        Freeze, Freezer, FrozenValue, Heap, SimpleValue, StarlarkValue, StringValue, Trace,
+        UnpackValue, Value, ValueLike,
+    },
 };
 
 mod basic;
@@ -84,15 +91,35 @@ fn test_tabs_fail() {
     a.dialect_set(|d| d.enable_tabs = false);
     a.fail("def f():\n\tpass", "Parse error");
     a.fail("def f():\n x\t=3", "Parse error");
+    a.fail(
+        "def f():\n\tx = 1\n        return x",
+        "tabs are not allowed",
+    );
+}
+
+#[test]
+fn test_tabs_standard() {
+    // Standard matches Bazel, where tabs are an error outside string literals.
+    let mut a = Assert::new();
+    a.dialect(&Dialect::Standard);
+    a.pass("def f():\n '\t'");
+    a.fail("def f():\n\tpass", "Parse error");
+    a.fail(
+        "def f():\n\tx = 1\n        return x",
+        "tabs are not allowed",
+    );
 }
 
 #[test]
 fn test_tabs_pass() {
     assert::pass("def f():\n '\t'");
     let mut a = Assert::new();
-    a.dialect(&Dialect::Standard);
+    a.dialect(&Dialect::Extended);
     a.pass("def f():\n\tpass");
     a.pass("def f():\n x\t=3");
+    // A tab is equivalent to 8 spaces, so these lines are at the same indentation
+    a.pass("def f():\n\tx = 1\n        return x\nassert_eq(f(), 1)");
+    a.fail("def f():\n\tx = 1\n    return x", "incorrect indentation");
 }
 
 #[test]
@@ -357,6 +384,104 @@ assert_eq(repr(s2), "$[2, 1, 3]")
     );
 }
 
+#[test]
+fn test_radd_order() {
+    // Records which of `add` and `radd` produced the result.
+    #[derive(Debug, Display)]
+    #[display(fmt = "tag")]
+    struct Tag;
+    starlark_simple_value!(Tag);
+
+    impl<'v> StarlarkValue<'v> for Tag {
+        starlark_type!("tag");
+        fn radd(&self, _lhs: Value<'v>, heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
+            Some(Ok(heap.alloc("radd")))
+        }
+        fn add(&self, _rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+            Ok(heap.alloc("add"))
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(|builder| builder.set("tag", Tag));
+    a.pass(
+        r#"
+assert_eq([1] + tag, "radd")
+assert_eq("x" + tag, "radd")
+assert_eq(1 + tag, "radd")
+assert_eq(tag + [1], "add")
+# The right operand's `radd` is tried first.
+assert_eq(tag + tag, "radd")
+xs = [1]
+xs += tag
+assert_eq(xs, "radd")
+"#,
+    );
+}
+
+#[test]
+fn test_is_in_custom_container() {
+    // A container holding the ints in a range, without storing them.
+    #[derive(Debug, Display)]
+    #[display(fmt = "Span")]
+    struct Span(i32, i32);
+    starlark_simple_value!(Span);
+
+    impl<'v> StarlarkValue<'v> for Span {
+        starlark_type!("span");
+        fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+            Ok(matches!(other.unpack_int(), Some(x) if self.0 <= x && x < self.1))
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(|builder| builder.set("c", Span(3, 7)));
+    a.pass(
+        r#"
+assert_eq(5 in c, True)
+assert_eq(7 in c, False)
+assert_eq("x" in c, False)
+assert_eq(5 not in c, False)
+assert_eq([x for x in range(10) if x in c], [3, 4, 5, 6])
+"#,
+    );
+    // The item is not a container, so `c in 5` fails.
+    a.fail("c in 5", "not supported");
+}
+
+#[test]
+fn test_collect_str() {
+    // Renders with delimiters under `repr()`, but without under `str()`.
+    #[derive(Debug, Display)]
+    #[display(fmt = "<path>")]
+    struct Path(&'static str);
+    starlark_simple_value!(Path);
+
+    impl<'v> StarlarkValue<'v> for Path {
+        starlark_type!("path");
+        fn collect_repr(&self, collector: &mut String) {
+            write!(collector, "<{}>", self.0).unwrap()
+        }
+        fn collect_str(&self, collector: &mut String) {
+            collector.push_str(self.0)
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(|builder| builder.set("p", Path("a/b")));
+    a.pass(
+        r#"
+assert_eq(repr(p), "<a/b>")
+assert_eq(str(p), "a/b")
+assert_eq("%s" % p, "a/b")
+assert_eq("%r" % p, "<a/b>")
+assert_eq("x/{}".format(p), "x/a/b")
+assert_eq(str([p]), "[<a/b>]")
+"#,
+    );
+    a.fail("fail(p)", "fail: a/b");
+}
+
 #[test]
 fn test_compound_assignment() {
     assert::pass(
@@ -493,6 +618,56 @@ fn test_load_reexport() {
     );
 }
 
+#[test]
+fn test_module_set_before_eval() -> anyhow::Result<()> {
+    let globals = Globals::standard();
+    let module = Module::new();
+    module.set("name", module.heap().alloc("foo"));
+
+    let mut eval = Evaluator::new(&module);
+    let ast = AstModule::parse(
+        "BUILD",
+        "greeting = 'hello ' + name\nname = 'bar'".to_owned(),
+        &Dialect::Standard,
+    )?;
+    let _: Value = eval.eval_module(ast, &globals)?;
+
+    assert_eq!(
+        Some("hello foo"),
+        module.get("greeting").unwrap().unpack_str()
+    );
+    assert_eq!(Some("bar"), module.get("name").unwrap().unpack_str());
+    Ok(())
+}
+
+#[test]
+fn test_instruction_budget() {
+    let globals = Globals::standard();
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.set_instruction_budget(100);
+    let ast = AstModule::parse(
+        "budget.star",
+        r#"
+xs = []
+def f():
+    for i in range(1000000):
+        xs.append(i)
+f()
+"#
+        .to_owned(),
+        &Dialect::Standard,
+    )
+    .unwrap();
+    let err = eval.eval_module(ast, &globals).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("budget exhausted after executing 100"));
+    // Each loop iteration executes one statement, so we stop near the budget.
+    let appended = module.get("xs").unwrap().length().unwrap();
+    assert!((90..100).contains(&appended), "{}", appended);
+}
+
 #[test]
 fn test_module_visibility_preserved_by_evaluator() -> anyhow::Result<()> {
     // Make sure that when we use a module in the evaluator, the entering / exiting the
@@ -546,6 +721,32 @@ fn test_load_did_you_mean() {
     );
 }
 
+#[test]
+fn test_load_cycle() {
+    let sources = hashmap![
+        "main.star" => "load('a.star', 'a')",
+        "a.star" => "load('b.star', 'b')\na = 1",
+        "b.star" => "load('a.star', 'a')\nb = 1",
+    ];
+    let globals = Globals::standard();
+    let loader = CallbackFileLoader::new(|path: &str, loader: &dyn FileLoader| {
+        let ast = AstModule::parse(path, sources[path].to_owned(), &Dialect::Standard)?;
+        let module = Module::new();
+        {
+            let mut eval = Evaluator::new(&module);
+            eval.set_loader(loader);
+            eval.eval_module(ast, &globals)?;
+        }
+        module.freeze()
+    });
+    let err = loader.load("main.star").unwrap_err().to_string();
+    assert!(
+        err.contains("import cycle: a.star -> b.star -> a.star"),
+        "Unexpected error: {}",
+        err
+    );
+}
+
 #[test]
 fn test_getattr_did_you_mean_builtin() {
     assert::fail(
@@ -655,6 +856,106 @@ bar(["a","b","c"])
     );
 }
 
+#[test]
+fn test_for_else() {
+    assert::pass(
+        r#"
+def find(xs, y):
+    for x in xs:
+        if x == y:
+            res = "found"
+            break
+    else:
+        res = "not found"
+    return res
+
+assert_eq("found", find([1, 2, 3], 2))
+assert_eq("not found", find([1, 2, 3], 4))
+assert_eq("not found", find([], 4))
+
+def nested():
+    res = []
+    for x in [1, 2, 3]:
+        for y in [10, 20]:
+            res.append(x + y)
+        else:
+            if x == 2:
+                # Stops the outer loop.
+                break
+    else:
+        res.append("unreachable")
+    return res
+
+assert_eq([11, 21, 12, 22], nested())
+
+def with_continue_and_return(xs):
+    for x in xs:
+        if x < 0:
+            return "negative"
+        continue
+    else:
+        return "done"
+
+assert_eq("done", with_continue_and_return([1, 2]))
+assert_eq("negative", with_continue_and_return([1, -2]))
+
+# Top-level loops also support `else`.
+found = None
+for x in range(5):
+    if x * x > 5:
+        found = x
+        break
+else:
+    found = -1
+assert_eq(3, found)
+"#,
+    );
+    let mut a = Assert::new();
+    a.dialect_set(|d| d.enable_for_else = false);
+    a.fail(
+        "def f():\n  for x in []:\n    pass\n  else:\n    pass",
+        "not allowed in this dialect",
+    );
+}
+
+#[test]
+fn test_assert_statement() {
+    let mut a = Assert::new();
+    a.dialect_set(|d| d.enable_assert_statement = true);
+    a.setup_eval(|eval| eval.set_assertions_enabled(true));
+    let program = "x = 1\nassert x == 2, 'x is ' + str(x)";
+    let err = a.fail(program, "Assertion failed: x is 1");
+    let diag = err.downcast_ref::<Diagnostic>().unwrap();
+    assert_eq!(diag.span.as_ref().unwrap().to_string(), "assert.bzl:2:1-32");
+    a.fail("def f(x):\n  assert x\nf([])", "Assertion failed");
+    // The message is only evaluated if the assertion fails.
+    a.pass(
+        r#"
+log = []
+def f(x):
+    assert x > 0, log.append(x)
+    return x
+assert f(3) == 3
+assert log == []
+"#,
+    );
+
+    // Disabled assertions are not compiled, so nothing is evaluated.
+    let mut a = Assert::new();
+    a.dialect_set(|d| d.enable_assert_statement = true);
+    a.pass(
+        r#"
+log = []
+assert False, log.append(1)
+assert log.append(2)
+def f():
+    assert False
+f()
+assert_eq(log, [])
+"#,
+    );
+}
+
 #[test]
 fn test_label_assign() {
     // Test the a.b = c construct.
@@ -876,3 +1177,286 @@ animal("Joe")
     let animal = SmallMap::<String, Value>::unpack_value(res).unwrap();
     println!("animal = {:?}", animal);
 }
+
+#[test]
+fn test_arguments_into_owned() {
+    fn run(eval: &mut Evaluator, code: &str) {
+        let ast = AstModule::parse("code.star", code.to_owned(), &Dialect::Standard).unwrap();
+        eval.eval_module(ast, &Globals::standard()).unwrap();
+    }
+
+    let m = Module::new();
+    let mut eval = Evaluator::new(&m);
+    run(
+        &mut eval,
+        "def f(*args, **kwargs): return (args, kwargs)\nxs = [2]\nkw = {'c': 3}",
+    );
+
+    let heap = m.heap();
+    let a = StringValue::new(heap.alloc("a")).unwrap();
+    let owned = Arguments {
+        pos: &[Value::new_int(1)],
+        named: &[Value::new_int(0)],
+        names: &[(Symbol::new("a"), a)],
+        args: Some(m.get("xs").unwrap()),
+        kwargs: Some(m.get("kw").unwrap()),
+    }
+    .into_owned(heap)
+    .unwrap();
+
+    // The copy is not affected by changes to `*args` and `**kwargs`.
+    run(&mut eval, "xs.append(9)\nkw['d'] = 4");
+    let f = m.get("f").unwrap();
+    for _ in 0..2 {
+        let res = f.invoke(None, owned.as_arguments(), &mut eval).unwrap();
+        assert_eq!(r#"((1, 2), {"a": 0, "c": 3})"#, res.to_repr());
+    }
+}
+
+#[test]
+fn test_arguments_forward() {
+    // Forwards calls to the module's `target` function, adding a keyword argument.
+    #[derive(Debug, Display)]
+    #[display(fmt = "proxy")]
+    struct Proxy;
+    starlark_simple_value!(Proxy);
+
+    impl<'v> StarlarkValue<'v> for Proxy {
+        starlark_type!("proxy");
+        fn invoke(
+            &self,
+            _me: Value<'v>,
+            location: Option<crate::codemap::Span>,
+            args: Arguments<'v, '_>,
+            eval: &mut Evaluator<'v, '_>,
+        ) -> anyhow::Result<Value<'v>> {
+            let heap = eval.heap();
+            let pos = args.positions(heap)?.collect();
+            let mut named = args.named_args()?.collect::<Vec<_>>();
+            named.push(("extra", Value::new_bool(true)));
+            let args = OwnedArguments::new(pos, named, heap);
+            let target = eval.module_env.get("target").unwrap();
+            target.invoke(location, args.as_arguments(), eval)
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(|builder| builder.set("proxy", Proxy));
+    let target = "def target(*args, **kwargs): return (args, kwargs)\n";
+    a.eq(
+        "((1, 2), {'a': 3, 'b': 4, 'extra': True})",
+        &format!("{}proxy(1, *[2], a = 3, **{{'b': 4}})", target),
+    );
+    a.eq("((), {'extra': True})", &format!("{}proxy()", target));
+    a.fail(
+        &format!("{}proxy(a = 1, **{{'a': 2}})", target),
+        "occurs both explicitly and in **kwargs",
+    );
+}
+
+#[test]
+fn test_get_type_dynamic() {
+    // A family of values sharing the `record` type, each reporting its own shape.
+    #[derive(Debug, Display)]
+    #[display(fmt = "{}()", shape)]
+    struct Record {
+        shape: String,
+    }
+    starlark_simple_value!(Record);
+
+    impl<'v> StarlarkValue<'v> for Record {
+        starlark_type!("record");
+        fn get_type_dynamic(&self) -> Option<String> {
+            Some(format!("record<{}>", self.shape))
+        }
+        fn matches_type(&self, ty: &str) -> bool {
+            ty == "record" || Some(ty) == self.get_type_dynamic().as_deref()
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(|builder| {
+        builder.set(
+            "point",
+            Record {
+                shape: "Point".to_owned(),
+            },
+        );
+        builder.set(
+            "size",
+            Record {
+                shape: "Size".to_owned(),
+            },
+        );
+    });
+    a.pass(
+        r#"
+assert_eq(type(point), "record<Point>")
+assert_eq(type(size), "record<Size>")
+assert_true(type(point) != "record")
+def is_point(x):
+    return type(x) == "record<Point>"
+assert_true(is_point(point))
+assert_false(is_point(size))
+def any_record(x: "record") -> "record<Point>":
+    return point
+any_record(size)
+"#,
+    );
+}
+
+#[test]
+fn test_global_access_observer() {
+    let seen = RefCell::new(Vec::new());
+    let m = Module::new();
+    let mut eval = Evaluator::new(&m);
+    eval.set_global_access_observer(box |name| seen.borrow_mut().push(name.to_owned()));
+    let ast = AstModule::parse(
+        "code.star",
+        r#"
+x = len([1]) + int("2")
+def f(y: "string"):
+    return str(len(y))
+if x > 5:
+    print(x)
+"#
+        .to_owned(),
+        &Dialect::Standard,
+    )
+    .unwrap();
+    eval.eval_module(ast, &Globals::standard()).unwrap();
+    // Names in a function which is never called and in a branch not taken are included.
+    assert_eq!(vec!["len", "int", "str", "print"], *seen.borrow());
+}
+
+#[test]
+fn test_module_names_before_freeze() {
+    let m = Module::new();
+    let mut eval = Evaluator::new(&m);
+    let globals = Globals::standard();
+    // Evaluate statements one by one, like a REPL.
+    for code in [
+        "x = 1\n_private = 2",
+        "y = x + 1",
+        "z = 3\nfail('oops')\nw = 4",
+    ] {
+        let ast = AstModule::parse("repl.star", code.to_owned(), &Dialect::Extended).unwrap();
+        let _ = eval.eval_module(ast, &globals);
+    }
+    assert_eq!(Some(2), m.get("y").and_then(|y| y.unpack_int()));
+    // Private and not yet assigned variables are not listed.
+    assert_eq!(vec!["x", "y", "z"], m.names());
+}
+
+#[test]
+fn test_eval_const_expr() {
+    fn eval_const_expr(code: &str) -> Option<String> {
+        let ast = AstModule::parse("const.star", code.to_owned(), &Dialect::Extended).unwrap();
+        ast.eval_const_expr(&Globals::standard())
+            .map(|v| v.value().to_string())
+    }
+
+    assert_eq!(Some("7"), eval_const_expr("1 + 2 * 3").as_deref());
+    assert_eq!(Some("2.5"), eval_const_expr("5 / 2").as_deref());
+    assert_eq!(Some("3"), eval_const_expr("len('abc')").as_deref());
+    assert_eq!(None, eval_const_expr("x"));
+    assert_eq!(None, eval_const_expr("1 // 0"));
+    assert_eq!(None, eval_const_expr("x = 1"));
+    assert_eq!(None, eval_const_expr("1\n2"));
+}
+
+#[test]
+fn test_globals_dynamic_resolver() {
+    let resolved = Arc::new(Mutex::new(Vec::new()));
+    let mut globals = GlobalsBuilder::standard();
+    globals.set_dynamic_resolver({
+        let resolved = resolved.dupe();
+        move |name, heap| {
+            let target = name.strip_prefix("host_")?;
+            resolved.lock().unwrap().push(name.to_owned());
+            Some(heap.alloc(format!("//{}", target)))
+        }
+    });
+    let globals = globals.build();
+    let run = |code: &str| {
+        let m = Module::new();
+        {
+            let mut eval = Evaluator::new(&m);
+            let ast = AstModule::parse("code.star", code.to_owned(), &Dialect::Standard).unwrap();
+            eval.eval_module(ast, &globals)?;
+        }
+        m.freeze()
+    };
+
+    let m = run(r#"
+a = host_a + host_a
+def f():
+    return host_b
+b = f()
+# Registered globals are not resolved dynamically.
+n = len(host_b)
+"#)
+    .unwrap();
+    assert_eq!("//a//a", m.get("a").unwrap().unpack_str().unwrap());
+    assert_eq!("//b", m.get("b").unwrap().unpack_str().unwrap());
+    assert_eq!(Some(3), m.get("n").unwrap().unpack_int());
+    // Resolved once per name for the module.
+    assert_eq!(vec!["host_a", "host_b"], *resolved.lock().unwrap());
+
+    let err = run("x = other").unwrap_err();
+    assert!(
+        err.to_string().contains("Variable `other` not found"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_frozen_value_downcast() {
+    #[derive(Debug, Display, PartialEq)]
+    #[display(fmt = "payload")]
+    struct Payload(String);
+    starlark_simple_value!(Payload);
+
+    impl<'v> StarlarkValue<'v> for Payload {
+        starlark_type!("payload");
+    }
+
+    let m = Module::new();
+    m.set("x", m.heap().alloc(Payload("cached".to_owned())));
+    let frozen = m.freeze().unwrap();
+    let owned = frozen.get("x").unwrap();
+    // Safe because `owned` keeps the frozen heap alive.
+    let x: FrozenValue = unsafe { owned.unchecked_frozen_value() };
+    assert_eq!(
+        Some(&Payload("cached".to_owned())),
+        x.downcast_ref::<Payload>()
+    );
+    assert_eq!(None, FrozenValue::new_int(1).downcast_ref::<Payload>());
+    assert_eq!("cached", owned.downcast::<Payload>().unwrap().as_ref().0);
+}
+
+#[test]
+fn test_method_call_site_different_types() {
+    // The same call site resolves `index` in the methods of each type,
+    // and falls back to `get_attr` for values without methods.
+    assert::pass(
+        r#"
+def index(x):
+    return x.index("b")
+for _ in range(3):
+    assert_eq(1, index("abc"))
+    assert_eq(2, index(["a", "c", "b"]))
+    assert_eq(0, index(struct(index = lambda x: 0)))
+"#,
+    );
+    assert::fail(
+        r#"
+def pop(x):
+    return x.pop()
+assert_eq(3, pop([1, 2, 3]))
+pop("abc")
+"#,
+        "has no attribute `pop`",
+    );
+}
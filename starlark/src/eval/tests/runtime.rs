@@ -29,8 +29,11 @@ use crate as starlark;
 use crate::{
     assert,
     assert::Assert,
-    environment::GlobalsBuilder,
-    values::{any::StarlarkAny, FrozenHeap, Heap},
+    environment::{Globals, GlobalsBuilder, Module},
+    errors::Diagnostic,
+    eval::Evaluator,
+    syntax::{AstModule, Dialect},
+    values::{any::StarlarkAny, FrozenHeap, Heap, ValueError},
 };
 
 #[test]
@@ -45,6 +48,39 @@ assert_eq(y, str(x))
     );
 }
 
+fn run(eval: &mut Evaluator, code: &str) -> anyhow::Result<()> {
+    let ast = AstModule::parse("test.star", code.to_owned(), &Dialect::Standard)?;
+    eval.eval_module(ast, &Globals::standard())?;
+    Ok(())
+}
+
+#[test]
+fn test_trigger_gc() {
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+
+    // Stays below the threshold for an automatic collection.
+    run(&mut eval, "x = [str(i) for i in range(1000)]\nx = None").unwrap();
+    let before = module.heap().allocated_bytes();
+    eval.trigger_gc();
+    run(&mut eval, "y = 1").unwrap();
+    assert!(module.heap().allocated_bytes() < before);
+    assert_eq!(Some(1), module.get("y").and_then(|y| y.unpack_int()));
+}
+
+#[test]
+fn test_max_object_count() {
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.set_max_object_count(1000);
+    run(&mut eval, "xs = [[] for _ in range(100)]").unwrap();
+    let err = run(&mut eval, "ys = [[] for _ in range(1000000)]").unwrap_err();
+    assert!(err.to_string().contains("the limit is 1000"), "{}", err);
+    // Stopped at the limit, not after allocating everything.
+    assert!(module.heap().allocated_count() < 1100);
+    assert!(module.get("ys").is_none());
+}
+
 #[test]
 fn test_deallocation() {
     // Check that we really do deallocate values we create
@@ -228,3 +264,68 @@ fn test_display_debug() {
     assert_eq!(format!("{:?}", v), "FrozenValue(\"test\")");
     assert_eq!(format!("{:#?}", v), "FrozenValue(\n    \"test\",\n)");
 }
+
+#[test]
+fn test_division_by_zero_error() {
+    // The error can be told apart from others without looking at the message.
+    fn check(program: &str, span: &str) {
+        let err = assert::fail(program, "divide by zero");
+        let diag = err.downcast_ref::<Diagnostic>().unwrap();
+        assert!(matches!(
+            diag.message.downcast_ref::<ValueError>(),
+            Some(ValueError::DivisionByZero)
+        ));
+        assert_eq!(diag.span.as_ref().unwrap().to_string(), span);
+    }
+
+    check("1 // 0", "assert.bzl:1:1-7");
+    check("1 % 0", "assert.bzl:1:1-6");
+    check("1 / 0", "assert.bzl:1:1-6");
+    check("1.0 / 0.0", "assert.bzl:1:1-10");
+    check("1.5 // 0", "assert.bzl:1:1-9");
+    check("1 % 0.0", "assert.bzl:1:1-8");
+    check(
+        "def f(a, b):\n    return a // b\nf(1, 0)",
+        "assert.bzl:2:12-18",
+    );
+}
+
+#[test]
+fn test_compare_unorderable_error() {
+    // Ordering values of different types is an error naming the operator and both types.
+    assert::fail(
+        "1 < 'a'",
+        "Operation `<` not supported for types `int` and `string`",
+    );
+    assert::fail(
+        "'a' >= 1",
+        "Operation `>=` not supported for types `string` and `int`",
+    );
+    assert::fail(
+        "True > 1",
+        "Operation `>` not supported for types `bool` and `int`",
+    );
+    assert::fail(
+        "1.5 <= None",
+        "Operation `<=` not supported for types `float` and `NoneType`",
+    );
+    assert::fail(
+        "[1] < ['a']",
+        "Operation `<` not supported for types `int` and `string`",
+    );
+    assert::fail(
+        "def f(x, y):\n    return x < y\nf((1,), 2)",
+        "Operation `<` not supported for types `tuple` and `int`",
+    );
+    // NaN ordering follows the Bazel spec referenced in `float.rs`:
+    // NaN is equal to itself and greater than every other number.
+    assert::all_true(
+        r#"
+float('nan') == float('nan')
+float('nan') > 1e300
+float('nan') >= float('+inf')
+not (float('nan') < 1)
+[float('nan')] > [1]
+"#,
+    );
+}
@@ -33,6 +33,32 @@ macro_rules! starlark_type {
     };
 }
 
+/// Define the [`get_attr`](crate::values::StarlarkValue::get_attr),
+/// [`has_attr`](crate::values::StarlarkValue::has_attr) and
+/// [`dir_attr`](crate::values::StarlarkValue::dir_attr) fields of
+/// [`StarlarkValue`](crate::values::StarlarkValue) in terms of
+/// [`DynAttr`](crate::values::DynAttr).
+#[macro_export]
+macro_rules! starlark_dyn_attrs {
+    () => {
+        fn get_attr(
+            &self,
+            attribute: &str,
+            heap: &'v $crate::values::Heap,
+        ) -> Option<$crate::values::Value<'v>> {
+            $crate::values::DynAttr::attr(self, attribute, heap)
+        }
+        fn has_attr(&self, attribute: &str) -> bool {
+            $crate::values::DynAttr::attr_names(self)
+                .iter()
+                .any(|x| x == attribute)
+        }
+        fn dir_attr(&self) -> Vec<String> {
+            $crate::values::DynAttr::attr_names(self)
+        }
+    };
+}
+
 /// Reduce boilerplate when making types instances of [`ComplexValue`](crate::values::ComplexValue)
 /// - see the [`ComplexValue`](crate::values::ComplexValue) docs for an example.
 #[macro_export]
@@ -35,9 +35,9 @@ use crate::{
     environment::GlobalsBuilder,
     eval::{Arguments, Evaluator},
     values::{
-        dict::Dict, function::FUNCTION_TYPE, none::NoneType, tuple::Tuple, Freeze, Freezer,
-        FrozenStringValue, FrozenValue, StarlarkValue, StringValue, StringValueLike, Trace, Value,
-        ValueLike,
+        dict::Dict, function::FUNCTION_TYPE, none::NoneType, num::Num, tuple::Tuple, Freeze,
+        Freezer, FrozenStringValue, FrozenValue, StarlarkValue, StringValue, StringValueLike,
+        Trace, Value, ValueError, ValueLike,
     },
 };
 
@@ -51,7 +51,7 @@ pub fn filter(builder: &mut GlobalsBuilder) {
                 if !v.is_none() {
                     res.push(v);
                 }
-            } else if func.invoke_pos(None, &[v], eval)?.to_bool() {
+            } else if func.invoke_pos(None, &[v], eval)?.try_to_bool()? {
                 res.push(v);
             }
         }
@@ -141,8 +141,58 @@ pub fn json(builder: &mut GlobalsBuilder) {
 
 #[starlark_module]
 pub fn abs(builder: &mut GlobalsBuilder) {
-    fn abs(ref x: i32) -> i32 {
-        Ok(x.abs())
+    /// The absolute value of an int or float.
+    /// Like other int arithmetic, `abs` of the smallest int fails with an overflow error.
+    fn abs(ref x: Num) -> Value<'v> {
+        match x {
+            Num::Int(x) => match x.checked_abs() {
+                Some(x) => Ok(Value::new_int(x)),
+                None => Err(ValueError::IntegerOverflow.into()),
+            },
+            Num::Float(x) => Ok(heap.alloc(x.abs())),
+        }
+    }
+}
+
+#[starlark_module]
+pub fn isinstance(builder: &mut GlobalsBuilder) {
+    /// Test whether a value matches a type, written as in a type annotation.
+    /// Types are strings, as returned by `type()` and the `.type` attribute of constructors,
+    /// so `isinstance(1, int.type)`, `isinstance(1, "int")` and `type(1) == int.type` agree.
+    /// Compound annotations work too, e.g. `isinstance(x, ["int", None])`.
+    fn isinstance(ref x: Value, ref ty: Value) -> bool {
+        x.is_type(ty, heap)
+    }
+}
+
+#[starlark_module]
+pub fn round(builder: &mut GlobalsBuilder) {
+    /// Round a number to `ndigits` decimal places, returning a float.
+    /// `ndigits` may be negative, e.g. `round(1234, -2) == 1200.0`.
+    ///
+    /// Values exactly halfway between two candidates round to the even one, as in Python,
+    /// so `round(0.5) == 0.0` and `round(1.5) == 2.0`. As the number is scaled by a power
+    /// of ten using float arithmetic, a value such as `2.675` (which as a float is slightly
+    /// below `2.675`) may not round as its decimal representation suggests.
+    fn round(ref x: Num, ndigits @ 0: i32) -> f64 {
+        fn round_half_even(x: f64) -> f64 {
+            let r = x.round();
+            if (r - x).abs() == 0.5 {
+                2.0 * (x / 2.0).round()
+            } else {
+                r
+            }
+        }
+
+        // Scale by dividing or multiplying by an exact power of ten, never a fraction.
+        let x = x.as_float();
+        if ndigits >= 0 {
+            let scale = 10f64.powi(ndigits);
+            Ok(round_half_even(x * scale) / scale)
+        } else {
+            let scale = 10f64.powi(-ndigits);
+            Ok(round_half_even(x / scale) * scale)
+        }
     }
 }
 
@@ -325,4 +375,77 @@ assert_eq(dedupe([a,b,a]), [a,b])
 "#,
         );
     }
+
+    #[test]
+    fn test_abs() {
+        assert::pass(
+            r#"
+assert_eq(abs(3), 3)
+assert_eq(abs(-3), 3)
+assert_eq(abs(0), 0)
+assert_eq(abs(2147483647), 2147483647)
+assert_eq(abs(-2147483647), 2147483647)
+assert_eq(abs(-2.5), 2.5)
+assert_eq(abs(2.5), 2.5)
+"#,
+        );
+        assert::fail("abs(-2147483647 - 1)", "overflow");
+        assert::fail("abs('1')", "Type of parameter");
+    }
+
+    #[test]
+    fn test_round() {
+        assert::pass(
+            r#"
+assert_eq(round(1.4), 1.0)
+assert_eq(round(1.6), 2.0)
+assert_eq(round(-1.4), -1.0)
+assert_eq(round(-1.6), -2.0)
+assert_eq(round(3), 3.0)
+# Halfway values round to even.
+assert_eq(round(0.5), 0.0)
+assert_eq(round(1.5), 2.0)
+assert_eq(round(2.5), 2.0)
+assert_eq(round(-0.5), 0.0)
+assert_eq(round(-1.5), -2.0)
+assert_eq(round(-2.5), -2.0)
+assert_eq(round(0.125, 2), 0.12)
+assert_eq(round(0.375, 2), 0.38)
+assert_eq(round(-1.25, 1), -1.2)
+assert_eq(round(1234, -2), 1200.0)
+assert_eq(round(1250, -2), 1200.0)
+assert_eq(round(1350, -2), 1400.0)
+assert_eq(round(2.5, ndigits = 0), 2.0)
+"#,
+        );
+        assert::fail("round('1')", "Type of parameter");
+    }
+
+    #[test]
+    fn test_isinstance() {
+        assert::pass(
+            r#"
+assert_eq(isinstance(1, int.type), True)
+assert_eq(isinstance(1, "int"), True)
+assert_eq(isinstance(True, int.type), False)
+assert_eq(isinstance(True, bool.type), True)
+assert_eq(isinstance("x", str.type), True)
+assert_eq(isinstance("x", "string"), True)
+assert_eq(isinstance([1], list.type), True)
+assert_eq(isinstance([1], dict.type), False)
+assert_eq(isinstance({1: 2}, dict.type), True)
+assert_eq(isinstance(None, None), True)
+assert_eq(isinstance(1, [str.type, int.type]), True)
+assert_eq(isinstance([1, 2], [int.type]), True)
+assert_eq(isinstance([1, "x"], [int.type]), False)
+colors = enum("red", "green")
+assert_eq(isinstance(colors("red"), colors.type), True)
+assert_eq(isinstance(colors("red"), "enum"), True)
+assert_eq(isinstance(1, colors.type), False)
+assert_eq(type(1) == int.type, True)
+assert_eq(type([]) == list.type, True)
+"#,
+        );
+        assert::fail("isinstance(1, 2)", "not a valid type annotation");
+    }
 }
@@ -71,10 +71,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
         let mut s = String::new();
         for x in args {
             s.push(' ');
-            match x.unpack_str() {
-                Some(x) => s.push_str(x),
-                None => x.collect_repr(&mut s),
-            }
+            x.collect_str(&mut s);
         }
         Err(anyhow!("fail:{}", s))
     }
@@ -99,12 +96,12 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     fn any(ref x: Value) -> bool {
         x.with_iterator(heap, |it| {
             for i in it {
-                if i.to_bool() {
-                    return true;
+                if i.try_to_bool()? {
+                    return Ok(true);
                 }
             }
-            false
-        })
+            Ok(false)
+        })?
     }
 
     /// [all](
@@ -130,12 +127,12 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     fn all(ref x: Value) -> bool {
         x.with_iterator(heap, |it| {
             for i in it {
-                if !i.to_bool() {
-                    return false;
+                if !i.try_to_bool()? {
+                    return Ok(false);
                 }
             }
-            true
-        })
+            Ok(true)
+        })?
     }
 
     /// [bool](
@@ -168,7 +165,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     fn bool(ref x: Option<Value>) -> bool {
         match x {
             None => Ok(false),
-            Some(x) => Ok(x.to_bool()),
+            Some(x) => x.try_to_bool(),
         }
     }
 
@@ -859,6 +856,9 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// repr("x\"y😿 \\'")      == "\"x\\\"y😿 \\\\\\'\""
     /// "#);
     /// ```
+    ///
+    /// Non-ASCII characters are escaped if the evaluator was configured with
+    /// [`set_repr_ascii`](crate::eval::Evaluator::set_repr_ascii).
     #[starlark(speculative_exec_safe)]
     fn repr(ref a: Value) -> Value<'v> {
         let mut s = eval.string_pool.alloc();
@@ -929,7 +929,10 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
 
         let mut compare_ok = Ok(());
 
-        let reverse = reverse.map_or(false, |x| x.to_bool());
+        let reverse = match reverse {
+            None => false,
+            Some(x) => x.try_to_bool()?,
+        };
         it.sort_by(|x: &(Value, Value), y: &(Value, Value)| {
             let ord_or_err = if reverse {
                 x.1.compare(y.1).map(Ordering::reverse)
@@ -973,7 +976,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
             Ok(a)
         } else {
             let mut s = eval.string_pool.alloc();
-            a.collect_repr(&mut s);
+            a.collect_str(&mut s);
             let r = eval.heap().alloc_str(&s);
             eval.string_pool.release(s);
             Ok(r)
@@ -1007,6 +1010,9 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// https://github.com/google/skylark/blob/a0e5de7e63b47e716cca7226662a4c95d47bf873/doc/spec.md#type
     /// ): returns a string describing the type of its operand.
     ///
+    /// Types are just strings. The constructor functions have a `type` attribute
+    /// holding the string for their type, so `type(x) == int.type` tests for an int.
+    ///
     /// ```
     /// # starlark::assert::all_true(r#"
     /// type(None)              == "NoneType"
@@ -1014,11 +1020,13 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// type(1)                 == "int"
     /// type(())                == "tuple"
     /// type("hello")           == "string"
+    /// type("hello")           == str.type
+    /// type([])                == list.type
     /// # "#);
     /// ```
     #[starlark(speculative_exec_safe)]
     fn r#type(ref a: Value) -> Value<'v> {
-        Ok(a.get_type_value().unpack().to_value())
+        Ok(a.get_type_value_dynamic(heap))
     }
 
     /// [zip](
@@ -1037,25 +1045,31 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// zip()                           == []
     /// zip(range(5))                   == [(0,), (1,), (2,), (3,), (4,)]
     /// zip(range(5), "abc".elems())    == [(0, "a"), (1, "b"), (2, "c")]
+    /// zip([1, 2], [3], [4, 5, 6])     == [(1, 3, 4)]
     /// # "#);
     /// ```
     #[starlark(speculative_exec_safe)]
     fn zip(args: Vec<Value>) -> Value<'v> {
+        if args.is_empty() {
+            return Ok(heap.alloc_list(&[]));
+        }
+        // Advance all the iterators in lockstep, so we stop at the shortest input
+        // and allocate each result tuple exactly once.
+        let mut its = args
+            .iter()
+            .map(|x| x.iterate(heap))
+            .collect::<anyhow::Result<Vec<_>>>()?;
         let mut v = Vec::new();
-        let mut first = true;
-        for arg in args {
-            let mut idx = 0;
-            for e in arg.iterate(heap)? {
-                if first {
-                    v.push(heap.alloc((e,)));
-                    idx += 1;
-                } else if idx < v.len() {
-                    v[idx] = v[idx].add(heap.alloc((e,)), heap)?;
-                    idx += 1;
+        let mut row = Vec::with_capacity(its.len());
+        'outer: loop {
+            row.clear();
+            for it in &mut its {
+                match it.next() {
+                    Some(e) => row.push(e),
+                    None => break 'outer,
                 }
             }
-            v.truncate(idx);
-            first = false;
+            v.push(heap.alloc_tuple(&row));
         }
         Ok(heap.alloc_list(&v))
     }
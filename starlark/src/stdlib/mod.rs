@@ -69,8 +69,14 @@ pub enum LibraryExtension {
     Breakpoint,
     /// Add a function `json()` which will generate JSON for a module.
     Json,
-    /// Add a function `abs()` which will take the absolute value of an int.
+    /// Add a function `abs()` which will take the absolute value of an int or float.
     Abs,
+    /// Add a function `round(x, ndigits=0)` which will round a number to a float,
+    /// with halfway values rounding to even.
+    Round,
+    /// Add a function `isinstance(x, ty)` which checks if `x` matches the type `ty`,
+    /// written as in a type annotation, e.g. `isinstance(1, int.type)`.
+    IsInstance,
     // Make sure if you add anything new, you add it to `all` below.
 }
 
@@ -80,7 +86,7 @@ impl LibraryExtension {
         use LibraryExtension::*;
         &[
             StructType, RecordType, EnumType, Map, Filter, Partial, Dedupe, Debug, Print,
-            Breakpoint, Json, Abs,
+            Breakpoint, Json, Abs, Round, IsInstance,
         ]
     }
 
@@ -100,6 +106,8 @@ impl LibraryExtension {
             Breakpoint => breakpoint::global(builder),
             Json => extra::json(builder),
             Abs => extra::abs(builder),
+            Round => extra::round(builder),
+            IsInstance => extra::isinstance(builder),
         }
     }
 }
@@ -113,7 +121,11 @@ mod tests {
         self as starlark,
         assert::Assert,
         environment::{GlobalsBuilder, Methods, MethodsBuilder, MethodsStatic},
-        values::{none::NoneType, StarlarkValue, UnpackValue, Value, ValueLike},
+        values::{
+            docs::{DocItem, DocString, Function, Param, Return, Type},
+            none::{NoneOr, NoneType},
+            StarlarkValue, UnpackValue, Value, ValueLike,
+        },
     };
 
     #[test]
@@ -199,4 +211,311 @@ getattr(True2, "invert2")() == False2
 "#,
         );
     }
+
+    #[test]
+    fn test_value_attributes_allocate() {
+        #[derive(Debug, Display)]
+        #[display(fmt = "pair")]
+        struct Pair(i32, i32);
+        starlark_simple_value!(Pair);
+
+        impl<'v> StarlarkValue<'v> for Pair {
+            starlark_type!("pair");
+
+            fn get_methods(&self) -> Option<&'static Methods> {
+                static RES: MethodsStatic = MethodsStatic::new();
+                RES.methods(methods)
+            }
+        }
+
+        #[starlark_module]
+        fn methods(builder: &mut MethodsBuilder) {
+            #[starlark(attribute)]
+            fn items(this: &Pair) -> Vec<i32> {
+                Ok(vec![this.0, this.1])
+            }
+
+            #[starlark(attribute)]
+            fn swapped(this: &Pair) -> Value<'v> {
+                Ok(heap.alloc_list(&[Value::new_int(this.1), Value::new_int(this.0)]))
+            }
+        }
+
+        let mut a = Assert::new();
+        a.globals_add(|gb| gb.set("pair", Pair(1, 2)));
+        a.pass(
+            r#"
+xs = pair.items
+assert_eq(xs, [1, 2])
+xs.append(3)
+assert_eq(pair.items, [1, 2])
+assert_eq(pair.swapped, [2, 1])
+assert_eq(getattr(pair, "items"), [1, 2])
+"#,
+        );
+    }
+
+    #[test]
+    fn test_method_this_typed() {
+        #[derive(Debug, Display)]
+        #[display(fmt = "pair")]
+        struct Pair(i32, i32);
+        starlark_simple_value!(Pair);
+
+        #[derive(Debug, Display)]
+        #[display(fmt = "other")]
+        struct Other;
+        starlark_simple_value!(Other);
+
+        impl<'v> StarlarkValue<'v> for Pair {
+            starlark_type!("pair");
+
+            fn get_methods(&self) -> Option<&'static Methods> {
+                static RES: MethodsStatic = MethodsStatic::new();
+                RES.methods(methods)
+            }
+        }
+
+        // Wrongly shares the methods of `Pair`.
+        impl<'v> StarlarkValue<'v> for Other {
+            starlark_type!("other");
+
+            fn get_methods(&self) -> Option<&'static Methods> {
+                static RES: MethodsStatic = MethodsStatic::new();
+                RES.methods(methods)
+            }
+        }
+
+        #[starlark_module]
+        fn methods(builder: &mut MethodsBuilder) {
+            fn sum(this: &Pair) -> i32 {
+                Ok(this.0 + this.1)
+            }
+        }
+
+        let mut a = Assert::new();
+        a.globals_add(|gb| {
+            gb.set("pair", Pair(1, 2));
+            gb.set("other", Other);
+        });
+        a.eq("3", "pair.sum()");
+        a.fail(
+            "other.sum()",
+            "Type of parameter `this` doesn't match, expected `pair`, actual `other`",
+        );
+    }
+
+    #[test]
+    fn test_value_call() {
+        #[derive(Debug, Display)]
+        #[display(fmt = "adder")]
+        struct Adder(i32);
+        starlark_simple_value!(Adder);
+
+        impl<'v> StarlarkValue<'v> for Adder {
+            starlark_type!("adder");
+
+            fn get_methods(&self) -> Option<&'static Methods> {
+                static RES: MethodsStatic = MethodsStatic::new();
+                RES.methods(methods)
+            }
+        }
+
+        #[starlark_module]
+        fn globals(builder: &mut GlobalsBuilder) {
+            fn adder(x: i32) -> Adder {
+                Ok(Adder(x))
+            }
+        }
+
+        #[starlark_module]
+        fn methods(builder: &mut MethodsBuilder) {
+            #[starlark(dunder = "call")]
+            fn call(this: Value, y: i32) -> i32 {
+                Ok(this.downcast_ref::<Adder>().unwrap().0 + y)
+            }
+        }
+
+        let mut a = Assert::new();
+        a.globals_add(globals);
+        a.all_true(
+            r#"
+adder(1)(2) == 3
+adder(10)(y = 5) == 15
+"#,
+        );
+        a.fail("adder(1)()", "Missing parameter");
+        a.fail("1()", "not supported");
+    }
+
+    #[test]
+    fn test_optional_distinguishes_none() {
+        fn describe(x: Option<NoneOr<i32>>) -> String {
+            match x {
+                None => "omitted".to_owned(),
+                Some(NoneOr::None) => "None".to_owned(),
+                Some(NoneOr::Other(x)) => x.to_string(),
+            }
+        }
+
+        #[starlark_module]
+        fn globals(builder: &mut GlobalsBuilder) {
+            fn named(x: Option<NoneOr<i32>>) -> String {
+                Ok(describe(x))
+            }
+
+            fn positional(ref x: Option<NoneOr<i32>>) -> String {
+                Ok(describe(x))
+            }
+        }
+
+        let mut a = Assert::new();
+        a.globals_add(globals);
+        a.all_true(
+            r#"
+named() == "omitted"
+named(None) == "None"
+named(x = None) == "None"
+named(3) == "3"
+positional() == "omitted"
+positional(None) == "None"
+positional(3) == "3"
+"#,
+        );
+    }
+
+    #[test]
+    fn test_parameter_type_error() {
+        #[starlark_module]
+        fn globals(builder: &mut GlobalsBuilder) {
+            fn repeat(ref text: &str, ref count: i32) -> String {
+                Ok(text.repeat(count as usize))
+            }
+
+            fn pad(text: &str, _width: Option<i32>) -> String {
+                Ok(text.to_owned())
+            }
+        }
+
+        let mut a = Assert::new();
+        a.globals_add(globals);
+        a.eq("'aaa'", "repeat('a', 3)");
+        a.fail(
+            "repeat('a', 'b')",
+            "Type of parameter `count` doesn't match, expected `int`, actual `string`",
+        );
+        a.fail(
+            "repeat(1, 2)",
+            "Type of parameter `text` doesn't match, expected `str`, actual `int`",
+        );
+        // Named as in the signature, without the underscore.
+        a.fail(
+            "pad('a', width = 'x')",
+            "Type of parameter `width` doesn't match, expected `int`, actual `string`",
+        );
+    }
+
+    #[test]
+    fn test_return_value() {
+        #[starlark_module]
+        fn globals(builder: &mut GlobalsBuilder) {
+            // Returned as is, not allocated again.
+            fn same(x: Value<'v>) -> Value<'v> {
+                Ok(x)
+            }
+
+            fn fresh_list(x: Value<'v>) -> Value<'v> {
+                Ok(heap.alloc_list(&[x]))
+            }
+        }
+
+        let mut a = Assert::new();
+        a.globals_add(globals);
+        a.pass(
+            r#"
+xs = []
+same(xs).append(1)
+assert_eq(xs, [1])
+assert_eq(fresh_list(2), [2])
+"#,
+        );
+    }
+
+    #[test]
+    fn test_dependent_consts() {
+        #[starlark_module]
+        fn globals(builder: &mut GlobalsBuilder) {
+            const BASE: i32 = 40;
+            const ANSWER: i32 = BASE + 2;
+            const GREETING: &str = "hello";
+            const GREETING_LEN: i32 = GREETING.len() as i32 + ANSWER - BASE;
+        }
+
+        let mut a = Assert::new();
+        a.globals_add(globals);
+        a.eq("40", "BASE");
+        a.eq("42", "ANSWER");
+        a.eq("7", "GREETING_LEN");
+    }
+
+    #[test]
+    fn test_documentation() {
+        #[starlark_module]
+        fn globals(builder: &mut GlobalsBuilder) {
+            /// Repeat a string.
+            ///
+            /// Joins `n` copies of `s`, separated by `sep`.
+            fn repeat(ref s: &str, n @ 2: i32, sep: Option<&str>, x: Value<'v>) -> String {
+                let _ = x;
+                Ok(vec![s; n as usize].join(sep.unwrap_or("")))
+            }
+        }
+
+        let env = GlobalsBuilder::new().with(globals).build();
+        let typ = |t: &str| {
+            Some(Type {
+                raw_type: t.to_owned(),
+            })
+        };
+        let expected = DocItem::Function(Function {
+            docs: Some(DocString {
+                summary: "Repeat a string.".to_owned(),
+                details: Some("Joins `n` copies of `s`, separated by `sep`.".to_owned()),
+            }),
+            params: vec![
+                Param::Arg {
+                    name: "s".to_owned(),
+                    docs: None,
+                    typ: typ("str"),
+                    default_value: None,
+                },
+                Param::Arg {
+                    name: "n".to_owned(),
+                    docs: None,
+                    typ: typ("int"),
+                    default_value: Some("2".to_owned()),
+                },
+                Param::Arg {
+                    name: "sep".to_owned(),
+                    docs: None,
+                    typ: typ("str"),
+                    default_value: Some("None".to_owned()),
+                },
+                Param::Arg {
+                    name: "x".to_owned(),
+                    docs: None,
+                    typ: None,
+                    default_value: None,
+                },
+            ],
+            ret: Return {
+                docs: None,
+                typ: typ("str"),
+            },
+        });
+        assert_eq!(
+            Some(expected),
+            env.get("repeat").unwrap().get_ref().documentation()
+        );
+    }
 }
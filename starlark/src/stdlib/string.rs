@@ -17,8 +17,6 @@
 
 //! Methods for the `string` type.
 
-use std::cmp;
-
 use anyhow::anyhow;
 use gazebo::prelude::*;
 
@@ -28,11 +26,12 @@ use crate::{
     eval::Arguments,
     stdlib::util::convert_indices,
     values::{
+        list::List,
         none::NoneOr,
         string,
         string::{fast_string, interpolation},
         tuple::Tuple,
-        StringValue, UnpackValue, Value, ValueOf,
+        Heap, StringValue, UnpackValue, Value, ValueOf,
     },
 };
 
@@ -115,6 +114,40 @@ impl<'v> UnpackValue<'v> for StringOrTuple<'v> {
     }
 }
 
+/// Join strings with a separator, allocating the result once.
+/// The singleton case returns the element itself.
+fn join_values<'v>(sep: &str, xs: &[Value<'v>], heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+    let mut len = sep.len() * xs.len().saturating_sub(1);
+    for (i, x) in xs.iter().enumerate() {
+        match x.unpack_str() {
+            Some(s) => len += s.len(),
+            None => {
+                return Err(anyhow!(
+                    "join() requires strings, got element {} of type `{}`",
+                    i,
+                    x.get_type()
+                ));
+            }
+        }
+    }
+    match xs {
+        [] => Ok(Value::new_empty_string()),
+        [x] => Ok(*x),
+        _ => {
+            let mut r = String::with_capacity(len);
+            for (i, x) in xs.iter().enumerate() {
+                if i != 0 {
+                    r.push_str(sep);
+                }
+                // Checked above.
+                r.push_str(x.unpack_str().unwrap());
+            }
+            debug_assert_eq!(len, r.len());
+            Ok(heap.alloc(r))
+        }
+    }
+}
+
 #[starlark_module]
 pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// [string.elems](
@@ -141,6 +174,28 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         Ok(string::iterate_chars(this, heap))
     }
 
+    /// string.elem_ords: returns an iterable of the bytes of a string.
+    ///
+    /// `S.elem_ords()` returns an iterable value containing the
+    /// sequence of UTF-8 bytes of the string S, as ints.
+    /// Unlike `S.elems()`, a non-ASCII character yields one value for
+    /// each byte of its encoding.
+    ///
+    /// To materialize the entire sequence of bytes, apply `list(...)` to the
+    /// result.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// list("Hi".elem_ords()) == [72, 105]
+    /// list("é".elem_ords()) == [195, 169]
+    /// # "#);
+    /// ```
+    fn elem_ords(this: Value<'v>) -> Value<'v> {
+        Ok(string::iterate_bytes(this, heap))
+    }
+
     /// [string.capitalize](
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#string·capitalize
     /// ): returns a copy of string, with each first letter of a word in upper
@@ -624,45 +679,19 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// ", ".join(["one", "two", "three"]) == "one, two, three"
     /// "a".join("ctmrn".elems()) == "catamaran"
     /// # "#);
+    /// # starlark::assert::fail(r#"
+    /// ", ".join(["one", 2]) # error: element 1 of type `int`
+    /// # "#, "element 1 of type `int`");
     /// ```
     fn join(this: &str, ref to_join: Value) -> Value<'v> {
-        #[inline(always)]
-        fn as_str<'v>(x: Value<'v>) -> anyhow::Result<&'v str> {
-            <&str>::unpack_named_param(x, "to_join")
+        if let Some(xs) = List::from_value(to_join) {
+            join_values(this, xs.content(), heap)
+        } else if let Some(xs) = Tuple::from_value(to_join) {
+            join_values(this, xs.content(), heap)
+        } else {
+            let xs = to_join.with_iterator(heap, |it| it.collect::<Vec<_>>())?;
+            join_values(this, &xs, heap)
         }
-
-        to_join.with_iterator(heap, |it| {
-            match it.next() {
-                None => Ok(Value::new_empty_string()),
-                Some(x1) => {
-                    match it.next() {
-                        None => {
-                            as_str(x1)?;
-                            // If there is a singleton we can avoid reallocation
-                            Ok(x1)
-                        }
-                        Some(x2) => {
-                            let s1 = as_str(x1)?;
-                            let s2 = as_str(x2)?;
-                            // guess towards the upper bound, since we throw away over-allocations quickly
-                            // include a buffer (20 bytes)
-                            let n = it.size_hint().0 + 2;
-                            let guess =
-                                (cmp::max(s1.len(), s2.len()) * n) + (this.len() * (n - 1)) + 20;
-                            let mut r = String::with_capacity(guess);
-                            r.push_str(s1);
-                            r.push_str(this);
-                            r.push_str(s2);
-                            for x in it {
-                                r.push_str(this);
-                                r.push_str(as_str(x)?);
-                            }
-                            Ok(heap.alloc(r))
-                        }
-                    }
-                }
-            }
-        })?
     }
 
     /// [string.lower](
@@ -1230,6 +1259,23 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
 mod tests {
     use crate::assert;
 
+    #[test]
+    fn test_join() {
+        assert::eq(r#""-".join([])"#, r#""""#);
+        assert::eq(r#""-".join(())"#, r#""""#);
+        assert::eq(r#""-".join({})"#, r#""""#);
+        assert::eq(r#""-".join(["a"])"#, r#""a""#);
+        assert::eq(r#""-".join(("a", "b", "c"))"#, r#""a-b-c""#);
+        assert::eq(r#""-".join({"a": 1, "b": 2})"#, r#""a-b""#);
+        assert::eq(r#""".join(["a", "b"])"#, r#""ab""#);
+        assert::fail(
+            r#""-".join(["a", "b", 3])"#,
+            "join() requires strings, got element 2 of type `int`",
+        );
+        assert::fail(r#""-".join((None,))"#, "got element 0 of type `NoneType`");
+        assert::fail(r#""-".join(1)"#, "not supported");
+    }
+
     #[test]
     fn test_error_codes() {
         assert::fail(r#""bonbon".index("on", 2, 5)"#, "not found in");
@@ -1241,5 +1287,20 @@ mod tests {
     fn test_opaque_iterator() {
         assert::is_true("type('foo'.elems()) != type([])");
         assert::is_true("type('foo'.codepoints()) != type([])");
+        assert::is_true("type('foo'.elem_ords()) != type([])");
+    }
+
+    #[test]
+    fn test_non_ascii_iteration() {
+        assert::all_true(
+            r#"
+list("café".elems()) == ["c", "a", "f", "é"]
+list("café".codepoints()) == [99, 97, 102, 233]
+list("café".elem_ords()) == [99, 97, 102, 195, 169]
+list("😀".elems()) == ["😀"]
+list("😀".codepoints()) == [128512]
+list("😀".elem_ords()) == [240, 159, 152, 128]
+"#,
+        );
     }
 }
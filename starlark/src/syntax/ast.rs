@@ -24,6 +24,7 @@ use std::{
 
 use derivative::Derivative;
 use gazebo::prelude::*;
+use serde::Serialize;
 use static_assertions::assert_eq_size;
 
 use crate::codemap::{CodeMap, Pos, Span, Spanned};
@@ -109,7 +110,8 @@ pub(crate) trait ToAst: Sized {
 
 impl<T> ToAst for T {}
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(bound = "")]
 pub enum ArgumentP<P: AstPayload> {
     Positional(AstExprP<P>),
     Named(AstString, AstExprP<P>),
@@ -117,7 +119,8 @@ pub enum ArgumentP<P: AstPayload> {
     KwArgs(AstExprP<P>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(bound = "")]
 pub enum ParameterP<P: AstPayload> {
     Normal(AstAssignIdentP<P>, Option<Box<AstExprP<P>>>),
     WithDefaultValue(
@@ -130,14 +133,15 @@ pub enum ParameterP<P: AstPayload> {
     KwArgs(AstAssignIdentP<P>, Option<Box<AstExprP<P>>>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum AstLiteral {
     Int(AstInt),
     Float(AstFloat),
     String(AstString),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(bound = "")]
 pub enum ExprP<P: AstPayload> {
     Tuple(Vec<AstExprP<P>>),
     Dot(Box<AstExprP<P>>, AstString),
@@ -149,8 +153,12 @@ pub enum ExprP<P: AstPayload> {
         Option<Box<AstExprP<P>>>,
         Option<Box<AstExprP<P>>>,
     ),
-    Identifier(AstString, P::IdentPayload),
-    Lambda(Vec<AstParameterP<P>>, Box<AstExprP<P>>, P::DefPayload),
+    Identifier(AstString, #[serde(skip)] P::IdentPayload),
+    Lambda(
+        Vec<AstParameterP<P>>,
+        Box<AstExprP<P>>,
+        #[serde(skip)] P::DefPayload,
+    ),
     Literal(AstLiteral),
     Not(Box<AstExprP<P>>),
     Minus(Box<AstExprP<P>>),
@@ -169,7 +177,8 @@ pub enum ExprP<P: AstPayload> {
 }
 
 /// In some places e.g. AssignModify, the Tuple case is not allowed.
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(bound = "")]
 pub enum AssignP<P: AstPayload> {
     // We use Tuple for both Tuple and List,
     // as these have the same semantics in Starlark.
@@ -180,30 +189,34 @@ pub enum AssignP<P: AstPayload> {
 }
 
 /// Identifier in assign position.
-#[derive(Debug, Eq, PartialEq, Clone)]
-pub struct AssignIdentP<P: AstPayload>(pub String, pub P::IdentAssignPayload);
+#[derive(Debug, Eq, PartialEq, Clone, Serialize)]
+#[serde(bound = "")]
+pub struct AssignIdentP<P: AstPayload>(pub String, #[serde(skip)] pub P::IdentAssignPayload);
 
 /// `load` statement.
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(bound = "")]
 pub struct LoadP<P: AstPayload> {
     pub module: AstString,
     pub args: Vec<(AstAssignIdentP<P>, AstString)>,
     pub visibility: Visibility,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(bound = "")]
 pub struct ForClauseP<P: AstPayload> {
     pub var: AstAssignP<P>,
     pub over: AstExprP<P>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(bound = "")]
 pub enum ClauseP<P: AstPayload> {
     For(ForClauseP<P>),
     If(AstExprP<P>),
 }
 
-#[derive(Debug, Clone, Copy, Dupe, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Dupe, Eq, PartialEq, Serialize)]
 pub enum BinOp {
     Or,
     And,
@@ -228,7 +241,7 @@ pub enum BinOp {
     RightShift,
 }
 
-#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Serialize)]
 pub enum AssignOp {
     Add,         // +=
     Subtract,    // -=
@@ -243,13 +256,14 @@ pub enum AssignOp {
     RightShift,  // >>=
 }
 
-#[derive(Debug, Copy, Clone, Dupe, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Dupe, Eq, PartialEq, Serialize)]
 pub enum Visibility {
     Private,
     Public,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(bound = "")]
 pub enum StmtP<P: AstPayload> {
     Break,
     Continue,
@@ -261,16 +275,22 @@ pub enum StmtP<P: AstPayload> {
     Statements(Vec<AstStmtP<P>>),
     If(AstExprP<P>, Box<AstStmtP<P>>),
     IfElse(AstExprP<P>, Box<(AstStmtP<P>, AstStmtP<P>)>),
-    For(AstAssignP<P>, Box<(AstExprP<P>, AstStmtP<P>)>),
+    /// Order: collection, body, `else` block run when the loop is not stopped by `break`.
+    For(
+        AstAssignP<P>,
+        Box<(AstExprP<P>, AstStmtP<P>, Option<AstStmtP<P>>)>,
+    ),
     Def(
         AstAssignIdentP<P>,
         Vec<AstParameterP<P>>,
         Option<Box<AstExprP<P>>>,
         Box<AstStmtP<P>>,
-        P::DefPayload,
+        #[serde(skip)] P::DefPayload,
     ),
     // The Visibility of a Load is implicit from the Dialect, not written by a user
     Load(AstLoadP<P>),
+    /// Order: condition, message.
+    Assert(AstExprP<P>, Option<Box<AstExprP<P>>>),
 }
 
 impl<P: AstPayload> ArgumentP<P> {
@@ -552,6 +572,10 @@ impl Stmt {
             Stmt::Break => writeln!(f, "{}break", tab),
             Stmt::Continue => writeln!(f, "{}continue", tab),
             Stmt::Pass => writeln!(f, "{}pass", tab),
+            Stmt::Assert(cond, None) => writeln!(f, "{}assert {}", tab, cond.node),
+            Stmt::Assert(cond, Some(msg)) => {
+                writeln!(f, "{}assert {}, {}", tab, cond.node, msg.node)
+            }
             Stmt::Return(Some(e)) => writeln!(f, "{}return {}", tab, e.node),
             Stmt::Return(None) => writeln!(f, "{}return", tab),
             Stmt::Expression(e) => writeln!(f, "{}{}", tab, e.node),
@@ -573,9 +597,14 @@ impl Stmt {
                 writeln!(f, "{}else:", tab)?;
                 suite2.node.fmt_with_tab(f, tab + "  ")
             }
-            Stmt::For(bind, box (coll, suite)) => {
+            Stmt::For(bind, box (coll, suite, else_suite)) => {
                 writeln!(f, "{}for {} in {}:", tab, bind.node, coll.node)?;
-                suite.node.fmt_with_tab(f, tab + "  ")
+                suite.node.fmt_with_tab(f, tab.clone() + "  ")?;
+                if let Some(else_suite) = else_suite {
+                    writeln!(f, "{}else:", tab)?;
+                    else_suite.node.fmt_with_tab(f, tab + "  ")?;
+                }
+                Ok(())
             }
             Stmt::Def(name, params, return_type, suite, _payload) => {
                 write!(f, "{}def {}(", tab, name.node)?;
@@ -21,7 +21,7 @@ use thiserror::Error;
 use crate::{
     codemap::{CodeMap, Pos, Span, Spanned},
     errors::Diagnostic,
-    syntax::ast::Visibility,
+    syntax::ast::{AstStmt, Stmt, Visibility},
 };
 
 #[derive(Error, Debug)]
@@ -36,6 +36,10 @@ enum DialectError {
     KeywordOnlyArguments,
     #[error("type annotations are not allowed in this dialect")]
     Types,
+    #[error("augmented assignment is not allowed in this dialect")]
+    AugmentedAssignment,
+    #[error("`else` after `for` is not allowed in this dialect")]
+    ForElse,
 }
 
 /// Starlark language features to enable, e.g. [`Standard`](Dialect::Standard) to follow the Starlark standard.
@@ -47,6 +51,9 @@ pub struct Dialect {
     /// Are `lambda` expressions permitted.
     /// Enabled in both [`Standard`](Dialect::Standard) and [`Extended`](Dialect::Extended).
     pub enable_lambda: bool,
+    /// Are augmented assignments such as `x += 1` permitted.
+    /// Enabled in both [`Standard`](Dialect::Standard) and [`Extended`](Dialect::Extended).
+    pub enable_augmented_assignment: bool,
     /// Are `load` statements permitted.
     /// Enabled in both [`Standard`](Dialect::Standard) and [`Extended`](Dialect::Extended).
     pub enable_load: bool,
@@ -56,8 +63,9 @@ pub struct Dialect {
     /// Are expressions allowed in type positions as per [PEP 484](https://www.python.org/dev/peps/pep-0484/).
     /// Only enabled in [`Extended`](Dialect::Extended).
     pub enable_types: bool,
-    /// Are tabs permitted for indentation. If permitted, tabs are equivalent to 8 spaces.
-    /// Enabled in both [`Standard`](Dialect::Standard) and [`Extended`](Dialect::Extended).
+    /// Are tabs permitted for indentation. If permitted, tabs are equivalent to 8 spaces,
+    /// otherwise any tab outside a string literal is a parse error.
+    /// Only enabled in [`Extended`](Dialect::Extended), to match Bazel, which rejects tabs.
     pub enable_tabs: bool,
     /// Do `load()` statements reexport their definition.
     /// Enabled in both [`Standard`](Dialect::Standard) and [`Extended`](Dialect::Extended),
@@ -66,6 +74,21 @@ pub struct Dialect {
     /// Are `for`, `if` and other statements allowed at the top level.
     /// Only enabled in [`Extended`](Dialect::Extended).
     pub enable_top_level_stmt: bool,
+    /// Can a top-level variable, including one bound by `load`, be assigned more than once.
+    /// If not, a second binding is a parse error pointing to the first one,
+    /// apart from reexporting a loaded name with `x = x`.
+    /// Enabled in both [`Standard`](Dialect::Standard) and [`Extended`](Dialect::Extended),
+    /// disable it to match Bazel and the Go implementation.
+    pub enable_top_level_reassignment: bool,
+    /// Can a `for` loop have an `else` block, run when the loop finishes without `break`,
+    /// as in Python.
+    /// Only enabled in [`Extended`](Dialect::Extended).
+    pub enable_for_else: bool,
+    /// Is `assert cond, "message"` a statement, checked only when
+    /// [`Evaluator::set_assertions_enabled`](crate::eval::Evaluator::set_assertions_enabled)
+    /// is set. If enabled, `assert` is a keyword, so it can't be used as a variable name.
+    /// Disabled in both [`Standard`](Dialect::Standard) and [`Extended`](Dialect::Extended).
+    pub enable_assert_statement: bool,
 }
 
 // These are morally enumerations, so give them enumeration-like names
@@ -76,24 +99,32 @@ impl Dialect {
     pub const Standard: Self = Self {
         enable_def: true,
         enable_lambda: true,
+        enable_augmented_assignment: true,
         enable_load: true,
         enable_keyword_only_arguments: false,
         enable_types: false,
-        enable_tabs: true,
+        enable_tabs: false,
         enable_load_reexport: true, // But they plan to change it
         enable_top_level_stmt: false,
+        enable_top_level_reassignment: true,
+        enable_for_else: false,
+        enable_assert_statement: false,
     };
 
     /// A superset of [`Standard`](Dialect::Standard), including extra features (types, top-level statements etc).
     pub const Extended: Self = Self {
         enable_def: true,
         enable_lambda: true,
+        enable_augmented_assignment: true,
         enable_load: true,
         enable_keyword_only_arguments: true,
         enable_types: true,
         enable_tabs: true,
         enable_load_reexport: true,
         enable_top_level_stmt: true,
+        enable_top_level_reassignment: true,
+        enable_for_else: true,
+        enable_assert_statement: false,
     };
 }
 
@@ -126,6 +157,30 @@ impl Dialect {
         }
     }
 
+    pub(crate) fn check_augmented_assignment(
+        &self,
+        codemap: &CodeMap,
+        x: AstStmt,
+    ) -> anyhow::Result<AstStmt> {
+        if self.enable_augmented_assignment || !matches!(x.node, Stmt::AssignModify(..)) {
+            Ok(x)
+        } else {
+            err(codemap, x.span, DialectError::AugmentedAssignment)
+        }
+    }
+
+    pub(crate) fn check_for_else<T>(
+        &self,
+        codemap: &CodeMap,
+        x: Spanned<T>,
+    ) -> anyhow::Result<Spanned<T>> {
+        if self.enable_for_else {
+            Ok(x)
+        } else {
+            err(codemap, x.span, DialectError::ForElse)
+        }
+    }
+
     pub(crate) fn check_load<T>(
         &self,
         codemap: &CodeMap,
@@ -17,7 +17,14 @@
 
 use gazebo::prelude::*;
 
-use crate::{assert, assert::Assert, syntax::ast::Stmt};
+use crate::{
+    assert,
+    assert::Assert,
+    syntax::{
+        ast::{AstStmt, Stmt},
+        AstModule, Dialect, TextEdit,
+    },
+};
 
 #[test]
 fn test_empty() {
@@ -112,6 +119,65 @@ fn test_top_level_statements() {
     assert_eq!(assert::parse("pass"), "pass\n");
 }
 
+#[test]
+fn test_top_level_reassignment() {
+    let mut a = Assert::new();
+    a.dialect_set(|x| x.enable_top_level_reassignment = false);
+    let err = a.parse_fail("x = 1\n!x! = 2");
+    assert!(
+        err.to_string()
+            .contains("`x` is already assigned at assert.bzl:1:1"),
+        "{}",
+        err
+    );
+    a.parse_fail("def f(): pass\n!f! += 1");
+    a.parse_fail("x = 1\nif True:\n  for !x! in []:\n    pass");
+    a.pass("x = 1\ndef f():\n  x = 2\n  x += 1\n  return x\nassert_eq(f(), 3)");
+
+    // The default dialects allow reassignment.
+    a.dialect_set(|x| x.enable_top_level_reassignment = true);
+    a.pass("x = 1\nx = 2\nassert_eq(x, 2)");
+}
+
+#[test]
+fn test_top_level_reassignment_load() {
+    let mut a = Assert::new();
+    a.dialect_set(|x| x.enable_top_level_reassignment = false);
+    let err = a.parse_fail("load('a.star', 'x')\n!x! = 1");
+    assert!(
+        err.to_string().contains("`x` is loaded at assert.bzl:1:"),
+        "{}",
+        err
+    );
+    a.parse_fail("load('a.star', 'x')\nload('b.star', !x!='y')");
+    // Reexporting a loaded name is allowed, but only once.
+    a.parse_ast("load('a.star', 'x')\nx = x");
+    a.parse_fail("load('a.star', 'x')\nx = x\n!x! = x");
+}
+
+#[test]
+fn test_for_else() {
+    assert_eq!(
+        assert::parse("for x in []:\n  pass\nelse:\n  x = 1\n"),
+        "for x in []:\n  pass\nelse:\n  x = 1\n"
+    );
+    let mut a = Assert::new();
+    a.dialect_set(|x| x.enable_for_else = false);
+    a.parse_fail("for x in []: pass\nelse: !x = 1!\n");
+}
+
+#[test]
+fn test_assert_statement() {
+    let mut a = Assert::new();
+    a.dialect_set(|x| x.enable_assert_statement = true);
+    assert_eq!(
+        a.parse("assert x\nassert x == 1, 'message'\n"),
+        "assert x\nassert (x == 1), \"message\"\n"
+    );
+    // Without the dialect option, `assert` is an ordinary identifier.
+    assert_eq!(assert::parse("assert.eq(x, 1)\n"), "assert.eq(x, 1)\n");
+}
+
 #[test]
 fn test_top_level_def_with_docstring() {
     assert_eq!(
@@ -290,12 +356,215 @@ fn test_op_associativity() {
     assert_eq!(assert::parse("1 + 2 + 3"), "((1 + 2) + 3)\n");
     assert_eq!(assert::parse("1 * 2 * 3"), "((1 * 2) * 3)\n");
     // Comparisons are not associative
-    // TODO - create a better error message for this case
     assert::fail("0 <= 1 < 2", "Parse error");
 }
 
+#[test]
+fn test_chained_comparison() {
+    // Unlike Python, Starlark does not allow chaining comparisons.
+    let chained = "comparisons cannot be chained";
+    assert::fail("0 <= 1 < 2", chained);
+    assert::fail("x = 1\n1 < x < 10", chained);
+    assert::fail("1 < 2 < 3 < 4", chained);
+    assert::fail("1 == 1 in [True]", chained);
+    assert::fail("1 < 2 not in [3]", chained);
+    assert::fail("def f(x):\n    return 1 < x < 10", chained);
+    // Other parse errors are reported as usual.
+    let err = assert::fail("x = < 1", "Parse error");
+    assert!(!err.to_string().contains(chained), "{}", err);
+    // The equivalent explicit forms are fine.
+    assert::is_true("0 <= 1 and 1 < 2");
+    assert::is_true("(1 < 2) < 3");
+}
+
 #[test]
 fn test_bad_assignment() {
     assert::parse_fail("[!x or y!] = 1");
     assert::parse_fail("![x]! += 1");
 }
+
+#[test]
+fn test_augmented_assignment_dialect() {
+    assert_eq!(assert::parse("x += 1"), "x += 1\n");
+    let mut a = Assert::new();
+    a.dialect_set(|x| x.enable_augmented_assignment = false);
+    a.parse_fail("x = 1\n!x += 1!\n");
+    a.parse_fail("def f(x):\n  !x *= 2!\n  return x");
+    assert_eq!(a.parse("x = 1"), "x = 1\n");
+    a.fail("x = 1\nx -= 1", "not allowed in this dialect");
+}
+
+#[test]
+fn test_parse_recoverable() {
+    let recover = |program: &str| {
+        let (ast, errors) =
+            AstModule::parse_recoverable("x.star", program.to_owned(), &Dialect::Standard);
+        (ast.map(|x| x.statement.to_string()), errors.len())
+    };
+    assert_eq!(
+        recover("x = 1\ny = 2\n"),
+        (Some("x = 1\ny = 2\n".to_owned()), 0)
+    );
+    assert_eq!(
+        recover("x = 1\ny = = 2\nz = 3\n"),
+        (Some("x = 1\nz = 3\n".to_owned()), 1)
+    );
+    // A bad statement inside a `def` drops the whole `def`.
+    assert_eq!(
+        recover("x = 1\ndef f():\n    return = 1\ny = [\n    2,\n]\nz = * 3\n"),
+        (Some("x = 1\ny = [2]\n".to_owned()), 2)
+    );
+    // Errors which are not syntax errors are recovered from too.
+    assert_eq!(recover("x = 1\nbreak\n"), (Some("x = 1\n".to_owned()), 1));
+}
+
+#[test]
+fn test_to_json_ast() {
+    let program = "x = \"é\\n\"\ny = f(1, 2.5)\n";
+    let ast = AstModule::parse("json.star", program.to_owned(), &Dialect::Standard).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&ast.to_json_ast()).unwrap();
+    assert_eq!(1, json["version"]);
+    assert_eq!("json.star", json["filename"]);
+
+    let stmts = &json["statement"]["node"]["Statements"];
+    let x = &stmts[0]["node"]["Assign"];
+    assert_eq!("é\n", x[1]["node"]["Literal"]["String"]["node"]);
+
+    let y = &stmts[1]["node"]["Assign"];
+    let y_span = &y[0]["span"];
+    assert_eq!(program.find('y').unwrap(), y_span["begin"]);
+    assert_eq!(1, y_span["begin_line"]);
+    assert_eq!(0, y_span["begin_column"]);
+    let call = &y[1]["node"]["Call"];
+    assert_eq!("f", call[0]["node"]["Identifier"][0]["node"]);
+    assert_eq!(
+        1,
+        call[1][0]["node"]["Positional"]["node"]["Literal"]["Int"]["node"]
+    );
+    assert_eq!(
+        2.5,
+        call[1][1]["node"]["Positional"]["node"]["Literal"]["Float"]["node"]
+    );
+    // Columns are counted in characters, offsets in bytes.
+    let literal_span = &x[1]["span"];
+    assert_eq!(4, literal_span["begin_column"]);
+    assert_eq!(9, literal_span["end_column"]);
+    assert_eq!(program.find('\n').unwrap(), literal_span["end"]);
+    // Literals keep their source text.
+    assert_eq!("\"é\\n\"", x[1]["text"]);
+    assert_eq!("2.5", call[1][1]["node"]["Positional"]["text"]);
+    assert!(y[0].get("text").is_none());
+}
+
+#[test]
+fn test_reparse() {
+    fn def_bodies(ast: &AstModule) -> Vec<*const AstStmt> {
+        match &ast.statement.node {
+            Stmt::Statements(stmts) => stmts.map(|s| match &s.node {
+                Stmt::Def(_, _, _, body, _) => &**body as *const AstStmt,
+                _ => panic!("not a def"),
+            }),
+            _ => panic!("not statements"),
+        }
+    }
+
+    // Reparse and check the result is the same as parsing the new text, spans included.
+    fn check_reparse(program: &str, old: &str, new: &str) {
+        let pos = program.find(old).unwrap();
+        let edit = TextEdit {
+            range: pos..pos + old.len(),
+            text: new.to_owned(),
+        };
+        let ast = AstModule::parse("reparse.star", program.to_owned(), &Dialect::Standard);
+        let reparsed = ast.unwrap().reparse(&edit, &Dialect::Standard).unwrap();
+        let mut source = program.to_owned();
+        source.replace_range(edit.range, new);
+        let expected = AstModule::parse("reparse.star", source, &Dialect::Standard).unwrap();
+        assert_eq!(expected.codemap.source(), reparsed.codemap.source());
+        assert_eq!(
+            format!("{:?}", expected.statement),
+            format!("{:?}", reparsed.statement)
+        );
+    }
+
+    let program = "def f():\n    return 1\n\ndef g():\n    return 2\n\ndef h():\n    return 3\n";
+
+    // Statements before and after the edited one are reused.
+    let ast = AstModule::parse("reparse.star", program.to_owned(), &Dialect::Standard).unwrap();
+    let old = def_bodies(&ast);
+    let pos = program.find('2').unwrap();
+    let edit = TextEdit {
+        range: pos..pos + 1,
+        text: "20 + x".to_owned(),
+    };
+    let new = def_bodies(&ast.reparse(&edit, &Dialect::Standard).unwrap());
+    assert_eq!(old[0], new[0]);
+    assert_eq!(old[2], new[2]);
+
+    check_reparse(program, "2", "20 + x");
+    check_reparse(program, "return 3", "pass");
+    // Add a line to the body of the statement before the edit.
+    check_reparse(program, "\n\ndef g", "\n    y = 1\n\ndef g");
+    // Split a statement in two, and join two statements.
+    check_reparse(program, "return 2\n", "return 2\nx = 1\n");
+    check_reparse(program, "\n\ndef h():\n    return 3", " + 3");
+
+    // Edits which need more than the edited statement fall back to a full parse.
+    let pos = program.find("def g").unwrap();
+    let edit = TextEdit {
+        range: pos..pos,
+        text: "x = \"\"\"\n".to_owned(),
+    };
+    let ast = AstModule::parse("reparse.star", program.to_owned(), &Dialect::Standard).unwrap();
+    assert!(ast.reparse(&edit, &Dialect::Standard).is_err());
+}
+
+#[test]
+fn test_load_statements() {
+    let program = "load('a.star', 'x', y = 'z')\nload('b.star', 'b')\nload('a.star', w = 'x')\n";
+    let ast = AstModule::parse("loads.star", program.to_owned(), &Dialect::Standard).unwrap();
+    let owned = |xs: &[(&str, &str)]| xs.map(|(a, b)| ((*a).to_owned(), (*b).to_owned()));
+    let loads = ast.load_statements();
+    assert_eq!(
+        loads.map(|x| (x.module.as_str(), x.symbols.clone())),
+        vec![
+            ("a.star", owned(&[("x", "x"), ("y", "z")])),
+            ("b.star", owned(&[("b", "b")])),
+            ("a.star", owned(&[("w", "x")])),
+        ]
+    );
+    assert_eq!(
+        ast.file_span(loads[1].span).to_string(),
+        "loads.star:2:1-20"
+    );
+    assert_eq!(ast.loads(), vec!["a.star", "b.star", "a.star"]);
+}
+
+#[test]
+fn test_top_level_spans() {
+    let program = r#"
+load("a.star", "b")
+def f(x):
+    return x
+def g():
+    pass
+y, z = f(1), g()
+"#;
+    let ast = AstModule::parse("outline.star", program.to_owned(), &Dialect::Standard).unwrap();
+    assert_eq!(
+        ast.top_level_spans()
+            .map(|(span, description)| format!("{} {}", ast.file_span(span), description))
+            .collect::<Vec<_>>(),
+        vec![
+            "outline.star:2:1-20 load",
+            // The body of a `def` includes its final newline.
+            "outline.star:3:1-5:1 def f",
+            "outline.star:5:1-7:1 def g",
+            "outline.star:7:1-17 assignment to y, z",
+        ]
+    );
+    assert_eq!(
+        ast.file_span(ast.span()).to_string(),
+        "outline.star:1:1-8:1"
+    );
+}
@@ -63,6 +63,7 @@ pub(crate) struct Lexer<'a> {
     lexer: logos::Lexer<'a, Token>,
     done: bool,
     dialect_allow_tabs: bool,
+    dialect_assert_statement: bool,
 }
 
 impl<'a> Lexer<'a> {
@@ -77,6 +78,7 @@ impl<'a> Lexer<'a> {
             parens: 0,
             done: false,
             dialect_allow_tabs: dialect.enable_tabs,
+            dialect_assert_statement: dialect.enable_assert_statement,
         };
         if let Err(e) = lexer2.calculate_indent() {
             lexer2.buffer.push_back(Err(e));
@@ -480,6 +482,13 @@ impl<'a> Lexer<'a> {
                             self.parens -= 1;
                             self.wrap(token)
                         }
+                        // Only a keyword in dialects with `assert` statements,
+                        // elsewhere it is commonly the name of a testing module.
+                        Token::Identifier(ref x)
+                            if self.dialect_assert_statement && x == "assert" =>
+                        {
+                            self.wrap(Token::Assert)
+                        }
                         _ => self.wrap(token),
                     },
                 }
@@ -570,6 +579,8 @@ pub enum Token {
     Return,
     #[token("lambda")]
     Lambda,
+    // Produced from an identifier, see `Dialect::enable_assert_statement`
+    Assert,
     // Symbols
     #[token(",")]
     Comma,
@@ -659,7 +670,109 @@ pub enum Token {
     ClosingRound,
 }
 
+/// The kind of a token returned by [`lex`].
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// A keyword, e.g. `def` or `lambda`.
+    Keyword,
+    /// A name, e.g. of a variable or function.
+    Identifier,
+    /// A string literal, including its quotes.
+    String,
+    /// An int or float literal.
+    Number,
+    /// An operator or punctuation, e.g. `+`, `=` or `(`.
+    Symbol,
+    /// A comment, from the `#` to the end of the line.
+    Comment,
+}
+
+/// Split Starlark source into tokens, e.g. for syntax highlighting.
+/// Tokens are returned in the order they appear, with their spans in `text`.
+/// Comments are included, but whitespace, newlines and indentation are not.
+///
+/// Unlike [`parse`](crate::syntax::AstModule::parse) this never fails: text which
+/// can't be lexed, such as an unfinished string literal, is skipped.
+///
+/// ```
+/// use starlark::syntax::{lex, TokenKind};
+///
+/// let kinds: Vec<TokenKind> = lex("x = 1 # one").into_iter().map(|(_, kind)| kind).collect();
+/// assert_eq!(
+///     kinds,
+///     vec![TokenKind::Identifier, TokenKind::Symbol, TokenKind::Number, TokenKind::Comment]
+/// );
+/// ```
+pub fn lex(text: &str) -> Vec<(Span, TokenKind)> {
+    let codemap = CodeMap::new(String::new(), text.to_owned());
+    let mut tokens = Vec::new();
+    // The end of the last token, comments can only occur between tokens.
+    let mut end_of_last = 0;
+    // The lexer carries on after errors, so skip them.
+    for (begin, token, end) in Lexer::new(text, &Dialect::Extended, codemap).flatten() {
+        if let Some(kind) = token.kind() {
+            lex_comments(text, end_of_last, begin, &mut tokens);
+            tokens.push((span(begin, end), kind));
+            end_of_last = end;
+        }
+    }
+    lex_comments(text, end_of_last, text.len(), &mut tokens);
+    tokens
+}
+
+/// Add the comments in `text[start..end]`, which contains no tokens.
+fn lex_comments(text: &str, start: usize, end: usize, tokens: &mut Vec<(Span, TokenKind)>) {
+    let mut pos = start;
+    while let Some(i) = text.get(pos..end).and_then(|x| x.find('#')) {
+        let comment_start = pos + i;
+        let comment_end = text[comment_start..end]
+            .find('\n')
+            .map_or(end, |i| comment_start + i);
+        tokens.push((span(comment_start, comment_end), TokenKind::Comment));
+        pos = comment_end;
+    }
+}
+
+fn span(begin: usize, end: usize) -> Span {
+    Span::new(Pos::new(begin as u32), Pos::new(end as u32))
+}
+
 impl Token {
+    /// The kind of the token for [`lex`], or [`None`] for layout tokens
+    /// (newlines and indentation) and tokens only used internally by the lexer.
+    fn kind(&self) -> Option<TokenKind> {
+        match self {
+            Token::Error
+            | Token::Tabs
+            | Token::Indent
+            | Token::Dedent
+            | Token::Newline
+            | Token::RawSingleQuote
+            | Token::RawDoubleQuote
+            | Token::Reserved => None,
+            Token::Identifier(_) => Some(TokenKind::Identifier),
+            Token::Int(_) | Token::Float(_) => Some(TokenKind::Number),
+            Token::String(_) => Some(TokenKind::String),
+            Token::And
+            | Token::Else
+            | Token::Load
+            | Token::Break
+            | Token::For
+            | Token::Not
+            | Token::Continue
+            | Token::If
+            | Token::Or
+            | Token::Def
+            | Token::In
+            | Token::Pass
+            | Token::Elif
+            | Token::Return
+            | Token::Lambda
+            | Token::Assert => Some(TokenKind::Keyword),
+            _ => Some(TokenKind::Symbol),
+        }
+    }
+
     /// Used for testing
     pub(crate) fn unlex(&self) -> String {
         match self {
@@ -710,6 +823,7 @@ impl Display for Token {
             Token::Elif => write!(f, "keyword 'elif'"),
             Token::Return => write!(f, "keyword 'return'"),
             Token::Lambda => write!(f, "keyword 'lambda'"),
+            Token::Assert => write!(f, "keyword 'assert'"),
             Token::Comma => write!(f, "symbol ','"),
             Token::Semicolon => write!(f, "symbol ';'"),
             Token::Colon => write!(f, "symbol ':'"),
@@ -15,7 +15,10 @@
  * limitations under the License.
  */
 
-use crate::{assert, syntax::lexer::Token::*};
+use crate::{
+    assert,
+    syntax::{lex, lexer::Token::*, TokenKind},
+};
 
 #[test]
 fn test_int_lit() {
@@ -297,3 +300,43 @@ fn test_float_lit() {
         "0 0.123 3.14 200 10000 \n"
     );
 }
+
+#[test]
+fn test_lex_token_kinds() {
+    let text = "def f(x):\n    # Say hi\n    return x + 'hi' # greeting\n";
+    let tokens = lex(text);
+    let kinds = tokens.iter().map(|(_, kind)| *kind).collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            TokenKind::Keyword,
+            TokenKind::Identifier,
+            TokenKind::Symbol,
+            TokenKind::Identifier,
+            TokenKind::Symbol,
+            TokenKind::Symbol,
+            TokenKind::Comment,
+            TokenKind::Keyword,
+            TokenKind::Identifier,
+            TokenKind::Symbol,
+            TokenKind::String,
+            TokenKind::Comment,
+        ],
+        kinds
+    );
+    let text_of = |kind| {
+        tokens
+            .iter()
+            .filter(|(_, k)| *k == kind)
+            .map(|(span, _)| &text[span.begin().get() as usize..span.end().get() as usize])
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec!["'hi'"], text_of(TokenKind::String));
+    assert_eq!(vec!["# Say hi", "# greeting"], text_of(TokenKind::Comment));
+
+    // A `#` in a string does not start a comment, and errors are skipped.
+    let kinds = lex("'a # b' $ 1")
+        .into_iter()
+        .map(|(_, kind)| kind)
+        .collect::<Vec<_>>();
+    assert_eq!(vec![TokenKind::String, TokenKind::Number], kinds);
+}
@@ -19,6 +19,9 @@
 
 pub use ast::AstModule;
 pub use dialect::Dialect;
+pub use lexer::{lex, TokenKind};
+pub use parser::LoadStatement;
+pub use reparse::TextEdit;
 
 #[cfg(test)]
 mod grammar_tests;
@@ -32,6 +35,7 @@ pub(crate) mod cursors;
 mod dialect;
 pub(crate) mod lexer;
 pub(crate) mod payload_map;
+mod reparse;
 pub(crate) mod validate;
 
 #[allow(clippy::all)]
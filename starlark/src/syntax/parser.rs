@@ -15,7 +15,7 @@
  * limitations under the License.
  */
 
-use std::{fs, path::Path};
+use std::{fs, ops::Range, path::Path};
 
 use anyhow::anyhow;
 use gazebo::prelude::*;
@@ -46,6 +46,23 @@ fn one_of(expected: &[String]) -> String {
     result
 }
 
+/// Comparisons are not associative, so a comparison operator is unexpected right after
+/// another comparison, e.g. in `0 <= x < 10`. Any other complete operand of `and`
+/// could be the left hand side of a comparison.
+fn is_chained_comparison(token: &Token, expected: &[String]) -> bool {
+    matches!(
+        token,
+        Token::EqualEqual
+            | Token::BangEqual
+            | Token::LessThan
+            | Token::GreaterThan
+            | Token::LessEqual
+            | Token::GreaterEqual
+            | Token::In
+            | Token::Not
+    ) && expected.iter().any(|e| e == "\"and\"")
+}
+
 /// Convert the error to a codemap diagnostic.
 ///
 /// To build this diagnostic, the method needs the file span corresponding
@@ -61,6 +78,14 @@ pub(crate) fn parse_error_add_span(
 
     let message = match &err {
         lu::ParseError::InvalidToken { .. } => "Parse error: invalid token".to_owned(),
+        lu::ParseError::UnrecognizedToken {
+            token: (_x, t, ..),
+            expected,
+        } if is_chained_comparison(t, expected) => format!(
+            "Parse error: unexpected {} here, comparisons cannot be chained, \
+            combine them with `and` or use parentheses",
+            t
+        ),
         lu::ParseError::UnrecognizedToken {
             token: (_x, t, ..),
             expected,
@@ -94,6 +119,53 @@ pub(crate) fn parse_error_add_span(
     Diagnostic::new(anyhow!(message), span, codemap)
 }
 
+/// The byte offset at which a parse error occurred, if known.
+fn parse_error_pos(err: &lu::ParseError<usize, Token, anyhow::Error>, len: usize) -> Option<usize> {
+    match err {
+        lu::ParseError::InvalidToken { location } => Some(*location),
+        lu::ParseError::UnrecognizedToken { token: (x, ..), .. }
+        | lu::ParseError::ExtraToken { token: (x, ..) } => Some(*x),
+        lu::ParseError::UnrecognizedEOF { .. } => Some(len),
+        lu::ParseError::User { error } => diagnostic_pos(error),
+    }
+}
+
+/// The byte offset at which a [`Diagnostic`] error occurred, if known.
+fn diagnostic_pos(err: &anyhow::Error) -> Option<usize> {
+    let span = err.downcast_ref::<Diagnostic>()?.span.as_ref()?;
+    Some(span.span.begin().get() as usize)
+}
+
+/// The byte range of the top-level statement containing `pos`,
+/// running up to the start of the next top-level statement.
+fn top_level_statement_range(
+    source: &str,
+    dialect: &Dialect,
+    codemap: &CodeMap,
+    pos: usize,
+) -> Range<usize> {
+    let mut starts = Vec::new();
+    let mut depth = 0;
+    let mut at_line_start = true;
+    // The lexer carries on after errors, so skip them.
+    for (begin, token, _) in Lexer::new(source, dialect, codemap.dupe()).flatten() {
+        match token {
+            Token::Indent => depth += 1,
+            Token::Dedent => depth -= 1,
+            Token::Newline => at_line_start = true,
+            _ => {
+                if at_line_start && depth == 0 {
+                    starts.push(begin);
+                }
+                at_line_start = false;
+            }
+        }
+    }
+    let start = starts.iter().rev().find(|x| **x <= pos).copied();
+    let end = starts.iter().find(|x| **x > pos).copied();
+    start.unwrap_or(0)..end.unwrap_or(source.len())
+}
+
 impl AstModule {
     fn create(
         codemap: CodeMap,
@@ -133,6 +205,71 @@ impl AstModule {
         }
     }
 
+    /// Parse a Starlark module, recovering from syntax errors at top-level statement boundaries.
+    /// Each top-level statement which fails to parse (or validate) is dropped and its error recorded,
+    /// and the rest of the module is parsed as normal. Intended for tools such as editors, which want
+    /// a best-effort [`AstModule`] even for code with errors. The [`AstModule`] is [`None`] only if an
+    /// error could not be attributed to a location, in which case recovery is impossible.
+    ///
+    /// Recovery is coarse: an unbalanced bracket, for example, runs to the end of the file,
+    /// so everything after it is dropped.
+    ///
+    /// ```
+    /// use starlark::syntax::{AstModule, Dialect};
+    ///
+    /// let (ast, errors) = AstModule::parse_recoverable(
+    ///     "filename",
+    ///     "x = 1\ny = = 2\nz = 3\n".to_owned(),
+    ///     &Dialect::Standard,
+    /// );
+    /// assert_eq!(errors.len(), 1);
+    /// assert_eq!(ast.unwrap().loads().len(), 0);
+    /// ```
+    pub fn parse_recoverable(
+        filename: &str,
+        content: String,
+        dialect: &Dialect,
+    ) -> (Option<Self>, Vec<anyhow::Error>) {
+        let codemap = CodeMap::new(filename.to_owned(), content);
+        // The text we parse, with the statements which failed blanked out.
+        // Blanking preserves byte offsets, so spans still refer to the original text in `codemap`.
+        let mut source = codemap.source().to_owned();
+        let mut errors = Vec::new();
+        loop {
+            let lexer = Lexer::new(&source, dialect, codemap.dupe());
+            let (err, pos) = match StarlarkParser::new().parse(&codemap, dialect, lexer) {
+                Ok(v) => match AstModule::create(codemap.dupe(), v, dialect) {
+                    Ok(ast) => return (Some(ast), errors),
+                    Err(e) => {
+                        let pos = diagnostic_pos(&e);
+                        (e, pos)
+                    }
+                },
+                Err(p) => {
+                    let pos = parse_error_pos(&p, source.len());
+                    (parse_error_add_span(p, source.len(), codemap.dupe()), pos)
+                }
+            };
+            errors.push(err);
+            let range = match pos {
+                Some(pos) => top_level_statement_range(&source, dialect, &codemap, pos),
+                None => return (None, errors),
+            };
+            // Every round must blank something out, or we would loop forever.
+            if source[range.clone()].trim().is_empty() {
+                return (None, errors);
+            }
+            let blank: String = source[range.clone()]
+                .chars()
+                .map(|c| match c {
+                    '\n' | '\r' => c.to_string(),
+                    _ => " ".repeat(c.len_utf8()),
+                })
+                .collect();
+            source.replace_range(range, &blank);
+        }
+    }
+
     /// Return the file names of all the `load` statements in the module.
     /// If the [`Dialect`] had [`enable_load`](Dialect::enable_load) set to [`false`] this will be an empty list.
     pub fn loads(&self) -> Vec<&str> {
@@ -155,8 +292,190 @@ impl AstModule {
         loads
     }
 
+    /// Return all the `load` statements in the module, with the symbols each one imports.
+    /// Like [`loads`](AstModule::loads), this only inspects the syntax tree, nothing is evaluated.
+    ///
+    /// ```
+    /// use starlark::syntax::{AstModule, Dialect};
+    ///
+    /// let ast = AstModule::parse(
+    ///     "x.star",
+    ///     "load('a.star', 'b', c = 'd')\n".to_owned(),
+    ///     &Dialect::Standard,
+    /// )
+    /// .unwrap();
+    /// let loads = ast.load_statements();
+    /// assert_eq!(loads[0].module, "a.star");
+    /// assert_eq!(
+    ///     loads[0].symbols,
+    ///     vec![("b".to_owned(), "b".to_owned()), ("c".to_owned(), "d".to_owned())]
+    /// );
+    /// ```
+    pub fn load_statements(&self) -> Vec<LoadStatement> {
+        // As with `loads`, `load` statements can only occur at the top-level.
+        fn f(ast: &AstStmt, vec: &mut Vec<LoadStatement>) {
+            match &ast.node {
+                Stmt::Load(load) => vec.push(LoadStatement {
+                    module: load.module.node.clone(),
+                    symbols: load
+                        .args
+                        .map(|(local, original)| (local.0.clone(), original.node.clone())),
+                    span: ast.span,
+                }),
+                Stmt::Statements(stmts) => {
+                    for s in stmts {
+                        f(s, vec);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut loads = Vec::new();
+        f(&self.statement, &mut loads);
+        loads
+    }
+
+    /// The span of the whole module, from the start to the end of the file.
+    pub fn span(&self) -> Span {
+        self.codemap.full_span()
+    }
+
+    /// The span of each top-level statement, in order, with a short description,
+    /// e.g. `def f`, `load` or `assignment to x`, as an outline of the module.
+    ///
+    /// ```
+    /// use starlark::syntax::{AstModule, Dialect};
+    ///
+    /// let program = "x = 1\ndef f(): pass\n";
+    /// let ast = AstModule::parse("x.star", program.to_owned(), &Dialect::Standard).unwrap();
+    /// let outline: Vec<_> = ast.top_level_spans().map(|(_, description)| description).collect();
+    /// assert_eq!(outline, vec!["assignment to x", "def f"]);
+    /// ```
+    pub fn top_level_spans(&self) -> impl Iterator<Item = (Span, String)> + '_ {
+        fn f<'a>(ast: &'a AstStmt, vec: &mut Vec<&'a AstStmt>) {
+            match &ast.node {
+                Stmt::Statements(stmts) => {
+                    for s in stmts {
+                        f(s, vec);
+                    }
+                }
+                _ => vec.push(ast),
+            }
+        }
+
+        fn describe(ast: &AstStmt) -> String {
+            match &ast.node {
+                Stmt::Def(name, ..) => format!("def {}", name.0),
+                Stmt::Load(..) => "load".to_owned(),
+                Stmt::Assign(dest, _) | Stmt::AssignModify(dest, _, _) => {
+                    let mut names = Vec::new();
+                    dest.visit_lvalue(|name| names.push(name.0.as_str()));
+                    if names.is_empty() {
+                        "assignment".to_owned()
+                    } else {
+                        format!("assignment to {}", names.join(", "))
+                    }
+                }
+                Stmt::Expression(..) => "expression".to_owned(),
+                Stmt::If(..) | Stmt::IfElse(..) => "if".to_owned(),
+                Stmt::For(..) => "for".to_owned(),
+                Stmt::Assert(..) => "assert".to_owned(),
+                Stmt::Return(..) => "return".to_owned(),
+                Stmt::Break => "break".to_owned(),
+                Stmt::Continue => "continue".to_owned(),
+                Stmt::Pass => "pass".to_owned(),
+                Stmt::Statements(..) => unreachable!("flattened above"),
+            }
+        }
+
+        let mut stmts = Vec::new();
+        f(&self.statement, &mut stmts);
+        stmts.into_iter().map(|x| (x.span, describe(x)))
+    }
+
     /// Look up a [`Span`] contained in this module to a [`FileSpan`].
     pub fn file_span(&self, x: Span) -> FileSpan {
         self.codemap.file_span(x)
     }
+
+    /// Serialize the syntax tree of this module to JSON, for processing by external tools.
+    ///
+    /// The result is an object with a `version` field, incremented on incompatible changes
+    /// to the format, the `filename`, and the top-level `statement`. Every AST node is an
+    /// object with `node` and `span` fields. Node types are named by variant, e.g.
+    /// `{"Call": [function, arguments]}`. The `span` holds `begin` and `end` byte offsets,
+    /// and `begin_line`, `begin_column`, `end_line` and `end_column`, all 0-based,
+    /// with columns counted in characters.
+    ///
+    /// Literals are the parsed values: strings with escapes resolved, and numbers.
+    /// Float literals which do not fit in a `f64` (e.g. `1e999`) are written as `null`.
+    /// Literal nodes also have a `text` field with their source text, e.g. `"\x41"`
+    /// or `0x10`, so tools can reproduce them exactly.
+    pub fn to_json_ast(&self) -> String {
+        // Cannot fail: the AST contains no maps with non-string keys.
+        let mut statement = serde_json::to_value(&self.statement).unwrap();
+        add_line_col(&mut statement, &self.codemap);
+        serde_json::json!({
+            "version": JSON_AST_VERSION,
+            "filename": self.codemap.filename(),
+            "statement": statement,
+        })
+        .to_string()
+    }
+}
+
+/// A `load` statement, as returned by [`AstModule::load_statements`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadStatement {
+    /// The module being loaded, e.g. `"a.star"` in `load("a.star", "b")`.
+    pub module: String,
+    /// The imported symbols, as `(local, original)` pairs, so `load("a.star", c = "d")`
+    /// binds `d` from `a.star` as `c`. Symbols which are not renamed have both names equal.
+    pub symbols: Vec<(String, String)>,
+    /// The span of the whole statement, see [`AstModule::file_span`].
+    pub span: Span,
+}
+
+/// Version of the format produced by [`AstModule::to_json_ast`].
+const JSON_AST_VERSION: u32 = 1;
+
+/// Add line and column numbers to the spans of serialized AST nodes,
+/// and the source text to literal nodes.
+fn add_line_col(json: &mut serde_json::Value, codemap: &CodeMap) {
+    match json {
+        serde_json::Value::Array(items) => {
+            for item in items {
+                add_line_col(item, codemap);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            if fields.contains_key("node") {
+                let mut node_span = None;
+                if let Some(serde_json::Value::Object(span)) = fields.get_mut("span") {
+                    let begin = span.get("begin").and_then(serde_json::Value::as_u64);
+                    let end = span.get("end").and_then(serde_json::Value::as_u64);
+                    if let (Some(begin), Some(end)) = (begin, end) {
+                        let x = Span::new(Pos::new(begin as u32), Pos::new(end as u32));
+                        let resolved = codemap.resolve_span(x);
+                        span.insert("begin_line".to_owned(), resolved.begin_line.into());
+                        span.insert("begin_column".to_owned(), resolved.begin_column.into());
+                        span.insert("end_line".to_owned(), resolved.end_line.into());
+                        span.insert("end_column".to_owned(), resolved.end_column.into());
+                        node_span = Some(x);
+                    }
+                }
+                if let Some(node_span) = node_span {
+                    if fields["node"].get("Literal").is_some() {
+                        let text = codemap.source_span(node_span).to_owned();
+                        fields.insert("text".to_owned(), text.into());
+                    }
+                }
+            }
+            for field in fields.values_mut() {
+                add_line_col(field, codemap);
+            }
+        }
+        _ => {}
+    }
 }
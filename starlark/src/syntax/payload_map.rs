@@ -82,9 +82,13 @@ impl<A: AstPayload> StmtP<A> {
                     else_block.into_map_payload(f),
                 ),
             ),
-            StmtP::For(assign, box (coll, body)) => StmtP::For(
+            StmtP::For(assign, box (coll, body, else_block)) => StmtP::For(
                 assign.into_map_payload(f),
-                box (coll.into_map_payload(f), body.into_map_payload(f)),
+                box (
+                    coll.into_map_payload(f),
+                    body.into_map_payload(f),
+                    else_block.map(|x| x.into_map_payload(f)),
+                ),
             ),
             StmtP::Def(name, params, ret, body, p) => StmtP::Def(
                 name.into_map_payload(f),
@@ -94,6 +98,10 @@ impl<A: AstPayload> StmtP<A> {
                 f.map_def(p),
             ),
             StmtP::Load(load) => StmtP::Load(load.into_map_payload(f)),
+            StmtP::Assert(cond, msg) => StmtP::Assert(
+                cond.into_map_payload(f),
+                msg.map(|msg| box msg.into_map_payload(f)),
+            ),
         }
     }
 }
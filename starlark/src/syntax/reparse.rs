@@ -0,0 +1,404 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Reparse a module after an edit, reusing the statements the edit does not touch.
+
+use std::ops::Range;
+
+use anyhow::anyhow;
+use gazebo::prelude::*;
+
+use crate::{
+    codemap::{CodeMap, Pos, Span, Spanned},
+    syntax::{
+        ast::{
+            ArgumentP, AssignIdentP, AssignP, AstLiteral, AstModule, AstPayload, ClauseP, ExprP,
+            ForClauseP, LoadP, ParameterP, Stmt, StmtP,
+        },
+        grammar::StarlarkParser,
+        lexer::Lexer,
+        Dialect,
+    },
+};
+
+/// A replacement of a range of the source text, such as an editor sends on each change.
+#[derive(Debug, Clone)]
+pub struct TextEdit {
+    /// The byte range of the old text which is replaced.
+    pub range: Range<usize>,
+    /// The text inserted in place of `range`.
+    pub text: String,
+}
+
+impl AstModule {
+    /// Apply an edit to the source of this module and parse the result.
+    ///
+    /// Only the top-level statements the edit touches are parsed again: those before
+    /// and after it are moved into the new [`AstModule`], the latter with their spans
+    /// shifted to match the new text. The result is the same as parsing the new text
+    /// with [`parse`](AstModule::parse), including the errors if it does not parse.
+    pub fn reparse(self, edit: &TextEdit, dialect: &Dialect) -> anyhow::Result<AstModule> {
+        let old_source = self.codemap.source();
+        let TextEdit { range, text } = edit;
+        if range.start > range.end
+            || range.end > old_source.len()
+            || !old_source.is_char_boundary(range.start)
+            || !old_source.is_char_boundary(range.end)
+        {
+            return Err(anyhow!(
+                "Edit range {:?} is not valid for `{}` of {} bytes",
+                range,
+                self.codemap.filename(),
+                old_source.len()
+            ));
+        }
+        let filename = self.codemap.filename().to_owned();
+        let source = format!(
+            "{}{}{}",
+            &old_source[..range.start],
+            text,
+            &old_source[range.end..]
+        );
+        let delta = text.len() as i64 - range.len() as i64;
+
+        let at_line_start = |pos: Pos| {
+            let pos = pos.get() as usize;
+            pos == 0 || old_source.as_bytes()[pos - 1] == b'\n'
+        };
+        let Spanned { span, node } = self.statement;
+        let mut stmts = match node {
+            StmtP::Statements(stmts) => stmts,
+            _ => return AstModule::parse(&filename, source, dialect),
+        };
+
+        // The edit may extend the statement before it (e.g. add a line to a `def` body),
+        // so start from the last statement beginning before the edit,
+        // and only cut at statements starting a line, where the lexer state is fresh.
+        let mut first = stmts
+            .iter()
+            .rposition(|s| s.span.begin().get() as usize <= range.start)
+            .unwrap_or(0);
+        while first > 0 && !at_line_start(stmts[first].span.begin()) {
+            first -= 1;
+        }
+        let last = stmts[first..]
+            .iter()
+            .position(|s| {
+                s.span.begin().get() as usize > range.end && at_line_start(s.span.begin())
+            })
+            .map_or(stmts.len(), |i| first + i);
+        let region_begin = match first {
+            0 => 0,
+            _ => stmts[first].span.begin().get() as usize,
+        };
+        let region_end = match stmts.get(last) {
+            Some(s) => shift_pos(s.span.begin(), delta).get() as usize,
+            None => source.len(),
+        };
+
+        // Parse just the region, with the text before it blanked out,
+        // so the spans are those of the whole new text.
+        let codemap = CodeMap::new(filename, source);
+        let mut region_source = codemap.source()[..region_end].to_owned();
+        let blank: String = region_source[..region_begin]
+            .chars()
+            .map(|c| match c {
+                '\n' | '\r' => c.to_string(),
+                _ => " ".repeat(c.len_utf8()),
+            })
+            .collect();
+        region_source.replace_range(..region_begin, &blank);
+        let lexer = Lexer::new(&region_source, dialect, codemap.dupe());
+        let region = match StarlarkParser::new().parse(&codemap, dialect, lexer) {
+            Ok(region) if Stmt::validate(&codemap, &region, dialect).is_ok() => region,
+            // The edit may need more context, e.g. it opened a string spanning the following
+            // statements, or the new text has errors: let the full parse decide.
+            _ => return AstModule::parse(codemap.filename(), codemap.source().to_owned(), dialect),
+        };
+        let region_span = region.span;
+        let region = match region.node {
+            StmtP::Statements(region) => region,
+            node => vec![Spanned {
+                span: region_span,
+                node,
+            }],
+        };
+
+        let mut suffix = stmts.split_off(last);
+        suffix.shift_spans(delta);
+        stmts.truncate(first);
+        let begin = match first {
+            0 => region_span.begin(),
+            _ => span.begin(),
+        };
+        let end = if suffix.is_empty() {
+            region_span.end()
+        } else {
+            shift_pos(span.end(), delta)
+        };
+        stmts.extend(region);
+        stmts.extend(suffix);
+        let statement = Spanned {
+            span: Span::new(begin, end),
+            node: StmtP::Statements(stmts),
+        };
+        if !dialect.enable_top_level_reassignment {
+            // The region may rebind names bound by the statements around it.
+            Stmt::validate(&codemap, &statement, dialect)?;
+        }
+        Ok(AstModule { codemap, statement })
+    }
+}
+
+/// Move all the spans in an AST node by a number of bytes.
+trait ShiftSpans {
+    fn shift_spans(&mut self, delta: i64);
+}
+
+fn shift_pos(pos: Pos, delta: i64) -> Pos {
+    Pos::new((pos.get() as i64 + delta) as u32)
+}
+
+impl<T: ShiftSpans> ShiftSpans for Spanned<T> {
+    fn shift_spans(&mut self, delta: i64) {
+        self.span = Span::new(
+            shift_pos(self.span.begin(), delta),
+            shift_pos(self.span.end(), delta),
+        );
+        self.node.shift_spans(delta);
+    }
+}
+
+impl<T: ShiftSpans> ShiftSpans for Box<T> {
+    fn shift_spans(&mut self, delta: i64) {
+        (**self).shift_spans(delta)
+    }
+}
+
+impl<T: ShiftSpans> ShiftSpans for Option<T> {
+    fn shift_spans(&mut self, delta: i64) {
+        if let Some(x) = self {
+            x.shift_spans(delta)
+        }
+    }
+}
+
+impl<T: ShiftSpans> ShiftSpans for Vec<T> {
+    fn shift_spans(&mut self, delta: i64) {
+        for x in self {
+            x.shift_spans(delta)
+        }
+    }
+}
+
+impl<A: ShiftSpans, B: ShiftSpans> ShiftSpans for (A, B) {
+    fn shift_spans(&mut self, delta: i64) {
+        self.0.shift_spans(delta);
+        self.1.shift_spans(delta);
+    }
+}
+
+impl<A: ShiftSpans, B: ShiftSpans, C: ShiftSpans> ShiftSpans for (A, B, C) {
+    fn shift_spans(&mut self, delta: i64) {
+        self.0.shift_spans(delta);
+        self.1.shift_spans(delta);
+        self.2.shift_spans(delta);
+    }
+}
+
+/// Leaves without spans inside.
+macro_rules! shift_spans_leaf {
+    ($($t:ty),*) => {
+        $(
+            impl ShiftSpans for $t {
+                fn shift_spans(&mut self, _delta: i64) {}
+            }
+        )*
+    };
+}
+
+shift_spans_leaf!(String, i32, f64);
+
+impl<P: AstPayload> ShiftSpans for AssignIdentP<P> {
+    fn shift_spans(&mut self, _delta: i64) {}
+}
+
+impl ShiftSpans for AstLiteral {
+    fn shift_spans(&mut self, delta: i64) {
+        match self {
+            AstLiteral::Int(x) => x.shift_spans(delta),
+            AstLiteral::Float(x) => x.shift_spans(delta),
+            AstLiteral::String(x) => x.shift_spans(delta),
+        }
+    }
+}
+
+impl<P: AstPayload> ShiftSpans for StmtP<P> {
+    fn shift_spans(&mut self, delta: i64) {
+        match self {
+            StmtP::Break | StmtP::Continue | StmtP::Pass => {}
+            StmtP::Return(e) => e.shift_spans(delta),
+            StmtP::Expression(e) => e.shift_spans(delta),
+            StmtP::Assign(lhs, rhs) | StmtP::AssignModify(lhs, _, rhs) => {
+                lhs.shift_spans(delta);
+                rhs.shift_spans(delta);
+            }
+            StmtP::Statements(stmts) => stmts.shift_spans(delta),
+            StmtP::If(cond, then_block) => {
+                cond.shift_spans(delta);
+                then_block.shift_spans(delta);
+            }
+            StmtP::IfElse(cond, blocks) => {
+                cond.shift_spans(delta);
+                blocks.shift_spans(delta);
+            }
+            StmtP::For(var, over_body) => {
+                var.shift_spans(delta);
+                over_body.shift_spans(delta);
+            }
+            StmtP::Def(name, params, ret, body, _) => {
+                name.shift_spans(delta);
+                params.shift_spans(delta);
+                ret.shift_spans(delta);
+                body.shift_spans(delta);
+            }
+            StmtP::Load(load) => load.shift_spans(delta),
+            StmtP::Assert(cond, msg) => {
+                cond.shift_spans(delta);
+                msg.shift_spans(delta);
+            }
+        }
+    }
+}
+
+impl<P: AstPayload> ShiftSpans for ExprP<P> {
+    fn shift_spans(&mut self, delta: i64) {
+        match self {
+            ExprP::Tuple(xs) | ExprP::List(xs) => xs.shift_spans(delta),
+            ExprP::Dot(object, field) => {
+                object.shift_spans(delta);
+                field.shift_spans(delta);
+            }
+            ExprP::Call(f, args) => {
+                f.shift_spans(delta);
+                args.shift_spans(delta);
+            }
+            ExprP::ArrayIndirection(array_index) => array_index.shift_spans(delta),
+            ExprP::Slice(x, a, b, c) => {
+                x.shift_spans(delta);
+                a.shift_spans(delta);
+                b.shift_spans(delta);
+                c.shift_spans(delta);
+            }
+            ExprP::Identifier(id, _) => id.shift_spans(delta),
+            ExprP::Lambda(params, body, _) => {
+                params.shift_spans(delta);
+                body.shift_spans(delta);
+            }
+            ExprP::Literal(x) => x.shift_spans(delta),
+            ExprP::Not(x) | ExprP::Minus(x) | ExprP::Plus(x) | ExprP::BitNot(x) => {
+                x.shift_spans(delta)
+            }
+            ExprP::Op(l, _, r) => {
+                l.shift_spans(delta);
+                r.shift_spans(delta);
+            }
+            ExprP::If(x) => x.shift_spans(delta),
+            ExprP::Dict(kvs) => kvs.shift_spans(delta),
+            ExprP::ListComprehension(x, for_, clauses) => {
+                x.shift_spans(delta);
+                for_.shift_spans(delta);
+                clauses.shift_spans(delta);
+            }
+            ExprP::DictComprehension(kv, for_, clauses) => {
+                kv.shift_spans(delta);
+                for_.shift_spans(delta);
+                clauses.shift_spans(delta);
+            }
+        }
+    }
+}
+
+impl<P: AstPayload> ShiftSpans for AssignP<P> {
+    fn shift_spans(&mut self, delta: i64) {
+        match self {
+            AssignP::Tuple(xs) => xs.shift_spans(delta),
+            AssignP::ArrayIndirection(array_index) => array_index.shift_spans(delta),
+            AssignP::Dot(object, field) => {
+                object.shift_spans(delta);
+                field.shift_spans(delta);
+            }
+            AssignP::Identifier(ident) => ident.shift_spans(delta),
+        }
+    }
+}
+
+impl<P: AstPayload> ShiftSpans for ParameterP<P> {
+    fn shift_spans(&mut self, delta: i64) {
+        match self {
+            ParameterP::Normal(name, ty)
+            | ParameterP::Args(name, ty)
+            | ParameterP::KwArgs(name, ty) => {
+                name.shift_spans(delta);
+                ty.shift_spans(delta);
+            }
+            ParameterP::WithDefaultValue(name, ty, default) => {
+                name.shift_spans(delta);
+                ty.shift_spans(delta);
+                default.shift_spans(delta);
+            }
+            ParameterP::NoArgs => {}
+        }
+    }
+}
+
+impl<P: AstPayload> ShiftSpans for ArgumentP<P> {
+    fn shift_spans(&mut self, delta: i64) {
+        match self {
+            ArgumentP::Named(name, x) => {
+                name.shift_spans(delta);
+                x.shift_spans(delta);
+            }
+            ArgumentP::Positional(x) | ArgumentP::Args(x) | ArgumentP::KwArgs(x) => {
+                x.shift_spans(delta)
+            }
+        }
+    }
+}
+
+impl<P: AstPayload> ShiftSpans for ClauseP<P> {
+    fn shift_spans(&mut self, delta: i64) {
+        match self {
+            ClauseP::For(for_) => for_.shift_spans(delta),
+            ClauseP::If(x) => x.shift_spans(delta),
+        }
+    }
+}
+
+impl<P: AstPayload> ShiftSpans for ForClauseP<P> {
+    fn shift_spans(&mut self, delta: i64) {
+        self.var.shift_spans(delta);
+        self.over.shift_spans(delta);
+    }
+}
+
+impl<P: AstPayload> ShiftSpans for LoadP<P> {
+    fn shift_spans(&mut self, delta: i64) {
+        self.module.shift_spans(delta);
+        self.args.shift_spans(delta);
+    }
+}
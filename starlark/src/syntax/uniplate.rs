@@ -56,10 +56,11 @@ impl<P: AstPayload> StmtP<P> {
                 ret_type.iter().for_each(|x| f(Visit::Expr(x)));
                 f(Visit::Stmt(body));
             }
-            StmtP::For(lhs, box (over, body)) => {
+            StmtP::For(lhs, box (over, body, else_block)) => {
                 lhs.visit_expr(|x| f(Visit::Expr(x)));
                 f(Visit::Expr(over));
                 f(Visit::Stmt(body));
+                else_block.iter().for_each(|x| f(Visit::Stmt(x)));
             }
             // Nothing else contains nested statements
             StmtP::Break => {}
@@ -77,6 +78,10 @@ impl<P: AstPayload> StmtP<P> {
                 lhs.visit_expr(|x| f(Visit::Expr(x)));
                 f(Visit::Expr(rhs));
             }
+            StmtP::Assert(cond, msg) => {
+                f(Visit::Expr(cond));
+                msg.iter().for_each(|x| f(Visit::Expr(x)));
+            }
             StmtP::Load(..) => {}
         }
     }
@@ -100,10 +105,11 @@ impl<P: AstPayload> StmtP<P> {
                 ret_type.iter_mut().for_each(|x| f(VisitMut::Expr(x)));
                 f(VisitMut::Stmt(body));
             }
-            StmtP::For(lhs, box (over, body)) => {
+            StmtP::For(lhs, box (over, body, else_block)) => {
                 lhs.visit_expr_mut(|x| f(VisitMut::Expr(x)));
                 f(VisitMut::Expr(over));
                 f(VisitMut::Stmt(body));
+                else_block.iter_mut().for_each(|x| f(VisitMut::Stmt(x)));
             }
             // Nothing else contains nested statements
             StmtP::Break => {}
@@ -121,6 +127,10 @@ impl<P: AstPayload> StmtP<P> {
                 lhs.visit_expr_mut(|x| f(VisitMut::Expr(x)));
                 f(VisitMut::Expr(rhs));
             }
+            StmtP::Assert(cond, msg) => {
+                f(VisitMut::Expr(cond));
+                msg.iter_mut().for_each(|x| f(VisitMut::Expr(x)));
+            }
             StmtP::Load(..) => {}
         }
     }
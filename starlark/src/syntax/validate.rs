@@ -17,13 +17,13 @@
 
 //! AST for parsed starlark files.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use gazebo::prelude::*;
 use thiserror::Error;
 
 use crate::{
-    codemap::{CodeMap, Spanned},
+    codemap::{CodeMap, FileSpan, Span, Spanned},
     errors::Diagnostic,
     syntax::{
         ast::{
@@ -52,6 +52,10 @@ enum ValidateError {
     InvalidLhs,
     #[error("left-hand-side of modifying assignment cannot be a list or tuple")]
     InvalidModifyLhs,
+    #[error("`{0}` is loaded at {1} and cannot be reassigned in this dialect")]
+    ReassignLoad(String, FileSpan),
+    #[error("`{0}` is already assigned at {1} and cannot be reassigned in this dialect")]
+    ReassignTopLevel(String, FileSpan),
 }
 
 #[derive(Eq, PartialEq, Ord, PartialOrd)]
@@ -316,11 +320,15 @@ impl Stmt {
 
             match &stmt.node {
                 Stmt::Def(_, _, _, body, _payload) => f(codemap, dialect, body, false, false, true),
-                Stmt::For(_, box (_, body)) => {
+                Stmt::For(_, box (_, body, else_block)) => {
                     if top_level && !dialect.enable_top_level_stmt {
                         err(ValidateError::NoTopLevelFor)
                     } else {
-                        f(codemap, dialect, body, false, true, inside_def)
+                        f(codemap, dialect, body, false, true, inside_def)?;
+                        // `break` in `else` stops the enclosing loop, if any.
+                        else_block
+                            .iter()
+                            .try_for_each(|x| f(codemap, dialect, x, false, inside_for, inside_def))
                     }
                 }
                 Stmt::If(..) | Stmt::IfElse(..) => {
@@ -342,6 +350,86 @@ impl Stmt {
             }
         }
 
-        f(codemap, dialect, stmt, true, false, false)
+        f(codemap, dialect, stmt, true, false, false)?;
+
+        if !dialect.enable_top_level_reassignment {
+            let mut res = Ok(());
+            Stmt::visit_top_level_rebinds(stmt, |x, old, is_load| {
+                if res.is_ok() {
+                    let old = codemap.file_span(old);
+                    let e = if is_load {
+                        ValidateError::ReassignLoad(x.0.clone(), old)
+                    } else {
+                        ValidateError::ReassignTopLevel(x.0.clone(), old)
+                    };
+                    res = Err(Diagnostic::new(e, x.span, codemap.dupe()));
+                }
+            });
+            res?;
+        }
+        Ok(())
+    }
+
+    /// Call `f` for every top-level binding of an identifier which is already bound at
+    /// the top level, with the span of the first binding and whether it was a `load`.
+    /// Assigning a loaded name to itself, `x = x`, is how Starlark reexports it,
+    /// so that is allowed once per name.
+    pub(crate) fn visit_top_level_rebinds<'a>(
+        stmt: &'a AstStmt,
+        mut f: impl FnMut(&'a AstAssignIdent, Span, bool),
+    ) {
+        fn ident<'a>(
+            x: &'a AstAssignIdent,
+            is_load: bool,
+            defined: &mut HashMap<&'a str, (Span, bool)>,
+            f: &mut impl FnMut(&'a AstAssignIdent, Span, bool),
+        ) {
+            if let Some((old, old_is_load)) = defined.get(x.0.as_str()) {
+                f(x, *old, *old_is_load);
+            } else {
+                defined.insert(&x.0, (x.span, is_load));
+            }
+        }
+
+        fn visit<'a>(
+            x: &'a AstStmt,
+            defined: &mut HashMap<&'a str, (Span, bool)>,
+            exported: &mut HashSet<&'a str>,
+            f: &mut impl FnMut(&'a AstAssignIdent, Span, bool),
+        ) {
+            match &**x {
+                Stmt::Assign(lhs, rhs) => match (&**lhs, &***rhs) {
+                    (Assign::Identifier(x), Expr::Identifier(y, _))
+                        if x.node.0 == y.node
+                            && defined.get(x.node.0.as_str()).map_or(false, |x| x.1)
+                            && !exported.contains(x.node.0.as_str()) =>
+                    {
+                        // Normally this would be an error, but if we load()'d it,
+                        // this is how we'd reexport through Starlark. But only allow one export.
+                        exported.insert(x.node.0.as_str());
+                    }
+                    _ => lhs.visit_lvalue(|x| ident(x, false, defined, f)),
+                },
+                Stmt::AssignModify(lhs, _, _) => lhs.visit_lvalue(|x| ident(x, false, defined, f)),
+                Stmt::Def(name, ..) => ident(name, false, defined, f),
+                Stmt::For(lhs, box (_, body, else_block)) => {
+                    lhs.visit_lvalue(|x| ident(x, false, defined, f));
+                    visit(body, defined, exported, f);
+                    if let Some(else_block) = else_block {
+                        visit(else_block, defined, exported, f);
+                    }
+                }
+                Stmt::Load(load) => {
+                    for (name, _) in &load.args {
+                        ident(name, true, defined, f)
+                    }
+                }
+                // Visit statements, but don't descend under def,
+                // only top-level statements are interesting
+                _ => x.visit_stmt(|x| visit(x, defined, exported, f)),
+            }
+        }
+
+        visit(stmt, &mut HashMap::new(), &mut HashSet::new(), &mut f)
     }
 }
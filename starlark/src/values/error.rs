@@ -32,6 +32,9 @@ pub enum ValueError {
         left: String,
         right: String,
     },
+    /// Raised by `/`, `//` and `%` with a zero right operand, for both ints and floats.
+    /// As the spec requires, float division by zero is an error, rather than producing
+    /// an infinity or NaN.
     #[error("Cannot divide by zero")]
     DivisionByZero,
     #[error("Integer overflow")]
@@ -111,3 +114,26 @@ impl ValueError {
         Self::unsupported_owned(left.get_type(), op, Some(right.get_type()))
     }
 }
+
+/// A failure to convert a value to JSON, with the path to the value which failed
+/// within the value being converted, built by [`Value::to_json_nested`].
+#[derive(Debug, Error)]
+#[error("Cannot convert value at `{path}` to JSON: {error}")]
+pub(crate) struct JsonPathError {
+    path: String,
+    error: anyhow::Error,
+}
+
+impl JsonPathError {
+    /// Record that `error` happened within the value at `path`.
+    /// The paths are built from the inside out, so `path` is prepended.
+    pub(crate) fn prepend(mut error: anyhow::Error, path: String) -> anyhow::Error {
+        match error.downcast_mut::<JsonPathError>() {
+            Some(e) => {
+                e.path.insert_str(0, &path);
+                error
+            }
+            None => JsonPathError { path, error }.into(),
+        }
+    }
+}
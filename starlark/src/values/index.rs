@@ -15,6 +15,10 @@
  * limitations under the License.
  */
 
+//! Helpers for implementing indexing and slicing of sequences.
+
+use either::Either;
+
 use crate::values::{Value, ValueError};
 
 // Helper for convert_slice_indices
@@ -52,25 +56,34 @@ fn convert_index_aux(
     }
 }
 
+/// Convert a Starlark index into an index into a sequence of length `len`,
+/// for implementing [`at`](crate::values::StarlarkValue::at) and
+/// [`set_at`](crate::values::StarlarkValue::set_at) on custom sequences.
+///
+/// Negative indices count from the end, so `-1` is the last element.
+/// Indices which are out of range after that adjustment are an error.
+pub fn convert_index(i: i32, len: usize) -> anyhow::Result<usize> {
+    // Compute in `i64`, where neither the adjustment nor the comparison can overflow.
+    let adjusted = if i < 0 {
+        len as i64 + i as i64
+    } else {
+        i as i64
+    };
+    if adjusted < 0 || adjusted >= len as i64 {
+        Err(ValueError::IndexOutOfBound(i).into())
+    } else {
+        Ok(adjusted as usize)
+    }
+}
+
 /// Function to parse the index for at/set_at methods.
 ///
 /// Return an `i32` from self corresponding to the index recenterd between 0
 /// and len. Raise the correct errors if the value is not numeric or the
 /// index is out of bound.
-pub(crate) fn convert_index(v: Value, len: i32) -> anyhow::Result<i32> {
+pub(crate) fn convert_index_value(v: Value, len: i32) -> anyhow::Result<i32> {
     match v.to_int() {
-        Ok(x) => {
-            let i = if x < 0 {
-                len.checked_add(x).ok_or(ValueError::IntegerOverflow)?
-            } else {
-                x
-            };
-            if i < 0 || i >= len {
-                Err(ValueError::IndexOutOfBound(i).into())
-            } else {
-                Ok(i)
-            }
-        }
+        Ok(x) => Ok(convert_index(x, len as usize)? as i32),
         Err(..) => Err(ValueError::IncorrectParameterTypeWithExpected(
             "int".to_owned(),
             v.get_type().to_owned(),
@@ -118,62 +131,93 @@ pub(crate) fn convert_slice_indices(
     }
 }
 
+/// Resolve the arguments of a Starlark slice `[start:stop:stride]` of a sequence
+/// of length `len` into the indices of the selected elements, in order,
+/// for implementing [`slice`](crate::values::StarlarkValue::slice) on custom sequences.
+///
+/// Follows Starlark's rules for defaults, negative values and clamping.
+/// Returns an error if any argument is not an int or `None`, or the stride is zero.
+pub fn resolve_slice(
+    len: usize,
+    start: Option<Value>,
+    stop: Option<Value>,
+    stride: Option<Value>,
+) -> anyhow::Result<impl Iterator<Item = usize>> {
+    let (start, stop, stride) = convert_slice_indices(len as i32, start, stop, stride)?;
+    let indices = if stride > 0 {
+        Either::Left((start..stop).step_by(stride as usize))
+    } else {
+        // Going down from `start` to `stop` exclusive, where `stop` may be `-1`.
+        Either::Right(
+            ((stop + 1)..(start + 1))
+                .rev()
+                .step_by(stride.unsigned_abs() as usize),
+        )
+    };
+    Ok(indices.map(|i| i as usize))
+}
+
 pub(crate) fn apply_slice<T: Copy>(
     xs: &[T],
     start: Option<Value>,
     stop: Option<Value>,
     stride: Option<Value>,
 ) -> anyhow::Result<Vec<T>> {
-    let (start, stop, stride) = convert_slice_indices(xs.len() as i32, start, stop, stride)?;
-    if stride == 1 {
-        if start >= stop {
-            return Ok(Vec::new());
-        } else {
-            return Ok(xs[start as usize..stop as usize].to_vec());
-        }
-    }
-
-    let (start, stop) = if stride < 0 {
-        (stop + 1, start + 1)
-    } else {
-        (start, stop)
-    };
-    if start >= stop {
-        return Ok(Vec::new());
-    }
-    let mut res = xs[start as usize..stop as usize].to_vec();
-    if stride == -1 {
-        res.reverse();
-        return Ok(res);
-    }
-    if stride < 0 {
-        res.reverse();
-    }
-    let astride = stride.abs();
-    let res = res
-        .into_iter()
-        .enumerate()
-        .filter_map(|x| {
-            if 0 == (x.0 as i32 % astride) {
-                Some(x.1)
-            } else {
-                None
-            }
-        })
-        .collect();
-    Ok(res)
+    Ok(resolve_slice(xs.len(), start, stop, stride)?
+        .map(|i| xs[i])
+        .collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::values::Heap;
+    use crate::{assert, values::Heap};
 
     #[test]
     fn test_convert_index() {
+        assert_eq!(6, convert_index(-1, 7).unwrap());
+        assert_eq!(0, convert_index(-7, 7).unwrap());
+        assert_eq!(0, convert_index(0, 1).unwrap());
+        assert_eq!(
+            "Index `-8` is out of bound",
+            convert_index(-8, 7).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Index `7` is out of bound",
+            convert_index(7, 7).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Index `0` is out of bound",
+            convert_index(0, 0).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Index `-1` is out of bound",
+            convert_index(-1, 0).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Index `-2147483648` is out of bound",
+            convert_index(i32::MIN, 7).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_builtin_at() {
+        for x in ["[1, 2]", "(1, 2)", "'ab'"] {
+            assert::eq(&format!("{}[-1]", x), &format!("{}[1]", x));
+            assert::fail(&format!("{}[-3]", x), "Index `-3` is out of bound");
+            assert::fail(&format!("{}[2]", x), "Index `2` is out of bound");
+        }
+        for x in ["[]", "()", "''"] {
+            assert::fail(&format!("{}[0]", x), "Index `0` is out of bound");
+            assert::fail(&format!("{}[-1]", x), "Index `-1` is out of bound");
+        }
+    }
+
+    #[test]
+    fn test_convert_index_value() {
         let heap = Heap::new();
-        assert_eq!(Some(6), convert_index(Value::new_int(6), 7).ok());
-        assert_eq!(Some(6), convert_index(Value::new_int(-1), 7).ok());
+        assert_eq!(Some(6), convert_index_value(Value::new_int(6), 7).ok());
+        assert_eq!(Some(6), convert_index_value(Value::new_int(-1), 7).ok());
         assert_eq!(
             Some((6, 7, 1)),
             convert_slice_indices(7, Some(Value::new_int(6)), None, None).ok()
@@ -187,9 +231,44 @@ mod tests {
             convert_slice_indices(7, Some(Value::new_int(-1)), Some(Value::new_int(10)), None).ok()
         );
         // Errors
-        assert!(convert_index(heap.alloc("a"), 7).is_err());
-        assert!(convert_index(Value::new_int(8), 7).is_err()); // 8 > 7 = len
-        assert!(convert_index(Value::new_int(-8), 7).is_err()); // -8 + 7 = -1 < 0
+        assert!(convert_index_value(heap.alloc("a"), 7).is_err());
+        assert!(convert_index_value(Value::new_int(8), 7).is_err()); // 8 > 7 = len
+        assert!(convert_index_value(Value::new_int(-8), 7).is_err()); // -8 + 7 = -1 < 0
+    }
+
+    #[test]
+    fn test_resolve_slice() {
+        fn resolve(
+            len: usize,
+            start: Option<i32>,
+            stop: Option<i32>,
+            stride: Option<i32>,
+        ) -> Vec<usize> {
+            resolve_slice(
+                len,
+                start.map(Value::new_int),
+                stop.map(Value::new_int),
+                stride.map(Value::new_int),
+            )
+            .unwrap()
+            .collect()
+        }
+
+        assert_eq!(resolve(5, None, None, None), &[0, 1, 2, 3, 4]);
+        // Strided
+        assert_eq!(resolve(7, Some(1), None, Some(3)), &[1, 4]);
+        assert_eq!(resolve(7, Some(-100), Some(100), Some(2)), &[0, 2, 4, 6]);
+        // Reversed
+        assert_eq!(resolve(5, None, None, Some(-1)), &[4, 3, 2, 1, 0]);
+        assert_eq!(resolve(7, Some(-2), Some(1), Some(-2)), &[5, 3]);
+        assert_eq!(resolve(7, Some(100), Some(-100), Some(-3)), &[6, 3, 0]);
+        // Empty
+        assert_eq!(resolve(0, None, None, None), &[] as &[usize]);
+        assert_eq!(resolve(0, None, None, Some(-1)), &[] as &[usize]);
+        assert_eq!(resolve(5, Some(3), Some(1), None), &[] as &[usize]);
+        assert_eq!(resolve(5, Some(1), Some(3), Some(-1)), &[] as &[usize]);
+        // Errors
+        assert!(resolve_slice(5, None, None, Some(Value::new_int(0))).is_err());
     }
 
     #[test]
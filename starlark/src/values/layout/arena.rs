@@ -28,6 +28,7 @@
 
 use std::{
     alloc::Layout,
+    cell::Cell,
     cmp,
     collections::HashMap,
     marker::PhantomData,
@@ -61,6 +62,8 @@ pub(crate) struct Arena {
     non_drop: Bump,
     /// Arena for things which might need dropping (e.g. Vec, with memory on heap)
     drop: Bump,
+    /// Number of values allocated, including reservations.
+    count: Cell<usize>,
 }
 
 #[derive(Hash, PartialEq, Eq, Clone)]
@@ -163,6 +166,16 @@ pub struct HeapSummary {
 }
 
 impl Arena {
+    /// Arena with room for about `bytes` bytes of values before it needs to grow,
+    /// split evenly between values which need dropping and those which do not.
+    pub fn with_capacity(bytes: usize) -> Self {
+        Arena {
+            non_drop: Bump::with_capacity(bytes / 2),
+            drop: Bump::with_capacity(bytes / 2),
+            count: Cell::new(0),
+        }
+    }
+
     pub fn allocated_bytes(&self) -> usize {
         self.drop.allocated_bytes() + self.non_drop.allocated_bytes()
     }
@@ -171,6 +184,10 @@ impl Arena {
         self.drop.chunk_capacity() + self.non_drop.chunk_capacity()
     }
 
+    pub fn allocated_count(&self) -> usize {
+        self.count.get()
+    }
+
     fn alloc_uninit<'v, 'v2: 'v, T: AValue<'v2>>(
         bump: &'v Bump,
         extra_len: usize,
@@ -223,6 +240,7 @@ impl Arena {
         // it returns `false` from `is_str`.
         assert!(!T::is_str());
 
+        self.count.set(self.count.get() + 1);
         let (p, extra) = Self::alloc_uninit::<T>(self.bump_for_type::<T>(), extra_len);
         // If we don't have a vtable we can't skip over missing elements to drop,
         // so very important to put in a current vtable
@@ -257,6 +275,7 @@ impl Arena {
         x: T,
     ) -> &'v AValueRepr<T> {
         debug_assert!(x.extra_len() == 0);
+        self.count.set(self.count.get() + 1);
         let bump = self.bump_for_type::<T>();
         let (p, extra) = Self::alloc_uninit::<T>(bump, 0);
         debug_assert!(extra.is_empty());
@@ -275,6 +294,7 @@ impl Arena {
     ) -> (*mut AValueRepr<T>, &'v mut [MaybeUninit<T::ExtraElem>]) {
         assert!(!mem::needs_drop::<T>());
 
+        self.count.set(self.count.get() + 1);
         let (p, extra) = Self::alloc_uninit::<T>(&self.non_drop, x.extra_len());
         let p = p.write(AValueRepr {
             header: AValueHeader::new(&x),
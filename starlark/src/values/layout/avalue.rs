@@ -81,10 +81,15 @@ pub(crate) const VALUE_STR_A_VALUE_PTR: AValueHeader = {
 };
 
 pub(crate) static VALUE_EMPTY_TUPLE: AValueRepr<AValueImpl<Direct, FrozenTuple>> = {
+    #[allow(clippy::declare_interior_mutable_const)]
     const PAYLOAD: AValueImpl<Direct, FrozenTuple> =
         AValueImpl(Direct, unsafe { FrozenTuple::new(0) });
-    const DYN: &dyn AValueDyn<'static> = &PAYLOAD;
-    AValueRepr::with_metadata(metadata(DYN), PAYLOAD)
+    AValueRepr::with_metadata(
+        metadata(
+            &PAYLOAD as *const AValueImpl<Direct, FrozenTuple> as *const dyn AValueDyn<'static>,
+        ),
+        PAYLOAD,
+    )
 };
 
 pub(crate) static VALUE_EMPTY_FROZEN_LIST: AValueRepr<AValueImpl<Direct, ListGen<FrozenList>>> = {
@@ -407,7 +412,7 @@ impl<'v> AValue<'v> for AValueImpl<Direct, StarlarkStr> {
         );
 
         let s = (*me).payload.1.unpack();
-        let fv = freezer.alloc(s);
+        let fv = freezer.alloc_str(s);
         debug_assert!(fv.is_str());
         AValueHeader::overwrite_with_forward::<Self>(me, fv.0.ptr_value());
         Ok(fv)
@@ -453,6 +458,11 @@ impl<'v> AValue<'v> for AValueImpl<Direct, Tuple<'v>> {
             "empty tuple is allocated statically"
         );
 
+        if let Some(fv) = freezer.intern_tuple((*me).payload.1.content())? {
+            AValueHeader::overwrite_with_forward::<Self>(me, fv.0.ptr_value());
+            return Ok(fv);
+        }
+
         AValueForward::assert_does_not_overwrite_extra::<Self>();
         let content = (*me).payload.1.content();
 
@@ -514,6 +524,10 @@ impl<'v> AValue<'v> for AValueImpl<Direct, FrozenTuple> {
     unsafe fn heap_copy(_me: *mut AValueRepr<Self>, _tracer: &Tracer<'v>) -> Value<'v> {
         panic!("shouldn't be copying frozen values");
     }
+
+    fn get_hash(&self) -> anyhow::Result<SmallHashResult> {
+        self.1.get_hash_cached()
+    }
 }
 
 impl<'v> AValue<'v> for AValueImpl<Direct, ListGen<List<'v>>> {
@@ -800,6 +814,9 @@ impl<'v> StarlarkValueDyn<'v> for BlackHole {
     fn matches_type(&self, _ty: &str) -> bool {
         panic!()
     }
+    fn get_type_dynamic(&self) -> Option<String> {
+        panic!()
+    }
     fn get_methods(&self) -> Option<&'static Methods> {
         panic!()
     }
@@ -809,12 +826,18 @@ impl<'v> StarlarkValueDyn<'v> for BlackHole {
     fn collect_repr(&self, _collector: &mut String) {
         panic!()
     }
+    fn collect_str(&self, _collector: &mut String) {
+        panic!()
+    }
     fn to_json(&self) -> anyhow::Result<String> {
         panic!()
     }
     fn to_bool(&self) -> bool {
         panic!()
     }
+    fn try_to_bool(&self) -> anyhow::Result<bool> {
+        panic!()
+    }
     fn to_int(&self) -> anyhow::Result<i32> {
         panic!()
     }
@@ -880,6 +903,9 @@ impl<'v> StarlarkValueDyn<'v> for BlackHole {
     fn length(&self) -> anyhow::Result<i32> {
         panic!()
     }
+    fn kwargs_items(&self) -> Option<Vec<(String, Value<'v>)>> {
+        panic!()
+    }
     fn get_attr(&self, _attribute: &str, _heap: &'v Heap) -> Option<Value<'v>> {
         panic!()
     }
@@ -976,6 +1002,9 @@ impl<'v, Mode: 'static, T: StarlarkValue<'v>> StarlarkValueDyn<'v> for AValueImp
     fn matches_type(&self, ty: &str) -> bool {
         self.1.matches_type(ty)
     }
+    fn get_type_dynamic(&self) -> Option<String> {
+        self.1.get_type_dynamic()
+    }
     fn get_methods(&self) -> Option<&'static Methods> {
         self.1.get_methods()
     }
@@ -985,12 +1014,18 @@ impl<'v, Mode: 'static, T: StarlarkValue<'v>> StarlarkValueDyn<'v> for AValueImp
     fn collect_repr(&self, collector: &mut String) {
         self.1.collect_repr(collector)
     }
+    fn collect_str(&self, collector: &mut String) {
+        self.1.collect_str(collector)
+    }
     fn to_json(&self) -> anyhow::Result<String> {
         self.1.to_json()
     }
     fn to_bool(&self) -> bool {
         self.1.to_bool()
     }
+    fn try_to_bool(&self) -> anyhow::Result<bool> {
+        self.1.try_to_bool()
+    }
     fn to_int(&self) -> anyhow::Result<i32> {
         self.1.to_int()
     }
@@ -1056,6 +1091,9 @@ impl<'v, Mode: 'static, T: StarlarkValue<'v>> StarlarkValueDyn<'v> for AValueImp
     fn length(&self) -> anyhow::Result<i32> {
         self.1.length()
     }
+    fn kwargs_items(&self) -> Option<Vec<(String, Value<'v>)>> {
+        self.1.kwargs_items()
+    }
     fn get_attr(&self, attribute: &str, heap: &'v Heap) -> Option<Value<'v>> {
         self.1.get_attr(attribute, heap)
     }
@@ -35,7 +35,7 @@ use either::Either;
 use gazebo::{cast, prelude::*};
 
 use crate::{
-    collections::Hashed,
+    collections::{BorrowHashed, Hashed, SmallMap, StarlarkHasher},
     eval::FrozenDef,
     values::{
         any::StarlarkAny,
@@ -47,12 +47,14 @@ use crate::{
                 list_avalue, simple, starlark_str, tuple_avalue, AValue, VALUE_EMPTY_ARRAY,
                 VALUE_EMPTY_FROZEN_LIST, VALUE_EMPTY_TUPLE,
             },
-            constant::constant_string,
+            constant::{constant_string, FrozenStringValue},
             value::{FrozenValue, Value},
         },
         string::hash_string_result,
+        tuple::Tuple,
         types::float::StarlarkFloat,
-        AllocFrozenValue, ComplexValue, FrozenRef, FrozenValueTyped, SimpleValue, ValueTyped,
+        AllocFrozenValue, ComplexValue, FrozenRef, FrozenValueTyped, SimpleValue, ValueLike,
+        ValueTyped,
     },
 };
 
@@ -271,6 +273,28 @@ impl FrozenHeap {
     pub fn allocated_summary(&self) -> HeapSummary {
         self.arena.allocated_summary()
     }
+
+    /// Compute a value with `f` on a fresh [`Heap`], then freeze it, along with all the values
+    /// reachable from it, so that it remains valid as long as this [`FrozenHeap`] is kept alive.
+    /// Shared and cyclic references are preserved. Useful for caching a single computed result
+    /// without freezing a whole [`Module`](crate::environment::Module).
+    ///
+    /// Freezing overwrites the values it freezes, so only values on a heap which is never
+    /// used again can be frozen, which is why the value is computed on a heap owned by this function.
+    /// Any [`FrozenValue`]s from other heaps that `f` uses must be kept alive separately,
+    /// e.g. by [`add_reference`](FrozenHeap::add_reference).
+    pub fn freeze_computed<F>(&self, f: F) -> anyhow::Result<FrozenValue>
+    where
+        F: for<'v> FnOnce(&'v Heap) -> anyhow::Result<Value<'v>>,
+    {
+        let heap = Heap::new();
+        let value = f(&heap)?;
+        // Without a module, `f` can't define functions, so there are no defs to post-process.
+        let freezer = Freezer::new(FrozenHeap::new());
+        let frozen = freezer.freeze(value)?;
+        self.add_reference(&freezer.heap.into_ref());
+        Ok(frozen)
+    }
 }
 
 /// Used to `freeze` values by [`Freeze::freeze`](crate::values::Freeze::freeze).
@@ -282,6 +306,10 @@ pub struct Freezer {
     pub(crate) heap: FrozenHeap,
     /// Defs frozen by this freezer.
     pub(crate) frozen_defs: RefCell<Vec<FrozenRef<FrozenDef>>>,
+    /// Strings frozen so far, if interning is enabled.
+    interned_strings: Option<RefCell<HashSet<FrozenStringValue>>>,
+    /// Tuples frozen so far (by content), if interning is enabled.
+    interned_tuples: Option<RefCell<SmallMap<Vec<FrozenValue>, FrozenValue>>>,
 }
 
 impl Freezer {
@@ -289,7 +317,81 @@ impl Freezer {
         Freezer {
             heap,
             frozen_defs: RefCell::new(Vec::new()),
+            interned_strings: None,
+            interned_tuples: None,
+        }
+    }
+
+    /// Make this freezer reuse a single frozen value for all equal strings it freezes,
+    /// and for all equal tuples of strings and ints.
+    pub(crate) fn with_interning(mut self) -> Self {
+        self.interned_strings = Some(RefCell::new(HashSet::new()));
+        self.interned_tuples = Some(RefCell::new(SmallMap::new()));
+        self
+    }
+
+    /// Allocate a string while freezing, reusing an equal string if interning.
+    pub(crate) fn alloc_str(&self, x: &str) -> FrozenValue {
+        match &self.interned_strings {
+            None => self.heap.alloc_str(x),
+            Some(interned) => {
+                let mut interned = interned.borrow_mut();
+                if let Some(s) = interned.get(x) {
+                    return s.unpack();
+                }
+                let fv = self.heap.alloc_str(x);
+                // Safe because we just allocated a string.
+                interned.insert(unsafe { FrozenStringValue::new_unchecked(fv) });
+                fv
+            }
+        }
+    }
+
+    /// Freeze a tuple reusing an equal frozen tuple, if interning.
+    /// Returns `None` if the tuple must be frozen as usual.
+    pub(crate) fn intern_tuple(&self, content: &[Value]) -> anyhow::Result<Option<FrozenValue>> {
+        let interned = match &self.interned_tuples {
+            None => return Ok(None),
+            Some(interned) => interned,
+        };
+        if !content.iter().all(|x| Self::is_internable(*x)) {
+            return Ok(None);
+        }
+        let content = content.try_map(|x| self.freeze(*x))?;
+        let mut hasher = StarlarkHasher::new();
+        for x in &content {
+            x.write_hash(&mut hasher)?;
+        }
+        let hash = hasher.finish_small();
+        let mut interned = interned.borrow_mut();
+        if let Some(fv) = interned.get_hashed(BorrowHashed::new_unchecked(hash, content.as_slice()))
+        {
+            return Ok(Some(*fv));
         }
+        let fv = self.heap.alloc_tuple(&content);
+        interned.insert_hashed(Hashed::new_unchecked(hash, content), fv);
+        Ok(Some(fv))
+    }
+
+    /// Only tuples of strings, ints, bools, `None` and such tuples are interned.
+    /// These cannot refer back to the tuple being frozen, so can be frozen before it,
+    /// and are only equal to values of the same type (unlike `1` and `1.0`).
+    fn is_internable(x: Value) -> bool {
+        let x = match x.unpack_frozen() {
+            Some(_) => x,
+            None => match x.0.unpack_ptr().unwrap().unpack_overwrite() {
+                // Already frozen, and replaced with a forward.
+                Either::Left(x) => FrozenValue::new_ptr_usize_with_str_tag(x).to_value(),
+                Either::Right(_) => x,
+            },
+        };
+        x.is_str()
+            || x.is_none()
+            || x.unpack_bool().is_some()
+            || x.unpack_int().is_some()
+            || Tuple::from_value(x).map_or(false, |t| {
+                t.content().iter().all(|x| Self::is_internable(*x))
+            })
     }
 
     pub(crate) fn into_ref(self) -> FrozenHeapRef {
@@ -346,12 +448,32 @@ impl Heap {
         Self::default()
     }
 
+    /// Create a new [`Heap`] with room for about `bytes` bytes of values up front,
+    /// e.g. as measured with [`allocated_bytes`](Heap::allocated_bytes) in a previous run,
+    /// so it does not need to grow in many small steps. Reserving too much only wastes memory.
+    ///
+    /// Garbage collection moves the live values to fresh memory, so the reservation
+    /// only applies until the first garbage collection.
+    pub fn with_capacity(bytes: usize) -> Self {
+        Heap {
+            peak_allocated: Cell::new(0),
+            arena: RefCell::new(Arena::with_capacity(bytes)),
+        }
+    }
+
     /// Number of bytes allocated on this heap, not including any memory
     /// represented by [`extra_memory`](crate::values::StarlarkValue::extra_memory).
     pub fn allocated_bytes(&self) -> usize {
         self.arena.borrow().allocated_bytes()
     }
 
+    /// Number of values allocated on this heap. Garbage collection frees unreachable values,
+    /// so after one this only counts the values which were still reachable, plus those
+    /// allocated since.
+    pub fn allocated_count(&self) -> usize {
+        self.arena.borrow().allocated_count()
+    }
+
     /// Peak memory allocated to this heap, even if the value is now lower
     /// as a result of a subsequent garbage collection.
     pub fn peak_allocated_bytes(&self) -> usize {
@@ -481,6 +603,12 @@ impl Heap {
         self.alloc_raw(list_avalue(array))
     }
 
+    /// Allocate an empty list with space for `capacity` elements.
+    pub(crate) fn alloc_list_with_capacity<'v>(&'v self, capacity: usize) -> Value<'v> {
+        let array = self.alloc_array(capacity);
+        self.alloc_raw(list_avalue(array))
+    }
+
     pub fn alloc_list_iter<'v>(&'v self, elems: impl IntoIterator<Item = Value<'v>>) -> Value<'v> {
         let elems = elems.into_iter();
         let array = self.alloc_array(0);
@@ -567,7 +695,8 @@ pub struct Tracer<'v> {
 }
 
 impl<'v> Tracer<'v> {
-    /// Walk over a value during garbage collection.
+    /// Walk over a value during garbage collection. The value is updated in place,
+    /// since garbage collection moves the values it keeps alive.
     pub fn trace(&self, value: &mut Value<'v>) {
         *value = self.adjust(*value)
     }
@@ -625,3 +754,39 @@ where
     FrozenHeapRef: Send + Sync,
 {
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::values::{FrozenHeap, Heap, Value};
+
+    #[test]
+    fn test_freeze_computed() {
+        let frozen_heap = FrozenHeap::new();
+        let frozen = frozen_heap
+            .freeze_computed(|heap: &Heap| {
+                let xs = heap.alloc_list(&[Value::new_int(1), heap.alloc("x")]);
+                // The same list twice, which must stay shared after freezing.
+                Ok(heap.alloc((xs, xs)))
+            })
+            .unwrap();
+        let frozen = frozen.to_value();
+        assert_eq!(frozen.to_repr(), r#"([1, "x"], [1, "x"])"#);
+        assert!(frozen.unpack_frozen().is_some());
+        let heap = Heap::new();
+        let elems = frozen.iterate_collect(&heap).unwrap();
+        assert!(elems[0].ptr_eq(elems[1]));
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let heap = Heap::with_capacity(1 << 20);
+        let available = heap.available_bytes();
+        assert!(available >= 1 << 19, "{}", available);
+        for i in 0..1000 {
+            heap.alloc(format!("string {}", i));
+        }
+        // The values fit in the reserved memory, no new memory was needed.
+        assert!(heap.available_bytes() < available);
+        assert!(heap.available_bytes() > available - 100_000);
+    }
+}
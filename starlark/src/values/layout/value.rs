@@ -344,3 +344,31 @@ where
     FrozenValue: Send + Sync,
 {
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::values::{Heap, Value};
+
+    #[test]
+    fn test_ptr_eq() {
+        let heap = Heap::new();
+        let xs = heap.alloc_list(&[Value::new_int(1), Value::new_int(2)]);
+        let ys = heap.alloc_list(&[Value::new_int(1), Value::new_int(2)]);
+        // Equal, but distinct lists.
+        assert!(xs.equals(ys).unwrap());
+        assert!(!xs.ptr_eq(ys));
+        assert!(xs.identity() != ys.identity());
+        // The same list aliased.
+        let alias = xs;
+        assert!(xs.ptr_eq(alias));
+        assert!(xs.identity() == alias.identity());
+        // Immediates are identical when their values are equal.
+        assert!(Value::new_int(7).ptr_eq(Value::new_int(7)));
+        assert!(!Value::new_int(7).ptr_eq(Value::new_int(8)));
+        assert!(Value::new_bool(true).ptr_eq(Value::new_bool(true)));
+        assert!(!Value::new_bool(true).ptr_eq(Value::new_bool(false)));
+        assert!(Value::new_none().ptr_eq(Value::new_none()));
+        assert!(!Value::new_none().ptr_eq(Value::new_bool(false)));
+        assert!(!Value::new_int(0).ptr_eq(Value::new_bool(false)));
+    }
+}
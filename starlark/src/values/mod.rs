@@ -49,7 +49,7 @@ use crate::{
     codemap::Span,
     collections::{Hashed, StarlarkHasher},
     eval::{Arguments, Evaluator},
-    values::function::FUNCTION_TYPE,
+    values::{dict::Dict, error::JsonPathError, function::FUNCTION_TYPE, list::List, tuple::Tuple},
 };
 
 #[macro_use]
@@ -62,7 +62,7 @@ pub mod docs;
 mod error;
 mod freeze;
 mod frozen_ref;
-mod index;
+pub mod index;
 pub(crate) mod iter;
 mod layout;
 pub(crate) mod num;
@@ -174,7 +174,7 @@ pub trait ValueLike<'v>:
         if let Some(s) = self.to_value().unpack_str() {
             collector.push_str(s);
         } else {
-            self.collect_repr(collector);
+            self.to_value().get_ref().collect_str(collector);
         }
     }
 
@@ -186,6 +186,10 @@ pub trait ValueLike<'v>:
 
     /// Get a reference to underlying data or [`None`]
     /// if contained object has different type than requested.
+    ///
+    /// Works on a [`FrozenValue`] too, e.g. to get a custom value back after freezing.
+    /// The reference is only valid while the [`FrozenHeap`] owning the value is alive,
+    /// so prefer [`OwnedFrozenValue::downcast`], which keeps the heap alive.
     fn downcast_ref<T: StarlarkValue<'v>>(self) -> Option<&'v T>;
 }
 
@@ -304,10 +308,14 @@ impl<'v> Value<'v> {
     }
 
     /// Implement the `str()` function - converts a string value to itself,
-    /// otherwise uses `repr()`.
+    /// otherwise uses [`collect_str`](StarlarkValue::collect_str), which defaults to `repr()`.
     pub fn to_str(self) -> String {
         match self.unpack_str() {
-            None => self.to_repr(),
+            None => {
+                let mut s = String::new();
+                self.collect_str(&mut s);
+                s
+            }
             Some(s) => s.to_owned(),
         }
     }
@@ -319,6 +327,66 @@ impl<'v> Value<'v> {
         s
     }
 
+    /// Convert a value held inside another value to JSON, for use in implementations of
+    /// [`StarlarkValue::to_json`]. If the conversion fails, the error records `path`,
+    /// the location of this value within its container (e.g. `[2]` or `.field`),
+    /// so the final error names the part which could not be converted, e.g. `.items[2]`.
+    pub fn to_json_nested(self, path: impl FnOnce() -> String) -> anyhow::Result<String> {
+        self.to_json()
+            .map_err(|e| JsonPathError::prepend(e, path()))
+    }
+
+    /// Like [`to_repr`](Value::to_repr), but a list, tuple or dict with more than
+    /// `max_elements` elements only renders the first `max_elements` of them, followed by
+    /// the number of elements left out, e.g. `[1, 2, ... (99998 more)]`.
+    /// The elements themselves, and all other values, are rendered in full.
+    /// Useful for logging values which may be very large.
+    pub fn repr_truncated(self, max_elements: usize) -> String {
+        fn truncated<T>(
+            open: &str,
+            close: &str,
+            len: usize,
+            items: impl Iterator<Item = T>,
+            max_elements: usize,
+            mut collect_repr: impl FnMut(T, &mut String),
+        ) -> String {
+            let mut s = String::new();
+            s.push_str(open);
+            for x in items.take(max_elements) {
+                collect_repr(x, &mut s);
+                s.push_str(", ");
+            }
+            s.push_str(&format!("... ({} more)", len - max_elements));
+            s.push_str(close);
+            s
+        }
+
+        if let Some(xs) = List::from_value(self) {
+            let xs = xs.content();
+            if xs.len() > max_elements {
+                return truncated("[", "]", xs.len(), xs.iter(), max_elements, |x, s| {
+                    x.collect_repr(s)
+                });
+            }
+        } else if let Some(xs) = Tuple::from_value(self) {
+            let xs = xs.content();
+            if xs.len() > max_elements {
+                return truncated("(", ")", xs.len(), xs.iter(), max_elements, |x, s| {
+                    x.collect_repr(s)
+                });
+            }
+        } else if let Some(xs) = Dict::from_value(self) {
+            if xs.len() > max_elements {
+                return truncated("{", "}", xs.len(), xs.iter(), max_elements, |(k, v), s| {
+                    k.collect_repr(s);
+                    s.push_str(": ");
+                    v.collect_repr(s);
+                });
+            }
+        }
+        self.to_repr()
+    }
+
     /// Forwards to [`StarlarkValue::set_attr`].
     pub fn set_attr(self, attribute: &str, alloc_value: Value<'v>) -> anyhow::Result<()> {
         self.get_ref().set_attr(attribute, alloc_value)
@@ -383,14 +451,16 @@ impl<'v> Value<'v> {
     /// move on to `StarlarkValue` and include data from members.
     pub fn describe(self, name: &str) -> String {
         if self.get_type() == FUNCTION_TYPE {
-            format!("def {}: pass", self.to_repr().replace(" = ...", " = None"))
+            format!(
+                "def {}: pass",
+                self.to_string().replace(" = ...", " = None")
+            )
         } else {
             format!("# {} = {}", name, self.to_repr())
         }
     }
 
-    /// Call `export_as` on the underlying value, but only if the type is mutable.
-    /// Otherwise, does nothing.
+    /// Call `export_as` on the underlying value.
     pub fn export_as(self, variable_name: &str, eval: &mut Evaluator<'v, '_>) {
         self.get_ref().export_as(variable_name, eval)
     }
@@ -461,6 +531,16 @@ impl<'v> Value<'v> {
             self.get_ref().to_bool()
         }
     }
+    pub fn try_to_bool(self) -> anyhow::Result<bool> {
+        // Fast path for the common case
+        if let Some(x) = self.unpack_bool() {
+            Ok(x)
+        } else {
+            self.get_ref().try_to_bool()
+        }
+    }
+    /// Coerce to an integer, see [`StarlarkValue::to_int`].
+    /// Ints and bools succeed, strings and floats fail.
     pub fn to_int(self) -> anyhow::Result<i32> {
         // Fast path for the common case
         if let Some(x) = self.unpack_int() {
@@ -487,6 +567,7 @@ impl<'v> Value<'v> {
         self.get_ref().length()
     }
 
+    /// Whether `other` is in `self`, i.e. the result of the Starlark expression `other in self`.
     pub fn is_in(self, other: Value<'v>) -> anyhow::Result<bool> {
         self.get_ref().is_in(other)
     }
@@ -572,4 +653,52 @@ impl<'v> Value<'v> {
     pub fn get_type_value(self) -> FrozenStringValue {
         self.get_ref().get_type_value()
     }
+
+    /// The result of the `type()` function: [`StarlarkValue::get_type_dynamic`]
+    /// if the value has one, otherwise [`get_type_value`](Value::get_type_value).
+    pub fn get_type_value_dynamic(self, heap: &'v Heap) -> Value<'v> {
+        match self.get_ref().get_type_dynamic() {
+            None => self.get_type_value().unpack().to_value(),
+            Some(ty) => heap.alloc_str(&ty),
+        }
+    }
+
+    /// Check if `type(self) == ty`.
+    pub(crate) fn type_is(self, ty: FrozenStringValue) -> bool {
+        match self.get_ref().get_type_dynamic() {
+            None => self.get_type_value() == ty,
+            Some(dynamic) => dynamic == ty.as_str(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_repr_truncated() {
+        let big = assert::pass("list(range(100000))");
+        let big = big.value();
+        assert_eq!(big.repr_truncated(3), "[0, 1, 2, ... (99997 more)]");
+        assert_eq!(big.repr_truncated(0), "[... (100000 more)]");
+
+        let small = assert::pass("[[1, 2, 3], (4,), {5: 6}]");
+        let small = small.value();
+        assert_eq!(small.repr_truncated(3), small.to_repr());
+        assert_eq!(small.repr_truncated(1), "[[1, 2, 3], ... (2 more)]");
+
+        assert_eq!(
+            assert::pass("(1, 2, 3)").value().repr_truncated(2),
+            "(1, 2, ... (1 more))"
+        );
+        assert_eq!(
+            assert::pass("{1: 2, 3: 4}").value().repr_truncated(1),
+            "{1: 2, ... (1 more)}"
+        );
+        assert_eq!(
+            assert::pass("'a long string'").value().repr_truncated(1),
+            "\"a long string\""
+        );
+    }
 }
@@ -29,6 +29,31 @@ use crate::{
 
 /// Called by the garbage collection, and must walk over every contained `Value` in the type.
 /// Marked `unsafe` because if you miss a nested `Value`, it will probably segfault.
+///
+/// Usually derived with `#[derive(Trace)]`. When written by hand, call
+/// [`Tracer::trace`] on each [`Value`], and `trace` on fields of types which implement
+/// [`Trace`], such as [`Vec`], [`SmallMap`], [`Option`] and [`RefCell`]:
+///
+/// ```
+/// use starlark::collections::SmallMap;
+/// use starlark::values::{Trace, Tracer, Value};
+///
+/// struct Container<'v> {
+///     items: Vec<Value<'v>>,
+///     named: SmallMap<String, Value<'v>>,
+///     first: Option<Value<'v>>,
+/// }
+///
+/// unsafe impl<'v> Trace<'v> for Container<'v> {
+///     fn trace(&mut self, tracer: &Tracer<'v>) {
+///         self.items.trace(tracer);
+///         self.named.trace(tracer);
+///         if let Some(first) = &mut self.first {
+///             tracer.trace(first);
+///         }
+///     }
+/// }
+/// ```
 pub unsafe trait Trace<'v> {
     fn trace(&mut self, tracer: &Tracer<'v>);
 }
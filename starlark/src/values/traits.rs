@@ -249,6 +249,10 @@ impl SimpleValue for NoSimpleValue {}
 /// Every additional field enables further features in Starlark. In most cases the default
 /// implementation returns an "unimplemented" [`Err`].
 ///
+/// Since [`Display`] is a supertrait, `repr()` and `str()` of the value come from its
+/// [`Display`] instance without further code, so deriving [`Display`] (e.g. with
+/// `derive_more`) is usually all that is required for printing.
+///
 /// # Note To Implementors
 /// Any additional methods that are added to this trait also need to be added to the
 /// [`StarlarkValue`] implementation in `crate::values::layout::avalue::Wrapper`. Otherwise,
@@ -275,6 +279,20 @@ pub trait StarlarkValue<'v>: 'v + AnyLifetime<'v> + Debug + Display {
         self.get_type() == ty
     }
 
+    /// Return a type name which depends on the value, to be returned by the `type()`
+    /// function instead of [`get_type`](StarlarkValue::get_type). For example, a family
+    /// of record values could report `record<Point>` and `record<Size>`.
+    ///
+    /// [`get_type`](StarlarkValue::get_type) must still return the name of the whole family
+    /// (e.g. `record`), as it is used wherever a `&'static str` is needed, such as error
+    /// messages. Implementations should also override [`matches_type`](StarlarkValue::matches_type)
+    /// to accept both names. Unlike [`get_type`](StarlarkValue::get_type), the returned name
+    /// is allocated on the heap on every call to `type()`, and cannot be precomputed when a
+    /// `type(x)` on a constant `x` is compiled.
+    fn get_type_dynamic(&self) -> Option<String> {
+        None
+    }
+
     /// Get the members associated with this type, accessible via `this_type.x`.
     /// These members will have `dir`/`getattr`/`hasattr` properly implemented,
     /// so it is the preferred way to go if possible. See
@@ -309,6 +327,22 @@ pub trait StarlarkValue<'v>: 'v + AnyLifetime<'v> + Debug + Display {
         write!(collector, "{}", self).unwrap()
     }
 
+    /// Convert to a string for `str()`, `"%s"` and `"{}".format()`. Differs from
+    /// [`collect_repr`](StarlarkValue::collect_repr) only for values whose `str()`
+    /// omits some presentation, like the quotes around a string.
+    /// Defaults to [`collect_repr`](StarlarkValue::collect_repr).
+    ///
+    /// # Examples:
+    /// ```rust
+    /// # starlark::assert::all_true(r#"
+    /// str("test") == 'test'
+    /// str([1, "x"]) == '[1, "x"]'
+    /// # "#);
+    /// ```
+    fn collect_str(&self, collector: &mut String) {
+        self.collect_repr(collector)
+    }
+
     /// Convert the type to a JSON string.
     fn to_json(&self) -> anyhow::Result<String> {
         ValueError::unsupported(self, "to_json()")
@@ -322,9 +356,20 @@ pub trait StarlarkValue<'v>: 'v + AnyLifetime<'v> + Debug + Display {
         true
     }
 
-    /// Convert self to a integer value, as returned by the int() function if
-    /// the type is numeric (not for string).
+    /// Convert self to a boolean, or fail if the value has no meaningful truth value
+    /// (e.g. an unresolved promise). Used by conditionals (`if`, `and`, `or`, `not`,
+    /// conditional expressions), `bool()`, `any()` and `all()`.
+    /// The default implementation returns [`to_bool`](StarlarkValue::to_bool).
+    fn try_to_bool(&self) -> anyhow::Result<bool> {
+        Ok(self.to_bool())
+    }
+
+    /// Convert self to an integer where an integer is expected implicitly,
+    /// e.g. an index, a `%d` argument or an operand of `~`.
     /// Works for int and bool (0 = false, 1 = true).
+    ///
+    /// This is a coercion, not a conversion: it must not parse strings or
+    /// truncate floats, which only the `int()` function does explicitly.
     fn to_int(&self) -> anyhow::Result<i32> {
         ValueError::unsupported(self, "int()")
     }
@@ -372,14 +417,21 @@ pub trait StarlarkValue<'v>: 'v + AnyLifetime<'v> + Debug + Display {
     /// The number of `named` and `names` arguments are guaranteed to be equal.
     /// A direct implementation is responsible for calling [`Evaluator::with_call_stack`] to ensure
     /// the call stack is properly updated.
+    ///
+    /// The default implementation calls the `__call__` entry of [`get_methods`](StarlarkValue::get_methods),
+    /// usually defined with `#[starlark(dunder = "call")]` in a
+    /// [`#[starlark_module]`](macro@crate::starlark_module), and fails if there is none.
     fn invoke(
         &self,
-        _me: Value<'v>,
-        _location: Option<Span>,
-        _args: Arguments<'v, '_>,
-        _eval: &mut Evaluator<'v, '_>,
+        me: Value<'v>,
+        location: Option<Span>,
+        args: Arguments<'v, '_>,
+        eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
-        ValueError::unsupported(self, "call()")
+        match self.get_methods().and_then(|m| m.get_frozen("__call__")) {
+            Some(call) => call.to_value().invoke_method(me, location, args, eval),
+            None => ValueError::unsupported(self, "call()"),
+        }
     }
 
     /// Invoke this function as a method (after getattr, so this object might be unbound).
@@ -441,13 +493,31 @@ pub trait StarlarkValue<'v>: 'v + AnyLifetime<'v> + Debug + Display {
 
     /// Returns an iterable over the value of this container if this value holds
     /// an iterable container.
+    ///
+    /// The default implementation falls back to
+    /// [`iterate_snapshot`](StarlarkValue::iterate_snapshot).
     fn iterate<'a>(
         &'a self,
-        _heap: &'v Heap,
+        heap: &'v Heap,
     ) -> anyhow::Result<Box<dyn Iterator<Item = Value<'v>> + 'a>>
     where
         'v: 'a,
     {
+        Ok(box self.iterate_snapshot(heap)?.into_iter())
+    }
+
+    /// Returns a snapshot of all the elements of this container.
+    ///
+    /// Implement this instead of [`iterate`](StarlarkValue::iterate) for values
+    /// which compute their elements on demand and cannot hand out an iterator
+    /// borrowing from `self`. It is used for `for` loops, comprehensions and
+    /// every other place which iterates a value. The trade-off is that all
+    /// elements are materialized into a [`Vec`] before iteration starts,
+    /// so prefer [`iterate`](StarlarkValue::iterate) for large containers.
+    ///
+    /// Only called by the default [`iterate`](StarlarkValue::iterate), so overriding
+    /// `iterate` makes this method unused.
+    fn iterate_snapshot(&self, _heap: &'v Heap) -> anyhow::Result<Vec<Value<'v>>> {
         ValueError::unsupported(self, "(iter)")
     }
 
@@ -468,6 +538,13 @@ pub trait StarlarkValue<'v>: 'v + AnyLifetime<'v> + Debug + Display {
         ValueError::unsupported(self, "len()")
     }
 
+    /// Returns the entries of the value as named arguments, if this value is a mapping
+    /// which can be passed as `**kwargs` to a function call. Dictionaries are handled
+    /// directly, and for any other value which returns [`None`] (the default) the call fails.
+    fn kwargs_items(&self) -> Option<Vec<(String, Value<'v>)>> {
+        None
+    }
+
     /// Get an attribute for the current value as would be returned by dotted
     /// expression (i.e. `a.attribute`).
     ///
@@ -500,6 +577,8 @@ pub trait StarlarkValue<'v>: 'v + AnyLifetime<'v> + Debug + Display {
     }
 
     /// Tell wether `other` is in the current value, if it is a container.
+    /// For `x in c` (and `x not in c`) the evaluator calls `is_in` on the container `c`,
+    /// passing `x` as `other`, so a custom container implements membership here.
     ///
     /// # Examples
     ///
@@ -542,6 +621,11 @@ pub trait StarlarkValue<'v>: 'v + AnyLifetime<'v> + Debug + Display {
 
     /// Add with the arguments the other way around. Should return [`None`]
     /// to fall through to normal add.
+    ///
+    /// For `a + b` the evaluator first calls `b.radd(a)`, and only if that
+    /// returns [`None`] calls `a.add(b)`. Since `radd` is tried first, a custom value can
+    /// appear on the right of `+` with a built-in value (e.g. a list or string) on the left.
+    /// The same order is used for `a += b`, before the in-place extension of lists.
     fn radd(&self, _lhs: Value<'v>, _heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
         None
     }
@@ -630,6 +714,7 @@ pub trait StarlarkValue<'v>: 'v + AnyLifetime<'v> + Debug + Display {
     /// "%s" % ((1,),) == "(1,)"
     /// "%s" % [1] == "[1]"
     /// "test" % () == "test"
+    /// "%(name)s is %(age)d" % {"name": "Bob", "age": 75} == "Bob is 75"
     /// # "#);
     /// ```
     fn percent(&self, other: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
@@ -682,7 +767,13 @@ pub trait StarlarkValue<'v>: 'v + AnyLifetime<'v> + Debug + Display {
         ValueError::unsupported_with(self, ">>", other)
     }
 
-    /// Called when exporting a value under a specific name,
+    /// Called when exporting a value under a specific name.
+    ///
+    /// Invoked for every value, regardless of type, whenever it is assigned to a
+    /// module-level variable (including as part of a tuple assignment such as
+    /// `a, b = x, y`). Functions use it to learn their name (`f = lambda: 1`),
+    /// and custom values may record the name to produce a nicer `repr`.
+    /// Assignments inside functions and into containers do not call it.
     fn export_as(&self, _variable_name: &str, _eval: &mut Evaluator<'v, '_>) {
         // Most data types ignore how they are exported
         // but rules/providers like to use it as a helpful hint for users
@@ -709,6 +800,23 @@ pub trait StarlarkValue<'v>: 'v + AnyLifetime<'v> + Debug + Display {
     }
 }
 
+/// Attributes computed at runtime, e.g. for a value wrapping a JSON object.
+///
+/// Implementing this trait and calling [`starlark_dyn_attrs!`](crate::starlark_dyn_attrs)
+/// in the [`StarlarkValue`] impl block defines [`get_attr`](StarlarkValue::get_attr),
+/// [`has_attr`](StarlarkValue::has_attr) and [`dir_attr`](StarlarkValue::dir_attr)
+/// in terms of these two methods, so `getattr`, `hasattr` and `dir` always agree.
+///
+/// [`attr`](DynAttr::attr) must return [`Some`] exactly for the names
+/// returned by [`attr_names`](DynAttr::attr_names).
+pub trait DynAttr<'v> {
+    /// Get the attribute `attribute`, if it exists.
+    fn attr(&self, attribute: &str, heap: &'v Heap) -> Option<Value<'v>>;
+
+    /// The names of all the attributes.
+    fn attr_names(&self) -> Vec<String>;
+}
+
 /// Trait implemented by a value stored in arena which delegates
 /// it's operations to contained [`StarlarkValue`].
 pub(crate) trait StarlarkValueDyn<'v>: 'v {
@@ -731,11 +839,14 @@ pub(crate) trait StarlarkValueDyn<'v>: 'v {
     fn get_type(&self) -> &'static str;
     fn get_type_value(&self) -> FrozenStringValue;
     fn matches_type(&self, _ty: &str) -> bool;
+    fn get_type_dynamic(&self) -> Option<String>;
     fn get_methods(&self) -> Option<&'static Methods>;
     fn documentation(&self) -> Option<DocItem>;
     fn collect_repr(&self, _collector: &mut String);
+    fn collect_str(&self, _collector: &mut String);
     fn to_json(&self) -> anyhow::Result<String>;
     fn to_bool(&self) -> bool;
+    fn try_to_bool(&self) -> anyhow::Result<bool>;
     fn to_int(&self) -> anyhow::Result<i32>;
     fn write_hash(&self, hasher: &mut StarlarkHasher) -> anyhow::Result<()>;
     fn extra_memory(&self) -> usize;
@@ -776,6 +887,7 @@ pub(crate) trait StarlarkValueDyn<'v>: 'v {
         _f: &mut dyn FnMut(&mut dyn Iterator<Item = Value<'v>>) -> anyhow::Result<()>,
     ) -> anyhow::Result<()>;
     fn length(&self) -> anyhow::Result<i32>;
+    fn kwargs_items(&self) -> Option<Vec<(String, Value<'v>)>>;
     fn get_attr(&self, _attribute: &str, _heap: &'v Heap) -> Option<Value<'v>>;
     fn has_attr(&self, _attribute: &str) -> bool;
     fn dir_attr(&self) -> Vec<String>;
@@ -119,7 +119,7 @@ impl StarlarkValue<'_> for StarlarkBool {
         if let Some(other) = other.unpack_bool() {
             Ok(self.0.cmp(&other))
         } else {
-            ValueError::unsupported_with(self, "<>", other)
+            ValueError::unsupported_with(self, "compare", other)
         }
     }
 }
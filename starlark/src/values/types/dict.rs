@@ -389,7 +389,7 @@ where
             }
             res.push_str(&k.to_json()?);
             res.push_str(": ");
-            res.push_str(&v.to_json()?);
+            res.push_str(&v.to_json_nested(|| format!("[{}]", k.to_repr()))?);
         }
         res.push('}');
         Ok(res)
@@ -574,4 +574,18 @@ b1 and b2 and b3
         assert_eq!(d.get_str("foo"), None);
         Ok(())
     }
+
+    #[test]
+    fn test_to_json_error_path() {
+        assert::fail(
+            "json({'a': 1, 'b': len})",
+            r#"Cannot convert value at `["b"]` to JSON"#,
+        );
+        assert::fail(
+            "json({'a': [1, {2: len}]})",
+            r#"Cannot convert value at `["a"][1][2]` to JSON"#,
+        );
+        assert::fail("json([len])", "Cannot convert value at `[0]` to JSON");
+        assert::fail("json(len)", "`to_json()` not supported on type `function`");
+    }
 }
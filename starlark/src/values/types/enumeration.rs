@@ -55,8 +55,8 @@ use crate::{
     environment::{Methods, MethodsBuilder, MethodsStatic},
     eval::{Arguments, Evaluator},
     values::{
-        function::FUNCTION_TYPE, index::convert_index, Freeze, FrozenValue, Heap, StarlarkValue,
-        Trace, Value, ValueLike,
+        function::FUNCTION_TYPE, index::convert_index_value, Freeze, FrozenValue, Heap,
+        StarlarkValue, Trace, Value, ValueLike,
     },
 };
 
@@ -143,7 +143,8 @@ impl<'v> EnumType<'v> {
         let t = typ.downcast_ref::<EnumType>().unwrap();
         #[allow(clippy::cast_ref_to_mut)]
         unsafe {
-            // To tie the cycle we can either have an UnsafeCell or similar, or just mutate in place.
+            // To tie the cycle we can either have an UnsafeCell or similar,
+            // or just mutate in place.
             // Since we only do the tie once, better to do the mutate in place.
             // Safe because we know no one else has a copy of this reference at this point.
             *(&t.elements as *const SmallMap<Value<'v>, Value<'v>>
@@ -199,8 +200,8 @@ where
     }
 
     fn at(&self, index: Value, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        let i = convert_index(index, self.elements.len() as i32)? as usize;
-        // Must be in the valid range since convert_index checks that, so just unwrap
+        let i = convert_index_value(index, self.elements.len() as i32)? as usize;
+        // Must be in the valid range since convert_index_value checks that, so just unwrap
         Ok(self.elements.get_index(i).map(|x| *x.1).unwrap().to_value())
     }
 
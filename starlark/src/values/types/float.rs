@@ -302,12 +302,12 @@ impl<'v> StarlarkValue<'v> for StarlarkFloat {
                         Ok(ordering)
                     } else {
                         // This shouldn't happen as we handle potential NaNs above
-                        ValueError::unsupported_with(self, "==", other)
+                        ValueError::unsupported_with(self, "compare", other)
                     }
                 }
             }
         } else {
-            ValueError::unsupported_with(self, "==", other)
+            ValueError::unsupported_with(self, "compare", other)
         }
     }
 }
@@ -404,6 +404,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mixed_arithmetic() {
+        assert::all_true(
+            r#"
+1 + 2.5 == 3.5
+2.5 + 1 == 3.5
+1 - 0.5 == 0.5
+0.5 - 1 == -0.5
+2 * 1.5 == 3.0
+1.5 * 2 == 3.0
+type(2 * 1.5) == "float"
+type(2 * 3) == "int"
+2 * "ab" == "abab"
+3 / 2 == 1.5
+7 // 2.0 == 3.0
+7.5 % 2 == 1.5
+1 < 1.5
+1.5 > 1
+2 == 2.0
+"#,
+        );
+    }
+
     #[test]
     fn test_dictionary_key() {
         assert::pass(
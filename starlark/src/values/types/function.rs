@@ -16,6 +16,8 @@
  */
 
 //! Function types, including native functions and `object.member` functions.
+use std::intrinsics::unlikely;
+
 use derivative::Derivative;
 use derive_more::Display;
 use gazebo::{any::AnyLifetime, coerce::Coerce};
@@ -25,6 +27,7 @@ use crate::{
     codemap::Span,
     eval::{Arguments, Evaluator, ParametersParser, ParametersSpec},
     values::{
+        docs::{self, DocItem},
         AllocFrozenValue, AllocValue, FrozenHeap, FrozenValue, FrozenValueTyped, Heap, SimpleValue,
         StarlarkValue, Trace, Value, ValueLike,
     },
@@ -32,6 +35,50 @@ use crate::{
 
 pub const FUNCTION_TYPE: &str = "function";
 
+/// Write the `repr` of a function, e.g. `<function f(a, b=1, *args, **kwargs)>`.
+/// The parameters are taken from the documentation, and omitted if there is none.
+pub(crate) fn collect_function_repr(
+    name: &str,
+    docs: Option<&docs::Function>,
+    collector: &mut String,
+) {
+    collector.push_str("<function ");
+    collector.push_str(name);
+    if let Some(docs) = docs {
+        collector.push('(');
+        for (i, param) in docs.params.iter().enumerate() {
+            if i != 0 {
+                collector.push_str(", ");
+            }
+            // Parameters of `def` are documented with their stars, e.g. `*args`.
+            match param {
+                docs::Param::Arg {
+                    name,
+                    default_value,
+                    ..
+                } => {
+                    collector.push_str(name);
+                    if let Some(default_value) = default_value {
+                        collector.push('=');
+                        collector.push_str(default_value);
+                    }
+                }
+                docs::Param::NoArgs => collector.push('*'),
+                docs::Param::Args { name, .. } => {
+                    collector.push('*');
+                    collector.push_str(name.trim_start_matches('*'));
+                }
+                docs::Param::Kwargs { name, .. } => {
+                    collector.push_str("**");
+                    collector.push_str(name.trim_start_matches('*'));
+                }
+            }
+        }
+        collector.push(')');
+    }
+    collector.push('>');
+}
+
 /// A native function that can be evaluated.
 pub trait NativeFunc:
     for<'v> Fn(&mut Evaluator<'v, '_>, Arguments<'v, '_>) -> anyhow::Result<Value<'v>>
@@ -93,6 +140,7 @@ pub struct NativeFunction {
     typ: Option<FrozenValue>,
     /// Safe to evaluate speculatively.
     pub(crate) speculative_exec_safe: bool,
+    docs: Option<docs::Function>,
 }
 
 impl AllocFrozenValue for NativeFunction {
@@ -117,6 +165,7 @@ impl NativeFunction {
             name,
             typ: None,
             speculative_exec_safe: false,
+            docs: None,
         }
     }
 
@@ -138,6 +187,7 @@ impl NativeFunction {
             name,
             typ: None,
             speculative_exec_safe: false,
+            docs: None,
         }
     }
 
@@ -149,6 +199,14 @@ impl NativeFunction {
     pub fn set_speculative_exec_safe(&mut self) {
         self.speculative_exec_safe = true;
     }
+
+    /// Documentation of the function, returned by
+    /// [`documentation`](StarlarkValue::documentation). Functions defined with
+    /// [`#[starlark_module]`](macro@starlark_module) document their parameters,
+    /// return type and doc comment.
+    pub fn set_documentation(&mut self, docs: docs::Function) {
+        self.docs = Some(docs);
+    }
 }
 
 impl SimpleValue for NativeFunction {}
@@ -170,6 +228,9 @@ impl<'v> StarlarkValue<'v> for NativeFunction {
         args: Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
+        if unlikely(eval.speculative_exec.is_some()) && !self.speculative_exec_safe {
+            eval.speculative_exec_violation(|| format!("call to `{}`", self.name))?;
+        }
         eval.with_call_stack(me, location, |eval| (self.function)(eval, args))
     }
 
@@ -177,6 +238,14 @@ impl<'v> StarlarkValue<'v> for NativeFunction {
         self.name.capacity()
     }
 
+    fn collect_repr(&self, collector: &mut String) {
+        collect_function_repr(&self.name, self.docs.as_ref(), collector)
+    }
+
+    fn documentation(&self) -> Option<DocItem> {
+        self.docs.clone().map(DocItem::Function)
+    }
+
     fn get_attr(&self, attribute: &str, _heap: &'v Heap) -> Option<Value<'v>> {
         if let Some(s) = &self.typ {
             if attribute == "type" {
@@ -248,6 +317,9 @@ impl<'v> StarlarkValue<'v> for NativeMethod {
         args: Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
+        if unlikely(eval.speculative_exec.is_some()) && !self.speculative_exec_safe {
+            eval.speculative_exec_violation(|| format!("call to `{}`", self.name))?;
+        }
         eval.with_call_stack(me, location, |eval| (self.function)(eval, this, args))
     }
 }
@@ -140,13 +140,15 @@ impl<'v> StarlarkValue<'v> for PointerI32 {
         }
     }
     fn mul(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        if let Some(other) = other.unpack_int() {
-            self.get()
+        match other.unpack_num() {
+            Some(Num::Int(other)) => self
+                .get()
                 .checked_mul(other)
-                .ok_or_else(|| ValueError::IntegerOverflow.into())
                 .map(Value::new_int)
-        } else {
-            other.mul(Value::new_int(self.get()), heap)
+                .ok_or_else(|| ValueError::IntegerOverflow.into()),
+            Some(Num::Float(_)) => StarlarkFloat(self.get() as f64).mul(other, heap),
+            // Sequence repetition, e.g. `3 * "abc"`
+            None => other.mul(Value::new_int(self.get()), heap),
         }
     }
     fn div(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
@@ -197,7 +199,7 @@ impl<'v> StarlarkValue<'v> for PointerI32 {
         match other.unpack_num() {
             Some(Num::Int(other)) => Ok(self.get().cmp(&other)),
             Some(Num::Float(_)) => StarlarkFloat(self.get() as f64).compare(other),
-            None => ValueError::unsupported_with(self, "==", other),
+            None => ValueError::unsupported_with(self, "compare", other),
         }
     }
 
@@ -230,7 +232,15 @@ impl<'v> StarlarkValue<'v> for PointerI32 {
             other
                 .try_into()
                 .ok()
-                .and_then(|unsigned_other| self.get().checked_shl(unsigned_other))
+                .and_then(|unsigned_other| {
+                    // `checked_shl` only checks the shift amount, not the bits shifted out.
+                    let x = self.get().checked_shl(unsigned_other)?;
+                    if x >> unsigned_other == self.get() {
+                        Some(x)
+                    } else {
+                        None
+                    }
+                })
                 .map(Value::new_int)
                 .ok_or_else(|| ValueError::IntegerOverflow.into())
         } else {
@@ -260,7 +270,10 @@ impl<'v> StarlarkValueBasic<'v> for PointerI32 {
 
 #[cfg(test)]
 mod tests {
-    use crate::assert;
+    use crate::{
+        assert,
+        values::{Heap, Value},
+    };
 
     #[test]
     fn test_arithmetic_operators() {
@@ -280,4 +293,37 @@ mod tests {
 "#,
         );
     }
+
+    #[test]
+    fn test_arithmetic_overflow() {
+        assert::eq("2147483647", "2147483646 + 1");
+        assert::eq("-2147483647 - 1", "-2147483646 - 2");
+        assert::eq("-2147483647 - 1", "-1 << 31");
+        assert::eq("1073741824", "1 << 30");
+        assert::fail("2147483647 + 1", "overflow");
+        assert::fail("-2147483647 - 2", "overflow");
+        assert::fail("65536 * 32768", "overflow");
+        assert::fail("-(-2147483647 - 1)", "overflow");
+        assert::fail("(-2147483647 - 1) // -1", "overflow");
+        assert::fail("1 << 31", "overflow");
+        assert::fail("3 << 30", "overflow");
+        assert::fail("-3 << 30", "overflow");
+        assert::fail("1 << 32", "overflow");
+    }
+
+    #[test]
+    fn test_to_int() {
+        let heap = Heap::new();
+        assert_eq!(17, heap.alloc(17).to_int().unwrap());
+        assert_eq!(1, Value::new_bool(true).to_int().unwrap());
+        assert_eq!(0, Value::new_bool(false).to_int().unwrap());
+        // Strings and floats are only converted by an explicit `int()`.
+        assert!(heap.alloc("17").to_int().is_err());
+        assert!(heap.alloc(17.0).to_int().is_err());
+        assert::eq("17", "int('17')");
+        assert::eq("17", "int(17.5)");
+        assert::fail("'%d' % '17'", "not supported");
+        assert::fail("[1, 2][1.0]", "expected `int`");
+        assert::eq("2", "[1, 2][True]");
+    }
 }
@@ -42,7 +42,7 @@ use crate::{
         array::Array,
         comparison::{compare_slice, equals_slice},
         error::ValueError,
-        index::{apply_slice, convert_index},
+        index::{apply_slice, convert_index_value},
         AllocFrozenValue, AllocValue, FrozenHeap, FrozenStringValue, FrozenValue, Heap,
         StarlarkValue, UnpackValue, Value, ValueLike, ValueTyped,
     },
@@ -468,7 +468,7 @@ where
             if i != 0 {
                 res.push_str(", ");
             }
-            res.push_str(&e.to_json()?);
+            res.push_str(&e.to_json_nested(|| format!("[{}]", i))?);
         }
         res.push(']');
         Ok(res)
@@ -487,13 +487,13 @@ where
 
     fn compare(&self, other: Value<'v>) -> anyhow::Result<Ordering> {
         match List::from_value(other) {
-            None => ValueError::unsupported_with(self, "cmp()", other),
+            None => ValueError::unsupported_with(self, "compare", other),
             Some(other) => compare_slice(&*self.0.content(), &other.content, |x, y| x.compare(*y)),
         }
     }
 
     fn at(&self, index: Value, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        let i = convert_index(index, self.0.content().len() as i32)? as usize;
+        let i = convert_index_value(index, self.0.content().len() as i32)? as usize;
         Ok(self.0.content()[i])
     }
 
@@ -562,7 +562,7 @@ where
     }
 
     fn set_at(&self, index: Value<'v>, alloc_value: Value<'v>) -> anyhow::Result<()> {
-        let i = convert_index(index, self.0.content().len() as i32)? as usize;
+        let i = convert_index_value(index, self.0.content().len() as i32)? as usize;
         self.0.set_at(i, alloc_value)
     }
 }
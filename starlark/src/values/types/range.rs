@@ -26,7 +26,7 @@ use std::{
 use gazebo::prelude::*;
 
 use crate::values::{
-    index::{convert_index, convert_slice_indices},
+    index::{convert_index_value, convert_slice_indices},
     Heap, StarlarkValue, Value, ValueError, ValueLike,
 };
 
@@ -146,7 +146,7 @@ impl<'v> StarlarkValue<'v> for Range {
     }
 
     fn at(&self, index: Value, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        let index = convert_index(index, self.length()?)?;
+        let index = convert_index_value(index, self.length()?)?;
         // Must not overflow if `length` is computed correctly
         Ok(Value::new_int(self.start + self.step.get() * index))
     }
@@ -371,7 +371,10 @@ where
             .get_record_fields()
             .keys()
             .zip(&self.values)
-            .map(|(k, v)| Ok(format!("\"{}\":{}", k, v.to_json()?)))
+            .map(|(k, v)| {
+                let v = v.to_value().to_json_nested(|| format!(".{}", k))?;
+                Ok(format!("\"{}\":{}", k, v))
+            })
             .collect::<anyhow::Result<Vec<String>>>()?
             .join(",");
         s += "}";
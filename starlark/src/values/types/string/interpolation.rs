@@ -33,7 +33,7 @@ use crate::{
 };
 
 /// Operator `%` format or evaluation errors
-#[derive(Clone, Dupe, Debug, Error)]
+#[derive(Clone, Debug, Error)]
 enum StringInterpolationError {
     /// Interpolation parameter is too big for the format string.
     #[error("Too many arguments for format string")]
@@ -41,6 +41,15 @@ enum StringInterpolationError {
     /// Interpolation parameter is too small for the format string.
     #[error("Not enough arguments for format string")]
     NotEnoughParameters,
+    /// `%(key)` was used, but the value is not a dictionary.
+    #[error("Format string with `%(key)` requires a dictionary")]
+    NotMapping,
+    /// `%(key)` is not closed with `)`, or is not followed by a conversion.
+    #[error("Incomplete `%(key)` in format string")]
+    IncompleteKey,
+    /// `%(key)` names a key which is not in the dictionary.
+    #[error("Key `{0}` not found in format dictionary")]
+    KeyNotFound(String),
 }
 
 pub(crate) fn percent(format: &str, value: Value) -> anyhow::Result<String> {
@@ -69,21 +78,46 @@ pub(crate) fn percent(format: &str, value: Value) -> anyhow::Result<String> {
             .ok_or_else(|| StringInterpolationError::NotEnoughParameters.into())
     };
 
+    // Arguments are not all used when formatting with a dictionary.
+    let mut used_mapping = false;
+
     // because of the way format is defined, we can deal with it as bytes
     let mut format = format.as_bytes().iter().copied();
     while let Some(c) = format.next() {
         if c == b'%' {
-            if let Some(c) = format.next() {
+            if let Some(mut c) = format.next() {
+                // Mapping key form, e.g. `%(name)s`, takes the value from the dictionary.
+                let mut named = None;
+                if c == b'(' {
+                    let mut key = Vec::new();
+                    loop {
+                        match format.next() {
+                            Some(b')') => break,
+                            Some(c) => key.push(c),
+                            None => return Err(StringInterpolationError::IncompleteKey.into()),
+                        }
+                    }
+                    // The key is delimited by ASCII characters, so it is valid UTF-8.
+                    let key = String::from_utf8(key).unwrap();
+                    let dict =
+                        Dict::from_value(value).ok_or(StringInterpolationError::NotMapping)?;
+                    named = Some(
+                        dict.get_str(&key)
+                            .ok_or(StringInterpolationError::KeyNotFound(key))?,
+                    );
+                    used_mapping = true;
+                    c = format
+                        .next()
+                        .ok_or(StringInterpolationError::IncompleteKey)?;
+                }
+                let mut next_value = || match named {
+                    Some(v) => Ok(v),
+                    None => next_value(),
+                };
                 let out: &mut String = unsafe { cast::ptr_mut(&mut res) };
                 match c {
                     b'%' => res.push(b'%'),
-                    b's' => {
-                        let arg = next_value()?;
-                        match arg.unpack_str() {
-                            None => arg.collect_repr(out),
-                            Some(s) => out.push_str(s),
-                        }
-                    }
+                    b's' => next_value()?.collect_str(out),
                     b'r' => next_value()?.collect_repr(out),
                     b'd' => {
                         let value = next_value()?;
@@ -160,7 +194,7 @@ pub(crate) fn percent(format: &str, value: Value) -> anyhow::Result<String> {
             res.push(c);
         }
     }
-    if values.next().is_some() {
+    if !used_mapping && values.next().is_some() {
         Err(StringInterpolationError::TooManyParameters.into())
     } else {
         Ok(unsafe { String::from_utf8_unchecked(res) })
@@ -243,7 +277,7 @@ pub(crate) fn format_one<'v>(
         None => {
             let mut result = String::with_capacity(before.len() + after.len() + 10);
             result.push_str(before);
-            arg.collect_repr(&mut result);
+            arg.collect_str(&mut result);
             result.push_str(after);
             heap.alloc_str(&result)
         }
@@ -429,7 +463,7 @@ fn format_capture<'v, T: Iterator<Item = Value<'v>>>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{collections::SmallMap, values::Heap};
+    use crate::{assert, collections::SmallMap, values::Heap};
 
     fn format_capture_for_test<'v, T: Iterator<Item = Value<'v>>>(
         capture: &str,
@@ -481,6 +515,28 @@ mod tests {
         assert!(format_capture_for_test("{", &mut args, &kwargs).is_err());
     }
 
+    #[test]
+    fn test_percent() {
+        // A single value which is not a tuple is the only argument.
+        assert::eq("'5'", "'%d' % 5");
+        assert::eq("'[1]'", "'%s' % [1]");
+        assert::eq("'1 2'", "'%s %s' % (1, 2)");
+        assert::eq("'(1, 2)'", "'%s' % ((1, 2),)");
+        assert::fail("'%s %s' % 1", "Not enough arguments");
+        assert::fail("'%s %s' % (1, 2, 3)", "Too many arguments");
+        assert::fail("'%s' % (1, 2)", "Too many arguments");
+        // A dictionary is a single value, unless the format uses keys.
+        assert::eq("'{1: 2}'", "'%s' % {1: 2}");
+        assert::eq(
+            "'x=1 y=\"b\" 1'",
+            "'x=%(x)d y=%(y)r %(x)s' % {'x': 1, 'y': 'b', 'z': 3}",
+        );
+        assert::fail("'%(x)s' % {'y': 1}", "Key `x` not found");
+        assert::fail("'%(x)s' % (1,)", "requires a dictionary");
+        assert::fail("'%(x' % {'x': 1}", "Incomplete");
+        assert::fail("'%(x)' % {'x': 1}", "Incomplete");
+    }
+
     #[test]
     fn test_parse_format_one() {
         assert_eq!(
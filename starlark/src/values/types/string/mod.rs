@@ -44,7 +44,7 @@ use crate::{
 
 pub(crate) mod fast_string;
 pub(crate) mod interpolation;
-mod repr;
+pub(crate) mod repr;
 pub(crate) mod simd;
 
 /// The result of calling `type()` on strings.
@@ -238,6 +238,10 @@ impl<'v> StarlarkValue<'v> for StarlarkStr {
         string_repr(self.unpack(), buffer)
     }
 
+    fn collect_str(&self, buffer: &mut String) {
+        buffer.push_str(self.unpack())
+    }
+
     fn to_json(&self) -> anyhow::Result<String> {
         Ok(json_escape(self.unpack()))
     }
@@ -263,12 +267,13 @@ impl<'v> StarlarkValue<'v> for StarlarkStr {
         if let Some(other) = other.unpack_str() {
             Ok(self.unpack().cmp(other))
         } else {
-            ValueError::unsupported_with(self, "cmp()", other)
+            ValueError::unsupported_with(self, "compare", other)
         }
     }
 
     fn at(&self, index: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        // This method is disturbingly hot. Use the logic from `convert_index`,
+        // This method is disturbingly hot. Use the logic from `convert_index`
+        // (including the error for out of bound indices),
         // but modified to be UTF8 string friendly.
         let s = self.unpack();
         let i = i32::unpack_param(index)?;
@@ -411,26 +416,46 @@ impl<'v, 'a> AllocFrozenValue for &'a str {
     }
 }
 
-/// An opaque iterator over a string, produced by elems/codepoints
+/// What a `StringIterator` produces.
+#[derive(Debug, Clone, Copy)]
+enum StringIteratorKind {
+    /// Single-character strings.
+    Chars,
+    /// Unicode codepoints as ints.
+    Codepoints,
+    /// UTF-8 bytes as ints.
+    Bytes,
+}
+
+/// An opaque iterator over a string, produced by elems/codepoints/elem_ords
 #[derive(Debug, Trace, Coerce, Display, Freeze)]
 #[display(fmt = "iterator")]
 #[repr(C)]
 struct StringIteratorGen<V> {
     string: V,
-    produce_char: bool, // if not char, then int
+    #[trace(unsafe_ignore)]
+    #[freeze(identity)]
+    kind: StringIteratorKind,
 }
 
 pub(crate) fn iterate_chars<'v>(string: Value<'v>, heap: &'v Heap) -> Value<'v> {
     heap.alloc(StringIterator {
         string,
-        produce_char: true,
+        kind: StringIteratorKind::Chars,
     })
 }
 
 pub(crate) fn iterate_codepoints<'v>(string: Value<'v>, heap: &'v Heap) -> Value<'v> {
     heap.alloc(StringIterator {
         string,
-        produce_char: false,
+        kind: StringIteratorKind::Codepoints,
+    })
+}
+
+pub(crate) fn iterate_bytes<'v>(string: Value<'v>, heap: &'v Heap) -> Value<'v> {
+    heap.alloc(StringIterator {
+        string,
+        kind: StringIteratorKind::Bytes,
     })
 }
 
@@ -449,11 +474,15 @@ where
     where
         'v: 'a,
     {
-        let s = self.string.to_value().unpack_str().unwrap().chars();
-        if self.produce_char {
-            Ok(box s.map(move |x| heap.alloc(x)))
-        } else {
-            Ok(box s.map(|x| Value::new_int(u32::from(x) as i32)))
+        let s = self.string.to_value().unpack_str().unwrap();
+        match self.kind {
+            StringIteratorKind::Chars => Ok(box s.chars().map(move |x| heap.alloc(x))),
+            StringIteratorKind::Codepoints => {
+                Ok(box s.chars().map(|x| Value::new_int(u32::from(x) as i32)))
+            }
+            StringIteratorKind::Bytes => {
+                Ok(box s.as_bytes().iter().map(|x| Value::new_int(*x as i32)))
+            }
         }
     }
 
@@ -462,11 +491,15 @@ where
         heap: &'v Heap,
         f: &mut dyn FnMut(&mut dyn Iterator<Item = Value<'v>>) -> anyhow::Result<()>,
     ) -> anyhow::Result<()> {
-        let s = self.string.to_value().unpack_str().unwrap().chars();
-        if self.produce_char {
-            f(&mut s.map(|x| heap.alloc(x)))
-        } else {
-            f(&mut s.map(|x| Value::new_int(u32::from(x) as i32)))
+        let s = self.string.to_value().unpack_str().unwrap();
+        match self.kind {
+            StringIteratorKind::Chars => f(&mut s.chars().map(|x| heap.alloc(x))),
+            StringIteratorKind::Codepoints => {
+                f(&mut s.chars().map(|x| Value::new_int(u32::from(x) as i32)))
+            }
+            StringIteratorKind::Bytes => {
+                f(&mut s.as_bytes().iter().map(|x| Value::new_int(*x as i32)))
+            }
         }
     }
 }
@@ -17,10 +17,37 @@
 
 //! Implementation of `repr()`.
 
-use std::mem;
+use std::{cell::Cell, mem};
 
 use crate::values::types::string::simd::{SwitchHaveSimd, Vector};
 
+// Should `repr` of strings escape non-ASCII characters, set by the evaluator.
+//
+// Thread-local like the stack depth in `stack_guard`, because `collect_repr`
+// has no access to the evaluator, and is also reached through `Display`.
+#[thread_local]
+static REPR_ASCII: Cell<bool> = Cell::new(false);
+
+/// Previous [`REPR_ASCII`] setting, restored on drop.
+#[must_use]
+pub(crate) struct ReprAsciiGuard {
+    prev: bool,
+}
+
+impl Drop for ReprAsciiGuard {
+    fn drop(&mut self) {
+        REPR_ASCII.set(self.prev);
+    }
+}
+
+/// Make [`string_repr`] escape every non-ASCII character as `\u{...}` (or not)
+/// until the returned guard is dropped.
+pub(crate) fn repr_ascii_guard(ascii: bool) -> ReprAsciiGuard {
+    ReprAsciiGuard {
+        prev: REPR_ASCII.replace(ascii),
+    }
+}
+
 /// Check if any byte in the buffer is non-ASCII or need escape.
 #[inline(always)]
 unsafe fn need_escape<V: Vector>(chunk: V) -> bool {
@@ -55,9 +82,12 @@ pub(crate) fn string_repr(str: &str, buffer: &mut String) {
 
     // Simple but definitely correct version
     fn loop_unicode(val: &str, buffer: &mut String) {
+        let ascii = REPR_ASCII.get();
         for x in val.chars() {
-            for c in x.escape_debug() {
-                buffer.push(c);
+            if ascii && !x.is_ascii() {
+                buffer.extend(x.escape_unicode());
+            } else {
+                buffer.extend(x.escape_debug());
             }
         }
     }
@@ -202,7 +232,7 @@ mod test {
 
     use crate::{
         assert,
-        values::types::string::repr::{need_escape, string_repr},
+        values::types::string::repr::{need_escape, repr_ascii_guard, string_repr},
     };
 
     #[test]
@@ -227,6 +257,39 @@ mod test {
         test(r#""\u{7f}""#, "\x7f");
     }
 
+    #[test]
+    fn test_string_repr_ascii() {
+        fn test(expected_utf8: &str, expected_ascii: &str, input: &str) {
+            assert_eq!(expected_utf8, string_repr_for_test(input));
+            let _guard = repr_ascii_guard(true);
+            assert_eq!(expected_ascii, string_repr_for_test(input));
+        }
+        test(r#""\u{12}\n""#, r#""\u{12}\n""#, "\x12\n");
+        test(r#""\"\'""#, r#""\"\'""#, "\"'");
+        test(r#""\\x""#, r#""\\x""#, "\\x");
+        test(r#""世界""#, r#""\u{4e16}\u{754c}""#, "世界");
+        test(r#""a😿b""#, r#""a\u{1f63f}b""#, "a😿b");
+        // The guard restores the previous setting.
+        assert_eq!(r#""é""#, string_repr_for_test("é"));
+    }
+
+    #[test]
+    fn test_repr_ascii_evaluator() {
+        let mut a = assert::Assert::new();
+        a.setup_eval(|eval| eval.set_repr_ascii(true));
+        a.all_true(
+            r#"
+repr("世界") == '"\\u{4e16}\\u{754c}"'
+repr(["\t", "é"]) == '["\\t", "\\u{e9}"]'
+repr("plain") == '"plain"'
+str(["é"]) == '["\\u{e9}"]'
+"%r" % "é" == '"\\u{e9}"'
+"{!r}".format("é") == '"\\u{e9}"'
+str("é") == "é"
+"#,
+        );
+    }
+
     #[test]
     fn test_to_repr_long_smoke() {
         assert::all_true(
@@ -149,10 +149,11 @@ where
             .fields
             .iter()
             .map(|(k, v)| {
+                let k = k.to_string_value().as_str();
                 Ok(format!(
                     "\"{}\":{}",
-                    k.to_string_value().as_str(),
-                    v.to_json()?
+                    k,
+                    v.to_value().to_json_nested(|| format!(".{}", k))?
                 ))
             })
             .collect::<anyhow::Result<Vec<String>>>()?
@@ -172,7 +173,7 @@ where
 
     fn compare(&self, other: Value<'v>) -> anyhow::Result<Ordering> {
         match Struct::from_value(other) {
-            None => ValueError::unsupported_with(self, "cmp()", other),
+            None => ValueError::unsupported_with(self, "compare", other),
             Some(other) => compare_small_map(
                 coerce_ref(&self.fields),
                 &other.fields,
@@ -278,4 +279,16 @@ struct(foo = [struct(bar = "some")]).to_json() == '{"foo":[{"bar":"some"}]}'
 "#,
         );
     }
+
+    #[test]
+    fn test_to_json_error_path() {
+        assert::fail(
+            "struct(items = [1, 2, len]).to_json()",
+            "Cannot convert value at `.items[2]` to JSON",
+        );
+        assert::fail(
+            "struct(a = struct(b = (1, len))).to_json()",
+            "Cannot convert value at `.a.b[1]` to JSON",
+        );
+    }
 }
@@ -21,7 +21,9 @@ use std::{
     cmp::Ordering,
     fmt,
     fmt::{Debug, Display, Formatter},
+    ops::Deref,
     slice,
+    sync::atomic,
 };
 
 use gazebo::{
@@ -30,15 +32,15 @@ use gazebo::{
 };
 
 use crate::{
-    collections::StarlarkHasher,
+    collections::{SmallHashResult, StarlarkHasher},
     values::{
         comparison::{compare_slice, equals_slice},
-        index::{apply_slice, convert_index},
+        index::{apply_slice, convert_index_value},
         AllocValue, FrozenValue, Heap, StarlarkValue, UnpackValue, Value, ValueError, ValueLike,
     },
 };
 
-/// Define the tuple type. See [`Tuple`] and [`FrozenTuple`] as the two aliases.
+/// Define the tuple type. See [`Tuple`] and [`FrozenTuple`] as the two possible representations.
 #[repr(C)]
 pub struct TupleGen<V> {
     len: usize,
@@ -84,28 +86,94 @@ impl<V> TupleGen<V> {
 }
 
 pub type Tuple<'v> = TupleGen<Value<'v>>;
-pub type FrozenTuple = TupleGen<FrozenValue>;
 
-unsafe impl<'v> Coerce<Tuple<'v>> for FrozenTuple {}
+/// Define the tuple type. See [`Tuple`] and [`FrozenTuple`] as the two possible representations.
+///
+/// The content of a frozen tuple can't change, so unlike [`Tuple`] it caches its hash.
+#[repr(C)]
+pub struct FrozenTuple {
+    /// Lazily-initialized cached hash code. Zero means not computed yet.
+    hash: atomic::AtomicU32,
+    /// Must be the last field, the content is stored after it.
+    tuple: TupleGen<FrozenValue>,
+}
+
+unsafe impl<'v> Coerce<Tuple<'v>> for TupleGen<FrozenValue> {}
 
 unsafe impl<'v> AnyLifetime<'v> for TupleGen<Value<'v>> {
     any_lifetime_body!(TupleGen<Value<'static>>);
 }
 
-unsafe impl<'v> AnyLifetime<'v> for TupleGen<FrozenValue> {
-    any_lifetime_body!(TupleGen<FrozenValue>);
-}
+any_lifetime!(FrozenTuple);
 
 impl<'v> Tuple<'v> {
     pub fn from_value(value: Value<'v>) -> Option<&'v Self> {
         if value.unpack_frozen().is_some() {
-            value.downcast_ref::<FrozenTuple>().map(coerce_ref)
+            value
+                .downcast_ref::<FrozenTuple>()
+                .map(FrozenTuple::as_tuple)
         } else {
             value.downcast_ref::<Tuple<'v>>()
         }
     }
 }
 
+impl Deref for FrozenTuple {
+    type Target = TupleGen<FrozenValue>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tuple
+    }
+}
+
+impl Display for FrozenTuple {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.tuple, f)
+    }
+}
+
+impl Debug for FrozenTuple {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrozenTuple")
+            .field("content", &self.content())
+            .finish()
+    }
+}
+
+impl FrozenTuple {
+    pub(crate) const unsafe fn new(len: usize) -> FrozenTuple {
+        FrozenTuple {
+            hash: atomic::AtomicU32::new(0),
+            tuple: TupleGen::new(len),
+        }
+    }
+
+    pub(crate) fn offset_of_content() -> usize {
+        memoffset::offset_of!(Self, tuple) + TupleGen::<FrozenValue>::offset_of_content()
+    }
+
+    fn as_tuple<'v>(&self) -> &Tuple<'v> {
+        coerce_ref(&self.tuple)
+    }
+
+    /// Hash of the tuple, computed on first use and cached afterwards.
+    pub(crate) fn get_hash_cached(&self) -> anyhow::Result<SmallHashResult> {
+        let hash = self.hash.load(atomic::Ordering::Relaxed);
+        if hash != 0 {
+            Ok(SmallHashResult::new_unchecked(hash))
+        } else {
+            let mut hasher = StarlarkHasher::new();
+            for v in self.content() {
+                v.write_hash(&mut hasher)?;
+            }
+            let hash = hasher.finish_small();
+            // If hash is zero, we are unlucky, but it is highly improbable.
+            self.hash.store(hash.get(), atomic::Ordering::Relaxed);
+            Ok(hash)
+        }
+    }
+}
+
 impl<'v, V: ValueLike<'v>> TupleGen<V> {
     /// Get the length of the tuple.
     pub fn len(&self) -> usize {
@@ -154,7 +222,7 @@ where
             if i != 0 {
                 res.push_str(", ");
             }
-            res.push_str(&e.to_json()?);
+            res.push_str(&e.to_json_nested(|| format!("[{}]", i))?);
         }
         res.push(']');
         Ok(res)
@@ -169,13 +237,13 @@ where
 
     fn compare(&self, other: Value<'v>) -> anyhow::Result<Ordering> {
         match Tuple::from_value(other) {
-            None => ValueError::unsupported_with(self, "cmp()", other),
+            None => ValueError::unsupported_with(self, "compare", other),
             Some(other) => compare_slice(self.content(), other.content(), |x, y| x.compare(*y)),
         }
     }
 
     fn at(&self, index: Value, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        let i = convert_index(index, self.len() as i32)? as usize;
+        let i = convert_index_value(index, self.len() as i32)? as usize;
         Ok(self.content()[i].to_value())
     }
 
@@ -245,6 +313,78 @@ where
     }
 }
 
+impl<'v> StarlarkValue<'v> for FrozenTuple {
+    starlark_type!(Tuple::TYPE);
+
+    fn to_bool(&self) -> bool {
+        self.as_tuple().to_bool()
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> anyhow::Result<()> {
+        self.as_tuple().write_hash(hasher)
+    }
+
+    fn to_json(&self) -> anyhow::Result<String> {
+        self.as_tuple().to_json()
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        self.as_tuple().equals(other)
+    }
+
+    fn compare(&self, other: Value<'v>) -> anyhow::Result<Ordering> {
+        self.as_tuple().compare(other)
+    }
+
+    fn at(&self, index: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.as_tuple().at(index, heap)
+    }
+
+    fn length(&self) -> anyhow::Result<i32> {
+        self.as_tuple().length()
+    }
+
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        self.as_tuple().is_in(other)
+    }
+
+    fn slice(
+        &self,
+        start: Option<Value>,
+        stop: Option<Value>,
+        stride: Option<Value>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        self.as_tuple().slice(start, stop, stride, heap)
+    }
+
+    fn iterate<'a>(
+        &'a self,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Box<dyn Iterator<Item = Value<'v>> + 'a>>
+    where
+        'v: 'a,
+    {
+        self.as_tuple().iterate(heap)
+    }
+
+    fn with_iterator(
+        &self,
+        heap: &'v Heap,
+        f: &mut dyn FnMut(&mut dyn Iterator<Item = Value<'v>>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        self.as_tuple().with_iterator(heap, f)
+    }
+
+    fn add(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.as_tuple().add(other, heap)
+    }
+
+    fn mul(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.as_tuple().mul(other, heap)
+    }
+}
+
 impl<'v, T1: AllocValue<'v>> AllocValue<'v> for (T1,) {
     fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
         heap.alloc_tuple(&[self.0.alloc_value(heap)])
@@ -288,7 +428,13 @@ impl<'v, T1: UnpackValue<'v>, T2: UnpackValue<'v>> UnpackValue<'v> for (T1, T2)
 
 #[cfg(test)]
 mod tests {
-    use crate::assert;
+    use std::mem;
+
+    use crate::{
+        assert,
+        environment::Module,
+        values::tuple::{FrozenTuple, Tuple},
+    };
 
     #[test]
     fn test_to_str() {
@@ -300,4 +446,47 @@ str((1,)) == "(1,)"
 "#,
         );
     }
+
+    #[test]
+    fn test_hash_cache_only_frozen() {
+        // Only frozen tuples pay for the cached hash.
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<Tuple>());
+        assert!(mem::size_of::<FrozenTuple>() > mem::size_of::<Tuple>());
+        assert_eq!(
+            mem::size_of::<FrozenTuple>(),
+            FrozenTuple::offset_of_content()
+        );
+    }
+
+    #[test]
+    fn test_frozen_hash_cached() {
+        let module = Module::new();
+        let tuple = module.heap().alloc((1, "x"));
+        let hash = tuple.get_hash().unwrap();
+        module.set("t", tuple);
+        let frozen = module.freeze().unwrap();
+        let frozen = frozen.get("t").unwrap();
+        let frozen = frozen.value();
+        // The first call computes and caches the hash, the second reads the cache,
+        // and both agree with the hash of the tuple before freezing.
+        assert_eq!(hash, frozen.get_hash().unwrap());
+        assert_eq!(hash, frozen.get_hash().unwrap());
+
+        let mut a = assert::Assert::new();
+        a.module(
+            "m",
+            "keys = {(1, 'x'): 1, ((2, 3), 'y'): 2}\nunhashable = (1, [])",
+        );
+        a.pass(
+            r#"
+load("m", "keys")
+assert_eq(keys[(1, "x")], 1)
+assert_eq(keys[((2, 3), "y")], 2)
+assert_eq(keys[((2, 3), "y")], 2)
+"#,
+        );
+        // Failing to hash is not cached.
+        a.fail("load('m', 'unhashable')\n{unhashable: 1}\n", "not hashable");
+        a.fail("load('m', 'unhashable')\n{unhashable: 1}\n", "not hashable");
+    }
 }
@@ -17,12 +17,12 @@
 
 //! Parameter conversion utilities for `starlark_module` macros.
 
-use std::ops::Deref;
+use std::{convert::TryFrom, ops::Deref};
 
 use either::Either;
 use gazebo::prelude::*;
 
-use crate::values::{list::List, tuple::Tuple, Value, ValueError};
+use crate::values::{list::List, num::Num, tuple::Tuple, Value, ValueError};
 
 /// How to convert a [`Value`] to a Rust type. Required for all arguments in a [`#[starlark_module]`](macro@starlark_module) definition.
 pub trait UnpackValue<'v>: Sized {
@@ -136,3 +136,67 @@ impl<'v, T: UnpackValue<'v>> UnpackValue<'v> for Vec<T> {
         }
     }
 }
+
+/// Implement [`TryFrom<Value>`](TryFrom) for types with an [`UnpackValue`] instance,
+/// failing with the same error as [`unpack_param`](UnpackValue::unpack_param).
+macro_rules! impl_try_from_value {
+    ($($t:ty),*) => {
+        $(
+            impl<'v> TryFrom<Value<'v>> for $t {
+                type Error = anyhow::Error;
+
+                fn try_from(value: Value<'v>) -> anyhow::Result<Self> {
+                    Self::unpack_param(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_value!(i32, bool, String, &'v str);
+
+/// Accepts both `int` and `float`.
+impl<'v> TryFrom<Value<'v>> for f64 {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Value<'v>) -> anyhow::Result<Self> {
+        Ok(Num::unpack_param(value)?.as_float())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::{TryFrom, TryInto};
+
+    use crate::values::{Heap, Value};
+
+    #[test]
+    fn test_try_from_value() {
+        let heap = Heap::new();
+        assert_eq!(17, i32::try_from(heap.alloc(17)).unwrap());
+        assert!(bool::try_from(Value::new_bool(true)).unwrap());
+        assert_eq!("x", String::try_from(heap.alloc("x")).unwrap());
+        let s: &str = heap.alloc("y").try_into().unwrap();
+        assert_eq!("y", s);
+        assert_eq!(2.5, f64::try_from(heap.alloc(2.5)).unwrap());
+        assert_eq!(3.0, f64::try_from(heap.alloc(3)).unwrap());
+
+        fn err<T: for<'v> TryFrom<Value<'v>, Error = anyhow::Error>>(value: Value) -> String {
+            match T::try_from(value) {
+                Ok(_) => panic!("conversion should fail"),
+                Err(e) => e.to_string(),
+            }
+        }
+
+        // Floats are not truncated, even without a fractional part.
+        assert_eq!(
+            "Type of parameters mismatch, expected `int`, actual `float`",
+            err::<i32>(heap.alloc(2.5))
+        );
+        assert!(err::<i32>(heap.alloc(2.0)).contains("expected `int`"));
+        assert!(err::<i32>(Value::new_bool(true)).contains("actual `bool`"));
+        assert!(err::<bool>(heap.alloc(1)).contains("expected `bool`, actual `int`"));
+        assert!(err::<String>(heap.alloc(1)).contains("expected `str`, actual `int`"));
+        assert!(err::<f64>(heap.alloc("1.5")).contains("actual `string`"));
+    }
+}
@@ -54,7 +54,10 @@ mod util;
 /// * `args` means the argument is the `*args`.
 /// * `kwargs` means the argument is the `**kwargs`.
 /// * `ref name` means the argument must be passed by position, not by name.
-/// * A type of `Option` means the argument is optional.
+/// * A type of `Option` means the argument is optional, and is `None` if not passed.
+///   An explicit `None` argument is only accepted if the inner type accepts it, so
+///   `Option<Value>` or `Option<NoneOr<T>>` can distinguish an omitted argument (`None`)
+///   from one passed as `None` (`Some(Value::new_none())` or `Some(NoneOr::None)`).
 /// * A pattern `x @ foo : bool` means the argument defaults to `foo` if not
 ///   specified.
 ///
@@ -63,21 +66,34 @@ mod util;
 /// * `eval` is the `Evaluator`.
 /// * `heap` is the `Heap`, obtained from `eval.heap()`.
 ///
+/// The function returns `anyhow::Result<T>`, and the result is allocated on the heap.
+/// A return type of `Value<'v>` opts out of that allocation: the value is returned as is.
+///
 /// A function with the `#[starlark_module]` attribute can be added to a `GlobalsBuilder` value
 /// using the `with` function. Those `Globals` can be passed to `Evaluator` to provide global functions.
 /// Alternatively, you can return `Globals` from `get_methods` to _attach_ functions to
 /// a specific type (e.g. the `string` type).
 ///
 /// * When unattached, you can define constants with `const`. We define `True`, `False` and
-///   `None` that way.
+///   `None` that way. A constant may refer to the constants defined before it
+///   in the same module; referring to one defined later is an error.
 /// * When attached, you can annotate the functions with `#[starlark(attribute)]` to turn the name into
 ///   an attribute on the value. Such a function must take exactly one argument, namely a value
-///   of the type you have attached it to.
+///   of the type you have attached it to. The body may use `heap` to allocate, and the
+///   function may return any allocatable type (e.g. `Vec<i32>`), which is allocated afresh
+///   on every access.
+/// * When attached, a function whose first parameter is named `this` is a method, and `this`
+///   is the value the method is called on. It may have any type implementing `UnpackValue`,
+///   e.g. `this: &MyType` for a type defined with `starlark_simple_value!`; if the value
+///   is of a different type the call fails with a type error for the `this` parameter.
 /// * The attribute `#[starlark(type("test"))]` causes `f.type` to return `"test"`.
 /// * If a member is annotated with `#[starlark(speculative_exec_safe)]`, then a function
 ///   is considered safe to execute speculatively: the function should have
 ///   no global side effects, should not panic, and should finish in reasonable time.
 ///   The evaluator may invoke such functions early to generate more efficient code.
+/// * When attached, a method annotated with `#[starlark(dunder = "call")]` is registered
+///   as `__call__`, which the default `StarlarkValue::invoke` uses to make the value
+///   itself callable.
 ///
 /// All these functions interoperate properly with `dir()`, `getattr()` and `hasattr()`.
 ///
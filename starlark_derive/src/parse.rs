@@ -16,10 +16,11 @@
  */
 
 use gazebo::prelude::*;
-use proc_macro2::Span;
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
+use quote::ToTokens;
 use syn::{
-    spanned::Spanned, Attribute, FnArg, Item, ItemConst, ItemFn, Meta, NestedMeta, Pat, PatType,
-    ReturnType, Stmt, Type, TypeReference,
+    spanned::Spanned, Attribute, FnArg, Item, ItemConst, ItemFn, Lit, Meta, NestedMeta, Pat,
+    PatType, ReturnType, Stmt, Type, TypeReference,
 };
 
 use crate::{typ::*, util::*};
@@ -67,15 +68,74 @@ pub(crate) fn parse(mut input: ItemFn) -> syn::Result<StarModule> {
             ));
         }
     };
+    let mut stmts = input.block.stmts.into_try_map(parse_stmt)?;
+    resolve_const_references(&mut stmts)?;
     Ok(StarModule {
         module_kind,
         visibility,
         globals_builder: *ty,
         name,
-        stmts: input.block.stmts.into_try_map(parse_stmt)?,
+        stmts,
     })
 }
 
+/// Find the identifiers in a token stream, looking inside groups.
+fn collect_idents(tokens: TokenStream, res: &mut Vec<Ident>) {
+    for t in tokens {
+        match t {
+            TokenTree::Ident(x) => res.push(x),
+            TokenTree::Group(x) => collect_idents(x.stream(), res),
+            _ => {}
+        }
+    }
+}
+
+/// Constants are registered in order, so a constant may only refer to
+/// the constants defined before it. Mark the constants which are referred to,
+/// and reject references to constants defined later.
+fn resolve_const_references(stmts: &mut [StarStmt]) -> syn::Result<()> {
+    let consts: Vec<(usize, Ident)> = stmts
+        .iter()
+        .enumerate()
+        .filter_map(|(i, x)| match x {
+            StarStmt::Const(x) => Some((i, x.name.clone())),
+            _ => None,
+        })
+        .collect();
+    let mut referenced = Vec::new();
+    for (i, name) in &consts {
+        let value = match &stmts[*i] {
+            StarStmt::Const(x) => &x.value,
+            _ => unreachable!(),
+        };
+        let mut idents = Vec::new();
+        collect_idents(value.to_token_stream(), &mut idents);
+        for ident in idents {
+            if &ident == name {
+                continue;
+            }
+            if let Some((j, _)) = consts.iter().find(|(_, x)| x == &ident) {
+                if j > i {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        format!(
+                            "Constant `{}` refers to `{}`, which is defined later in the module",
+                            name, ident
+                        ),
+                    ));
+                }
+                referenced.push(*j);
+            }
+        }
+    }
+    for j in referenced {
+        if let StarStmt::Const(x) = &mut stmts[j] {
+            x.referenced = true;
+        }
+    }
+    Ok(())
+}
+
 fn parse_stmt(stmt: Stmt) -> syn::Result<StarStmt> {
     match stmt {
         Stmt::Item(Item::Fn(x)) => parse_fun(x),
@@ -92,6 +152,7 @@ fn parse_const(x: ItemConst) -> StarConst {
         name: x.ident,
         ty: *x.ty,
         value: *x.expr,
+        referenced: false,
     }
 }
 
@@ -99,6 +160,8 @@ struct ProcessedAttributes {
     is_attribute: bool,
     type_attribute: Option<NestedMeta>,
     speculative_exec_safe: bool,
+    /// `#[starlark(dunder = "call")]`, the method implements `__call__`.
+    dunder: Option<String>,
     /// Rest attributes
     attrs: Vec<Attribute>,
 }
@@ -107,12 +170,14 @@ struct ProcessedAttributes {
 fn process_attributes(span: Span, xs: Vec<Attribute>) -> syn::Result<ProcessedAttributes> {
     const ERROR: &str = "Couldn't parse attribute. \
         Expected `#[starlark(type(\"ty\")]`, \
-        `#[starlark(attribute)]` or `#[starlark(speculative_exec_safe)]`";
+        `#[starlark(attribute)]`, `#[starlark(speculative_exec_safe)]` \
+        or `#[starlark(dunder = \"call\")]`";
 
     let mut attrs = Vec::with_capacity(xs.len());
     let mut is_attribute = false;
     let mut type_attribute = None;
     let mut speculative_exec_safe = false;
+    let mut dunder = None;
     for x in xs {
         if x.path.is_ident("starlark") {
             match x.parse_meta()? {
@@ -139,6 +204,21 @@ fn process_attributes(span: Span, xs: Vec<Attribute>) -> syn::Result<ProcessedAt
                                     is_attribute = true;
                                 } else if meta.path().is_ident("speculative_exec_safe") {
                                     speculative_exec_safe = true;
+                                } else if meta.path().is_ident("dunder") {
+                                    match meta {
+                                        Meta::NameValue(nv) => match nv.lit {
+                                            Lit::Str(s) if s.value() == "call" => {
+                                                dunder = Some(s.value())
+                                            }
+                                            lit => {
+                                                return Err(syn::Error::new(
+                                                    lit.span(),
+                                                    "Only `dunder = \"call\"` is supported",
+                                                ));
+                                            }
+                                        },
+                                        _ => return Err(syn::Error::new(meta.span(), ERROR)),
+                                    }
                                 } else {
                                     return Err(syn::Error::new(meta.span(), ERROR));
                                 }
@@ -155,10 +235,14 @@ fn process_attributes(span: Span, xs: Vec<Attribute>) -> syn::Result<ProcessedAt
     if is_attribute && type_attribute.is_some() {
         return Err(syn::Error::new(span, "Can't be an attribute with a .type"));
     }
+    if is_attribute && dunder.is_some() {
+        return Err(syn::Error::new(span, "Can't be an attribute with a dunder"));
+    }
     Ok(ProcessedAttributes {
         is_attribute,
         type_attribute,
         speculative_exec_safe,
+        dunder,
         attrs,
     })
 }
@@ -172,6 +256,7 @@ fn parse_fun(func: ItemFn) -> syn::Result<StarStmt> {
         is_attribute,
         type_attribute,
         speculative_exec_safe,
+        dunder,
         attrs,
     } = process_attributes(func.span(), func.attrs)?;
 
@@ -224,6 +309,7 @@ fn parse_fun(func: ItemFn) -> syn::Result<StarStmt> {
             args,
             return_type: *return_type,
             speculative_exec_safe,
+            dunder,
             body: *func.block,
             source: StarFunSource::Unknown,
         }))
@@ -55,12 +55,28 @@ fn render_stmt(x: StarStmt) -> TokenStream {
 }
 
 fn render_const(x: StarConst) -> TokenStream {
-    let StarConst { name, ty, value } = x;
+    let StarConst {
+        name,
+        ty,
+        value,
+        referenced,
+    } = x;
     let span = name.span();
-    let name = ident_string(&name);
-    quote_spanned! {
-        span=>
-        globals_builder.set::<#ty>(#name, #value);
+    let name_str = ident_string(&name);
+    if referenced {
+        // Later constants use this one, so bind it to a local they can see.
+        quote_spanned! {
+            span=>
+            #[allow(non_snake_case)]
+            let #name: #ty = #value;
+            #[allow(clippy::clone_on_copy, clippy::redundant_clone)]
+            globals_builder.set::<#ty>(#name_str, #name.clone());
+        }
+    } else {
+        quote_spanned! {
+            span=>
+            globals_builder.set::<#ty>(#name_str, #value);
+        }
     }
 }
 
@@ -121,9 +137,18 @@ fn render_fun(x: StarFun) -> TokenStream {
     let span = x.span();
 
     let name_str = ident_string(&x.name);
+    let set_name_str = match &x.dunder {
+        Some(dunder) => format!("__{}__", dunder),
+        None => name_str.clone(),
+    };
     let signature = render_signature(&x);
     let binding = render_binding(&x);
     let is_method = x.is_method();
+    let set_documentation = if is_method {
+        None
+    } else {
+        Some(render_documentation(&x))
+    };
 
     let StarFun {
         name,
@@ -132,6 +157,7 @@ fn render_fun(x: StarFun) -> TokenStream {
         args: _,
         return_type,
         speculative_exec_safe,
+        dunder: _,
         body,
         source: _,
     } = x;
@@ -178,6 +204,19 @@ fn render_fun(x: StarFun) -> TokenStream {
         )
     };
 
+    // Returning a `Value` opts out of allocating the result.
+    let call_inner = if is_type_name(&return_type, "Value") {
+        quote_spanned! {span=> inner(eval, #this_arg parameters, #signature_val) }
+    } else {
+        quote_spanned! {
+            span=>
+            match inner(eval, #this_arg parameters, #signature_val) {
+                Ok(v) => Ok(eval.heap().alloc(v)),
+                Err(e) => Err(e),
+            }
+        }
+    };
+
     quote_spanned! {
         span=>
         #( #attrs )*
@@ -200,10 +239,7 @@ fn render_fun(x: StarFun) -> TokenStream {
                 #binding
                 #body
             }
-            match inner(eval, #this_arg parameters, #signature_val) {
-                Ok(v) => Ok(eval.heap().alloc(v)),
-                Err(e) => Err(e),
-            }
+            #call_inner
         }
         {
             #signature
@@ -215,11 +251,130 @@ fn render_fun(x: StarFun) -> TokenStream {
             );
             #set_type
             #set_speculative_exec_safe
-            globals_builder.set(#name_str, func);
+            #set_documentation
+            globals_builder.set(#set_name_str, func);
         }
     }
 }
 
+// Generate a statement that sets the `docs::Function` of `func`.
+fn render_documentation(x: &StarFun) -> TokenStream {
+    let span = x.span();
+    let docs = match doc_comment(&x.attrs) {
+        Some(docs) => {
+            quote_spanned! {span=> starlark::values::docs::DocString::from_docstring(#docs) }
+        }
+        None => quote_spanned! {span=> None },
+    };
+    let params = match x.source {
+        StarFunSource::Argument(_) | StarFunSource::Positional(..) => x
+            .args
+            .iter()
+            .filter(|a| !a.is_this())
+            .map(render_documentation_param)
+            .collect(),
+        // The function takes raw arguments, so accepts anything.
+        _ => vec![
+            quote_spanned! {span=> starlark::values::docs::Param::Args { name: "args".to_owned(), docs: None, typ: None } },
+            quote_spanned! {span=> starlark::values::docs::Param::Kwargs { name: "kwargs".to_owned(), docs: None, typ: None } },
+        ],
+    };
+    let ret = match return_type_name(&x.return_type) {
+        Some(t) => {
+            quote_spanned! {span=> Some(starlark::values::docs::Type { raw_type: #t.to_owned() }) }
+        }
+        None => quote_spanned! {span=> None },
+    };
+    quote_spanned! {
+        span=>
+        func.set_documentation(starlark::values::docs::Function {
+            docs: #docs,
+            params: vec![ #( #params ),* ],
+            ret: starlark::values::docs::Return { docs: None, typ: #ret },
+        });
+    }
+}
+
+fn render_documentation_param(arg: &StarArg) -> TokenStream {
+    let span = arg.span;
+    let name_str = ident_string(&arg.name);
+    let name_str = name_str.trim_matches('_');
+    if arg.is_args() {
+        return quote_spanned! {span=> starlark::values::docs::Param::Args { name: #name_str.to_owned(), docs: None, typ: None } };
+    }
+    if arg.is_kwargs() {
+        return quote_spanned! {span=> starlark::values::docs::Param::Kwargs { name: #name_str.to_owned(), docs: None, typ: None } };
+    }
+    let ty = unpack_option(&arg.ty).unwrap_or(&arg.ty);
+    let typ = if is_type_name(ty, "Value") {
+        quote_spanned! {span=> None }
+    } else {
+        quote_spanned! {
+            span=>
+            Some(starlark::values::docs::Type {
+                raw_type: {
+                    fn expected<'v>() -> String {
+                        <#ty as starlark::values::UnpackValue<'v>>::expected()
+                    }
+                    expected()
+                },
+            })
+        }
+    };
+    let default_value = if arg.is_option() {
+        quote_spanned! {span=> Some("None".to_owned()) }
+    } else if let Some(default) = &arg.default {
+        let default = default_repr(default);
+        quote_spanned! {span=> Some(#default.to_owned()) }
+    } else {
+        quote_spanned! {span=> None }
+    };
+    quote_spanned! {
+        span=>
+        starlark::values::docs::Param::Arg {
+            name: #name_str.to_owned(),
+            docs: None,
+            typ: #typ,
+            default_value: #default_value,
+        }
+    }
+}
+
+// Starlark `repr` of a default value, or `_` if it is not a literal.
+fn default_repr(x: &syn::Pat) -> String {
+    match x {
+        syn::Pat::Lit(syn::PatLit { expr, .. }) => match &**expr {
+            syn::Expr::Lit(syn::ExprLit { lit, .. }) => match lit {
+                syn::Lit::Bool(b) => (if b.value { "True" } else { "False" }).to_owned(),
+                syn::Lit::Int(i) => i.base10_digits().to_owned(),
+                syn::Lit::Float(f) => f.base10_digits().to_owned(),
+                syn::Lit::Str(s) => format!("{:?}", s.value()),
+                _ => "_".to_owned(),
+            },
+            _ => "_".to_owned(),
+        },
+        syn::Pat::Path(syn::PatPath { path, .. }) if path.is_ident("NoneType") => "None".to_owned(),
+        _ => "_".to_owned(),
+    }
+}
+
+// Starlark type of the values returned by a function, if known.
+fn return_type_name(x: &syn::Type) -> Option<&'static str> {
+    let name = match x {
+        syn::Type::Reference(r) if is_type_name(&r.elem, "str") => return Some("str"),
+        syn::Type::Path(syn::TypePath { path, .. }) => path.segments.last()?.ident.to_string(),
+        _ => return None,
+    };
+    match name.as_str() {
+        "i32" | "i64" | "u32" | "u64" | "usize" => Some("int"),
+        "f64" => Some("float"),
+        "bool" => Some("bool"),
+        "String" => Some("str"),
+        "NoneType" => Some("None"),
+        _ => None,
+    }
+}
+
 // Given __args and __signature (if render_signature was Some)
 // create bindings for all the arguments
 fn render_binding(x: &StarFun) -> TokenStream {
@@ -287,6 +442,8 @@ fn render_binding_arg(arg: &StarArg) -> TokenStream {
     let span = arg.span;
     let name = &arg.name;
     let name_str = ident_string(name);
+    // Errors name the parameter as the signature does, see `render_signature_arg`.
+    let name_str = name_str.trim_matches('_');
     let ty = &arg.ty;
 
     let source = match arg.source {
@@ -65,6 +65,9 @@ pub(crate) struct StarConst {
     pub name: Ident,
     pub ty: Type,
     pub value: Expr,
+    /// A later constant in the same module refers to this one,
+    /// so it must be bound to a local before registration.
+    pub referenced: bool,
 }
 
 impl StarConst {
@@ -84,6 +87,8 @@ pub(crate) struct StarFun {
     pub args: Vec<StarArg>,
     pub return_type: Type,
     pub speculative_exec_safe: bool,
+    /// Name of the special method this function implements, e.g. `call` for `__call__`.
+    pub dunder: Option<String>,
     pub body: Block,
     pub source: StarFunSource,
 }
@@ -240,6 +245,13 @@ impl StarFun {
             ));
         }
 
+        if self.dunder.is_some() && !self.is_method() {
+            return Err(syn::Error::new(
+                self.span(),
+                "Dunder functions must be methods with a `this` parameter",
+            ));
+        }
+
         Ok(())
     }
 }
@@ -34,6 +34,44 @@ pub(crate) fn is_type_name(x: &Type, name: &str) -> bool {
     false
 }
 
+// The `T` of `Option<T>`
+pub(crate) fn unpack_option(x: &Type) -> Option<&Type> {
+    if let Type::Path(TypePath {
+        path: Path { segments, .. },
+        ..
+    }) = x
+    {
+        let seg = segments.last()?;
+        if seg.ident == "Option" {
+            if let PathArguments::AngleBracketed(args) = &seg.arguments {
+                if let Some(GenericArgument::Type(t)) = args.args.first() {
+                    return Some(t);
+                }
+            }
+        }
+    }
+    None
+}
+
+// The text of `///` comments
+pub(crate) fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.path.is_ident("doc"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::NameValue(MetaNameValue {
+                lit: Lit::Str(s), ..
+            })) => Some(s.value()),
+            _ => None,
+        })
+        .collect();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
 pub(crate) fn ident_string(x: &Ident) -> String {
     x.to_string().trim_start_match("r#").to_owned()
 }